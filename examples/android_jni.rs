@@ -0,0 +1,165 @@
+//! C ABI for reading an eMRTD through Android's `IsoDep`, or any other platform transport.
+//!
+//! Build it as a shared library for the app, without the Proxmark3 USB driver:
+//!
+//! ```sh
+//! cargo build --release --example android_jni --no-default-features \
+//!     --target aarch64-linux-android
+//! ```
+//!
+//! The app passes a `transceive` callback wrapping `IsoDep.transceive`, for example with JNA:
+//!
+//! ```kotlin
+//! interface Icao9303 : Library {
+//!     fun interface Transceive : Callback {
+//!         fun invoke(
+//!             context: Pointer?, command: Pointer, commandLength: Long,
+//!             response: Pointer, responseCapacity: Long,
+//!         ): Long
+//!     }
+//!
+//!     fun icao9303_open(transceive: Transceive, context: Pointer?, mrz: String): Pointer?
+//!     fun icao9303_read_data_group(session: Pointer, number: Byte, output: ByteArray, capacity: Long): Long
+//!     fun icao9303_close(session: Pointer)
+//! }
+//!
+//! val isoDep = IsoDep.get(tag).apply { connect(); timeout = 5000 }
+//! val transceive = Icao9303.Transceive { _, command, commandLength, response, capacity ->
+//!     val reply = isoDep.transceive(command.getByteArray(0, commandLength.toInt()))
+//!     if (reply.size > capacity) -1 else reply.size.toLong().also { response.write(0, reply, 0, reply.size) }
+//! }
+//! ```
+//!
+//! A JNI shim calling the same functions works equally well. All calls for one session must
+//! come from the same thread, as `IsoDep` is blocking anyway this is usually a worker thread.
+
+use {
+    anyhow::{bail, ensure},
+    icao_9303_nfc::{
+        emrtd::{Emrtd, FileId},
+        nfc::CallbackReader,
+    },
+    std::{
+        ffi::{c_char, c_void, CStr},
+        ptr,
+    },
+};
+
+/// Largest response APDU with extended length, 65536 bytes of data and the status word.
+const MAX_RESPONSE: usize = 65538;
+
+/// Exchange `command` for a response written to `response`.
+///
+/// Returns the length of the response including the status word, or a negative value if
+/// the exchange failed, e.g. because the card was removed.
+pub type TransceiveFn = unsafe extern "C" fn(
+    context: *mut c_void,
+    command: *const u8,
+    command_length: usize,
+    response: *mut u8,
+    response_capacity: usize,
+) -> isize;
+
+/// The app's context pointer, passed back to every `transceive` call.
+struct Context(*mut c_void);
+
+// Safety: the caller of `icao9303_open` guarantees the context can be used from the thread
+// driving the session.
+unsafe impl Send for Context {}
+
+impl Context {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Run the Chip Access Procedure through `transceive` with the MRZ information.
+///
+/// Returns a session for [`icao9303_read_data_group`], or null on failure.
+///
+/// # Safety
+///
+/// `mrz` must be a valid NUL terminated string. `transceive` and `context` must remain valid
+/// until the session is passed to [`icao9303_close`].
+#[no_mangle]
+pub unsafe extern "C" fn icao9303_open(
+    transceive: TransceiveFn,
+    context: *mut c_void,
+    mrz: *const c_char,
+) -> *mut Emrtd {
+    let Ok(mrz) = CStr::from_ptr(mrz).to_str() else {
+        return ptr::null_mut();
+    };
+    let context = Context(context);
+    let reader = CallbackReader::new(Box::new(move |command| {
+        let mut response = vec![0; MAX_RESPONSE];
+        let length = transceive(
+            context.get(),
+            command.as_ptr(),
+            command.len(),
+            response.as_mut_ptr(),
+            response.len(),
+        );
+        let Ok(length) = usize::try_from(length) else {
+            bail!("Transceive failed with {length}.");
+        };
+        ensure!(
+            length <= response.len(),
+            "Transceive overflowed the response."
+        );
+        response.truncate(length);
+        Ok(response)
+    }));
+
+    let mut card = Emrtd::new(Box::new(reader));
+    match card.access_procedure(&mut rand::thread_rng(), mrz) {
+        Ok(_) => Box::into_raw(Box::new(card)),
+        Err(e) => {
+            eprintln!("Access procedure failed: {e:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Copy data group `number` into `output`.
+///
+/// Returns the length of the data group, 0 if it is absent, or -1 if it could not be read
+/// or does not fit in `capacity` bytes. Data groups read by the access procedure are
+/// returned without further communication.
+///
+/// # Safety
+///
+/// `session` must come from [`icao9303_open`] and `output` must be valid for `capacity`
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn icao9303_read_data_group(
+    session: *mut Emrtd,
+    number: u8,
+    output: *mut u8,
+    capacity: usize,
+) -> isize {
+    let card = &mut *session;
+    let Some(file) = FileId::data_group(number) else {
+        return -1;
+    };
+    match card.read_file_cached(file) {
+        Ok(Some(data)) if data.len() <= capacity => {
+            ptr::copy_nonoverlapping(data.as_ptr(), output, data.len());
+            data.len() as isize
+        }
+        Ok(None) => 0,
+        _ => -1,
+    }
+}
+
+/// End the session and release it. The card stays connected by the platform.
+///
+/// # Safety
+///
+/// `session` must come from [`icao9303_open`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn icao9303_close(session: *mut Emrtd) {
+    if !session.is_null() {
+        drop(Box::from_raw(session));
+    }
+}
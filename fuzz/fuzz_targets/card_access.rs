@@ -0,0 +1,13 @@
+#![no_main]
+
+use {
+    der::{Decode, Encode},
+    icao_9303_nfc::asn1::EfCardAccess,
+    libfuzzer_sys::fuzz_target,
+};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(card_access) = EfCardAccess::from_der(data) {
+        let _ = card_access.to_der();
+    }
+});
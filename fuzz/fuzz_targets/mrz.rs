@@ -0,0 +1,24 @@
+#![no_main]
+
+use {
+    icao_9303_nfc::lds::{
+        data_group::parse_dg1, parse_data_group, validate_mrz_info, MrzInput, MrzName,
+        OptionalData, ParseOptions,
+    },
+    libfuzzer_sys::fuzz_target,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_dg1(data);
+    let _ = parse_data_group(1, data, ParseOptions { strict: true });
+
+    if let Ok(mrz) = std::str::from_utf8(data) {
+        let _ = MrzInput::from_mrz(mrz);
+        let _ = OptionalData::from_mrz(mrz);
+        let _ = MrzName::from_mrz(mrz);
+        let _ = validate_mrz_info(mrz);
+        if let Some((line1, line2)) = mrz.split_once('\n') {
+            let _ = MrzInput::from_td1(line1, line2);
+        }
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use {
+    der::Decode,
+    icao_9303_nfc::{asn1::EfSod, passive_authentication},
+    libfuzzer_sys::fuzz_target,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(sod) = EfSod::from_der(data) else {
+        return;
+    };
+    let _ = sod.lds_security_object();
+    let _ = sod.document_signer_certificate();
+    let _ = sod.document_hash();
+    let _ = passive_authentication::signature_input(&sod);
+    let _ = passive_authentication::check_signed_attributes(&sod);
+});
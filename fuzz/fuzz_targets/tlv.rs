@@ -0,0 +1,22 @@
+#![no_main]
+
+use {
+    icao_9303_nfc::lds::tlv::{parse_tlv, TlvIter},
+    libfuzzer_sys::fuzz_target,
+};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((tlv, rest)) = parse_tlv(data) {
+        assert!(tlv.value.len() + rest.len() <= data.len());
+    }
+
+    // Recursively walk all constructed objects.
+    fn walk(iter: TlvIter) {
+        for tlv in iter.flatten() {
+            if tlv.is_constructed() {
+                walk(tlv.children());
+            }
+        }
+    }
+    walk(TlvIter::new(data));
+});
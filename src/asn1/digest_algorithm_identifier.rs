@@ -86,6 +86,18 @@ impl DigestAlgorithmIdentifier {
         }
     }
 
+    /// Length of the hash in bytes, `None` for unknown algorithms.
+    pub fn output_size(&self) -> Option<usize> {
+        Some(match self {
+            Self::Sha1(_) => 20,
+            Self::Sha224(_) | Self::Sha512_224(_) => 28,
+            Self::Sha256(_) | Self::Sha512_256(_) => 32,
+            Self::Sha384(_) => 48,
+            Self::Sha512(_) => 64,
+            Self::Unknown(_) => return None,
+        })
+    }
+
     pub fn hash_bytes(&self, data: &[u8]) -> Vec<u8> {
         match self {
             Self::Sha1(_) => hash::<sha1::Sha1>(data),
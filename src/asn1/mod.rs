@@ -16,23 +16,32 @@ mod digest_algorithm_identifier;
 mod ordered_set;
 pub mod public_key;
 pub mod security_info;
+#[cfg(feature = "passive-auth")]
+mod sod;
 
+#[cfg(feature = "passive-auth")]
+pub use self::sod::EfSod;
 pub use self::{
     application_tagged::ApplicationTagged,
     content_info::{ContentInfo, ContentType},
-    digest_algorithm_identifier::DigestAlgorithmIdentifier,
+    digest_algorithm_identifier::{
+        DigestAlgorithmIdentifier, ID_SHA1, ID_SHA224, ID_SHA256, ID_SHA384, ID_SHA512,
+        ID_SHA512_224, ID_SHA512_256,
+    },
+    ordered_set::OrderedSet,
 };
 use {
-    self::security_info::{
-        ChipAuthenticationInfo, ChipAuthenticationPublicKeyInfo, SecurityInfo, SecurityInfos,
+    self::{
+        public_key::SubjectPublicKeyInfo,
+        security_info::{
+            ChipAuthenticationInfo, ChipAuthenticationPublicKeyInfo, SecurityInfo, SecurityInfos,
+        },
     },
-    crate::ensure_err,
-    cms::signed_data::{EncapsulatedContentInfo, SignedData, SignerInfo},
     der::{
         asn1::{ObjectIdentifier as Oid, OctetString, PrintableString},
-        Any, Decode, Error, ErrorKind, Length, Result, Sequence, Tag, ValueOrd,
+        Any, Sequence, ValueOrd,
     },
-    security_info::{ChipAuthenticationProtocol, KeyAgreement, SymmetricCipher},
+    security_info::{ChipAuthenticationProtocol, SymmetricCipher},
 };
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Sequence, ValueOrd)]
@@ -41,10 +50,6 @@ pub struct AnyAlgorithmIdentifier {
     pub parameters: Option<Any>,
 }
 
-impl ContentType for SignedData {
-    const CONTENT_TYPE: Oid = Oid::new_unwrap("1.2.840.113549.1.7.2");
-}
-
 /// EF_CardAccess is a [`SecurityInfos`] with no further wrapping.
 ///
 /// See ICAO-9303-10 3.11.3
@@ -55,11 +60,10 @@ pub type EfCardAccess = SecurityInfos;
 /// See ICAO-9303-10 3.11.4
 pub type EfDg14 = ApplicationTagged<14, SecurityInfos>;
 
-/// EF_SOD is a wrapped [`SignedData`] structure.
+/// EF_DG15 is the Active Authentication public key.
 ///
-/// See ICAO-9303-10 4.7.14. The 0x6E tag is an ASN1 Application
-/// constructed application tag with the value 14.
-pub type EfSod = ApplicationTagged<23, ContentInfo<SignedData>>;
+/// See ICAO-9303-10 4.7.15
+pub type EfDg15 = ApplicationTagged<15, SubjectPublicKeyInfo>;
 
 /// ICAO-9303-10 4.6.2.3
 #[derive(Clone, Debug, PartialEq, Eq, Sequence)]
@@ -70,6 +74,15 @@ pub struct LdsSecurityObject {
     pub lds_version_info: Option<LdsVersionInfo>,
 }
 
+/// Document types a Document Signer is allowed to sign.
+///
+/// ICAO-9303-12 7.1.1.3
+#[derive(Clone, Debug, PartialEq, Eq, Sequence)]
+pub struct DocumentTypeListSyntax {
+    pub version: u64,
+    pub doc_type_list: OrderedSet<PrintableString>,
+}
+
 /// ICAO-9303-10 4.6.2.3
 #[derive(Clone, Debug, PartialEq, Eq, Sequence)]
 pub struct LdsVersionInfo {
@@ -84,112 +97,179 @@ pub struct DataGroupHash {
     pub hash_value: OctetString,
 }
 
+impl DocumentTypeListSyntax {
+    /// ICAO-9303-12 7.1.1.3
+    pub const EXTENSION_ID: Oid = Oid::new_unwrap("2.23.136.1.1.6.2");
+}
+
 impl ContentType for LdsSecurityObject {
     /// ICAO-9303-10 4.6.2.3
     const CONTENT_TYPE: Oid = Oid::new_unwrap("2.23.136.1.1.1");
 }
 
 impl EfDg14 {
-    pub fn chip_authentication(
+    /// Chip Authentication public keys, each paired with the protocol info of the same key id.
+    ///
+    /// The key id is optional if there is only a single key. Some passports only have a
+    /// ChipAuthenticationPublicKey, in which case version 1 with 3DES-CBC-CBC is assumed for
+    /// all keys. Otherwise keys without a matching ChipAuthenticationInfo are paired with
+    /// `None`, they can not be used.
+    pub fn chip_authentication_keys(
         &self,
-    ) -> Option<(&ChipAuthenticationInfo, &ChipAuthenticationPublicKeyInfo)> {
-        // For now, we take the first ChipAuthentication and ChipAuthenticationPublicKey.
-        let ca = self
+    ) -> Vec<(
+        Option<ChipAuthenticationInfo>,
+        &ChipAuthenticationPublicKeyInfo,
+    )> {
+        let infos: Vec<&ChipAuthenticationInfo> = self
             .0
             .iter()
-            .find_map(|si| match si {
+            .filter_map(|si| match si {
                 SecurityInfo::ChipAuthentication(ca) => Some(ca),
                 _ => None,
             })
-            .unwrap_or(
-                // Some passports only have ChipAuthenticationPublicKey. In this case we assume that
-                // the Cipher is the 3DES-CBC-CBC.
-                &ChipAuthenticationInfo {
-                    protocol: ChipAuthenticationProtocol {
-                        key_agreement: KeyAgreement::Ecdh, // TODO: From pubkey
-                        cipher: Some(SymmetricCipher::Tdes),
-                    },
-                    version: 1,
-                    key_id: None,
-                },
-            );
-        // Do some verification checks
-        if ca.protocol.cipher.is_none() || ca.version != 1 {
-            // TODO: Error message
-            return None;
-        }
-        // Find the corresponding ChipAuthenticationPublicKey based on key id (could both be None)
-        let capk = self.0.iter().find_map(|si| match si {
-            SecurityInfo::ChipAuthenticationPublicKey(capk) if capk.key_id == ca.key_id => {
-                Some(capk)
-            }
-            _ => None,
-        })?;
-        Some((ca, capk))
+            .collect();
+        self.0
+            .iter()
+            .filter_map(|si| match si {
+                SecurityInfo::ChipAuthenticationPublicKey(capk) => Some(capk),
+                _ => None,
+            })
+            .map(|capk| {
+                let ca = if infos.is_empty() {
+                    Some(ChipAuthenticationInfo {
+                        protocol: ChipAuthenticationProtocol {
+                            key_agreement: capk.protocol,
+                            cipher: Some(SymmetricCipher::Tdes),
+                        },
+                        version: 1,
+                        key_id: capk.key_id,
+                    })
+                } else {
+                    infos
+                        .iter()
+                        .find(|ca| ca.key_id == capk.key_id)
+                        .map(|&&ca| ca)
+                };
+                (ca, capk)
+            })
+            .collect()
     }
-}
 
-impl EfSod {
-    pub fn signed_data(&self) -> &SignedData {
-        &self.0 .0
+    /// The first usable Chip Authentication key and its protocol, see
+    /// [`Self::chip_authentication_keys`].
+    ///
+    /// A key is usable if its info has a cipher, version 1 and the same key agreement.
+    pub fn chip_authentication(
+        &self,
+    ) -> Option<(ChipAuthenticationInfo, &ChipAuthenticationPublicKeyInfo)> {
+        self.chip_authentication_keys()
+            .into_iter()
+            .find_map(|(ca, capk)| {
+                let ca = ca?;
+                let usable = ca.protocol.cipher.is_some()
+                    && ca.version == 1
+                    && ca.protocol.key_agreement == capk.protocol;
+                usable.then_some((ca, capk))
+            })
     }
+}
 
-    pub fn signer_info(&self) -> &SignerInfo {
-        // TODO: Handle errors
-        self.signed_data()
-            .signer_infos
-            .0
-            .as_slice()
-            .first()
-            .expect("missing signer info")
+impl LdsSecurityObject {
+    pub fn hash_for_dg(&self, dg_number: usize) -> Option<&[u8]> {
+        for entry in &self.data_group_hash_values {
+            if entry.data_group_number == dg_number as u64 {
+                return Some(entry.hash_value.as_bytes());
+            }
+        }
+        None
     }
+}
 
-    pub fn signature(&self) -> &[u8] {
-        self.signer_info().signature.as_bytes()
-    }
+#[cfg(test)]
+mod tests {
+    use {
+        super::{security_info::KeyAgreement, *},
+        der::{Decode, Encode},
+        hex_literal::hex,
+    };
 
-    /// Returns the Blake3 hash of the document signature
-    pub fn document_hash(&self) -> [u8; 32] {
-        *blake3::hash(self.signature()).as_bytes()
+    fn ca_info(cipher: SymmetricCipher, key_id: u64) -> SecurityInfo {
+        SecurityInfo::ChipAuthentication(ChipAuthenticationInfo {
+            protocol: ChipAuthenticationProtocol {
+                key_agreement: KeyAgreement::Ecdh,
+                cipher: Some(cipher),
+            },
+            version: 1,
+            key_id: Some(key_id),
+        })
     }
 
-    pub fn encapsulated_content(&self) -> &EncapsulatedContentInfo {
-        &self.signed_data().encap_content_info
+    fn ca_public_key(key_id: Option<u64>) -> SecurityInfo {
+        let mut spki =
+            hex!("3059 3013 0607 2A8648CE3D0201 0608 2A8648CE3D030107 0342 0004").to_vec();
+        spki.extend_from_slice(&[0x42; 64]);
+        SecurityInfo::ChipAuthenticationPublicKey(ChipAuthenticationPublicKeyInfo {
+            protocol: KeyAgreement::Ecdh,
+            public_key: SubjectPublicKeyInfo::from_der(&spki).unwrap(),
+            key_id,
+        })
     }
 
-    pub fn lds_security_object(&self) -> Result<LdsSecurityObject> {
-        let econ = self.encapsulated_content();
-        ensure_err!(
-            econ.econtent_type == LdsSecurityObject::CONTENT_TYPE,
-            Error::new(
-                ErrorKind::OidUnknown {
-                    oid: econ.econtent_type
-                },
-                Length::ZERO,
-            )
+    #[test]
+    fn test_chip_authentication_key_ids() {
+        // Two keys with their infos in a different order, and a key without info.
+        let dg14 = ApplicationTagged::<14, _>(OrderedSet(vec![
+            ca_public_key(Some(1)),
+            ca_info(SymmetricCipher::Aes128, 2),
+            ca_public_key(Some(2)),
+            ca_info(SymmetricCipher::Tdes, 1),
+            ca_public_key(Some(3)),
+        ]))
+        .to_der()
+        .unwrap();
+        let dg14 = EfDg14::from_der(&dg14).unwrap();
+        let keys = dg14.chip_authentication_keys();
+        let ciphers: Vec<_> = keys
+            .iter()
+            .map(|(ca, pk)| (pk.key_id, ca.and_then(|ca| ca.protocol.cipher)))
+            .collect();
+        assert_eq!(
+            ciphers,
+            [
+                (Some(1), Some(SymmetricCipher::Tdes)),
+                (Some(2), Some(SymmetricCipher::Aes128)),
+                (Some(3), None),
+            ]
         );
-        let octet_string = econ
-            .econtent
-            .as_ref()
-            .ok_or(Error::new(
-                ErrorKind::TagUnexpected {
-                    expected: Some(Tag::OctetString),
-                    actual: Tag::Null, // Actually None
-                },
-                Length::ZERO,
-            ))?
-            .decode_as::<OctetString>()?;
-        LdsSecurityObject::from_der(octet_string.as_bytes())
+        let (ca, pk) = dg14.chip_authentication().unwrap();
+        assert_eq!((ca.key_id, pk.key_id), (Some(1), Some(1)));
+
+        // Without any info, 3DES is assumed.
+        let dg14 = ApplicationTagged::<14, _>(OrderedSet(vec![ca_public_key(None)]));
+        let (ca, _) = dg14.chip_authentication().unwrap();
+        assert_eq!(ca.protocol.cipher, Some(SymmetricCipher::Tdes));
+
+        // Only unmatched keys.
+        let dg14 = ApplicationTagged::<14, _>(OrderedSet(vec![
+            ca_info(SymmetricCipher::Tdes, 1),
+            ca_public_key(Some(2)),
+        ]));
+        assert!(dg14.chip_authentication().is_none());
     }
-}
 
-impl LdsSecurityObject {
-    pub fn hash_for_dg(&self, dg_number: usize) -> Option<&[u8]> {
-        for entry in &self.data_group_hash_values {
-            if entry.data_group_number == dg_number as u64 {
-                return Some(entry.hash_value.as_bytes());
-            }
-        }
-        None
+    #[test]
+    fn test_unknown_security_info() {
+        let unknown = hex!("3008 06032A0304 020105");
+        let mut der = hex!("311E").to_vec();
+        der.extend_from_slice(&unknown);
+        der.extend_from_slice(&hex!("3012 060A04007F00070202040202 020102 02010C"));
+        let infos = SecurityInfos::from_der(&der).unwrap();
+        assert!(matches!(infos.0[1], SecurityInfo::Pace(_)));
+        let SecurityInfo::Unknown(info) = &infos.0[0] else {
+            panic!("expected an unknown SecurityInfo");
+        };
+        assert_eq!(info.protocol, Oid::new_unwrap("1.2.3.4"));
+        assert_eq!(infos.0[0].to_der().unwrap(), unknown);
+        assert_eq!(infos.to_der().unwrap(), der);
     }
 }
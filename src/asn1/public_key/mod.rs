@@ -13,12 +13,25 @@ use der::{
 // elliptic curve 1
 pub const ID_EC_PUBLIC_KEY: Oid = Oid::new_unwrap("1.2.840.10045.2.1");
 
+// PKCS #1 1.2.840.113549.1.1
+// rsaEncryption 1
+pub const ID_RSA_ENCRYPTION: Oid = Oid::new_unwrap("1.2.840.113549.1.1.1");
+
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Sequence, ValueOrd)]
 pub struct SubjectPublicKeyInfo {
     pub algorithm: PubkeyAlgorithmIdentifier,
     pub subject_public_key: BitString,
 }
 
+/// RSA public key, the content of the `subject_public_key` bit string.
+///
+/// RFC 8017 appendix A.1.1
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Sequence, ValueOrd)]
+pub struct RsaPublicKey {
+    pub modulus: Int,
+    pub public_exponent: Int,
+}
+
 /// Elliptic Curve Algorithm Parameters.
 ///
 /// **Note**: This deviates from RFC 5480 by allowing for explicit
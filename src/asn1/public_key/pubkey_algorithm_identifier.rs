@@ -44,7 +44,7 @@ impl EncodeValue for PubkeyAlgorithmIdentifier {
                 ID_EC.encode(writer)?;
                 params.encode(writer)
             }
-            Self::Unknown(any) => any.encode(writer),
+            Self::Unknown(any) => any.encode_value(writer),
         }
     }
 }
@@ -2,7 +2,7 @@ mod chip_authentication_info;
 mod pace_info;
 
 pub use {
-    self::pace_info::{PaceInfo, PaceProtocol},
+    self::pace_info::{PaceDomainParameterInfo, PaceInfo, PaceProtocol},
     chip_authentication_info::{
         ChipAuthenticationInfo, ChipAuthenticationProtocol, ChipAuthenticationPublicKeyInfo,
     },
@@ -15,7 +15,6 @@ use {
         Any, Decode, DecodeValue, Encode, EncodeValue, Error, ErrorKind, FixedTag, Header, Length,
         Reader, Result, Sequence, Tag, ValueOrd, Writer,
     },
-    pace_info::PaceDomainParameterInfo,
     std::{
         cmp::Ordering,
         fmt::{self, Display, Formatter},
@@ -44,7 +43,9 @@ pub enum SecurityInfo {
     ActiveAutentication(ActiveAuthenticationInfo),
     TerminalAuthentication(TerminalAuthenticationInfo),
     EfDir(EfDirInfo),
-    Unknow(AnySecurityInfo),
+
+    /// A protocol this library does not know, kept as is so it re-encodes to the same bytes.
+    Unknown(AnySecurityInfo),
 }
 
 /// A [`SecurityInfo`] object from ICAO-9303-11 9.2.
@@ -99,7 +100,7 @@ impl SecurityInfo {
             Self::ActiveAutentication(info) => info.protocol,
             Self::TerminalAuthentication(info) => info.protocol,
             Self::EfDir(info) => info.protocol,
-            Self::Unknow(info) => info.protocol,
+            Self::Unknown(info) => info.protocol,
         }
     }
 
@@ -112,7 +113,7 @@ impl SecurityInfo {
             Self::ActiveAutentication(_info) => "AA".to_string(),
             Self::TerminalAuthentication(_info) => "TA".to_string(),
             Self::EfDir(_info) => "EF_DIR".to_string(),
-            Self::Unknow(info) => info.protocol.to_string(),
+            Self::Unknown(info) => info.protocol.to_string(),
         }
     }
 }
@@ -139,7 +140,7 @@ impl EncodeValue for SecurityInfo {
             Self::ActiveAutentication(info) => info.value_len(),
             Self::TerminalAuthentication(info) => info.value_len(),
             Self::EfDir(info) => info.value_len(),
-            Self::Unknow(info) => info.value_len(),
+            Self::Unknown(info) => info.value_len(),
         }
     }
 
@@ -152,7 +153,7 @@ impl EncodeValue for SecurityInfo {
             Self::ActiveAutentication(info) => info.encode_value(writer),
             Self::TerminalAuthentication(info) => info.encode_value(writer),
             Self::EfDir(info) => info.encode_value(writer),
-            Self::Unknow(info) => info.encode_value(writer),
+            Self::Unknown(info) => info.encode_value(writer),
         }
     }
 }
@@ -199,7 +200,7 @@ impl<'a> DecodeValue<'a> for SecurityInfo {
                 .map_err(offset_err)
                 .map(Self::EfDir)
         } else {
-            Ok(Self::Unknow(any))
+            Ok(Self::Unknown(any))
         }
     }
 }
@@ -0,0 +1,95 @@
+//! EF.SOD, the Document Security Object.
+//!
+//! Only available with the `passive-auth` feature, as it requires CMS and X.509 parsing.
+
+use {
+    super::{ApplicationTagged, ContentInfo, ContentType, LdsSecurityObject},
+    crate::ensure_err,
+    cms::{
+        cert::CertificateChoices,
+        signed_data::{EncapsulatedContentInfo, SignedData, SignerInfo},
+    },
+    der::{
+        asn1::{ObjectIdentifier as Oid, OctetString},
+        Decode, Error, ErrorKind, Length, Result, Tag,
+    },
+    x509_cert::Certificate,
+};
+
+/// EF_SOD is a wrapped [`SignedData`] structure.
+///
+/// See ICAO-9303-10 4.7.14. The 0x6E tag is an ASN1 Application
+/// constructed application tag with the value 14.
+pub type EfSod = ApplicationTagged<23, ContentInfo<SignedData>>;
+
+impl ContentType for SignedData {
+    const CONTENT_TYPE: Oid = Oid::new_unwrap("1.2.840.113549.1.7.2");
+}
+
+impl EfSod {
+    pub fn signed_data(&self) -> &SignedData {
+        &self.0 .0
+    }
+
+    /// The first signer info, which should be the Document Signer's.
+    ///
+    /// The `SET OF SignerInfo` may be empty, so this is not guaranteed to exist.
+    pub fn signer_info(&self) -> Option<&SignerInfo> {
+        self.signed_data().signer_infos.0.as_slice().first()
+    }
+
+    /// The first X.509 certificate in the SOD, which should be the Document Signer.
+    pub fn document_signer_certificate(&self) -> Option<&Certificate> {
+        self.signed_data()
+            .certificates
+            .as_ref()?
+            .0
+            .iter()
+            .find_map(|cert| match cert {
+                CertificateChoices::Certificate(cert) => Some(cert),
+                _ => None,
+            })
+    }
+
+    pub fn signature(&self) -> Option<&[u8]> {
+        Some(self.signer_info()?.signature.as_bytes())
+    }
+
+    /// Returns the Blake3 hash of the document signature
+    pub fn document_hash(&self) -> Option<[u8; 32]> {
+        Some(*blake3::hash(self.signature()?).as_bytes())
+    }
+
+    pub fn encapsulated_content(&self) -> &EncapsulatedContentInfo {
+        &self.signed_data().encap_content_info
+    }
+
+    /// The encapsulated content bytes, i.e. the encoded [`LdsSecurityObject`].
+    pub fn econtent(&self) -> Result<OctetString> {
+        self.encapsulated_content()
+            .econtent
+            .as_ref()
+            .ok_or(Error::new(
+                ErrorKind::TagUnexpected {
+                    expected: Some(Tag::OctetString),
+                    actual: Tag::Null, // Actually None
+                },
+                Length::ZERO,
+            ))?
+            .decode_as::<OctetString>()
+    }
+
+    pub fn lds_security_object(&self) -> Result<LdsSecurityObject> {
+        let econ = self.encapsulated_content();
+        ensure_err!(
+            econ.econtent_type == LdsSecurityObject::CONTENT_TYPE,
+            Error::new(
+                ErrorKind::OidUnknown {
+                    oid: econ.econtent_type
+                },
+                Length::ZERO,
+            )
+        );
+        LdsSecurityObject::from_der(self.econtent()?.as_bytes())
+    }
+}
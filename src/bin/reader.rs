@@ -1,46 +1,370 @@
 #![allow(dead_code)]
 
 use {
-    anyhow::{anyhow, Context, Result},
+    anyhow::{anyhow, bail, ensure, Context, Result},
+    argh::FromArgs,
     icao_9303_nfc::{
-        asn1::EfSod,
-        emrtd::{Emrtd, Error, FileId},
+        asn1::{security_info::SymmetricCipher, EfCardAccess},
+        emrtd::{
+            pace_key,
+            secure_messaging::{derive_key, KDF_ENC, KDF_MAC},
+            seed_from_mrz, AccessReport, Document, Emrtd, Error, FileId, PartialRead,
+        },
         ensure_err,
         iso7816::StatusWord,
-        nfc::connect_reader,
+        lds::{face::parse_dg2, validate_mrz_info, FaceImageFormat, MrzInput},
+        nfc::{
+            connect_reader, connect_reader_by_name, list_readers, transcript::Recorder, NfcReader,
+            Protocol,
+        },
+        passive_authentication::TrustStore,
+    },
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        str::FromStr,
     },
-    std::env,
 };
 
 // https://github.com/RfidResearchGroup/proxmark3/issues/1117
 
+/// Read an ICAO 9303 eMRTD over NFC.
+#[derive(FromArgs)]
+struct Args {
+    /// NFC reader backend, currently only `proxmark3`
+    #[argh(option, default = "Backend::Proxmark3")]
+    backend: Backend,
+
+    /// name of the reader to use, the first one found if omitted
+    #[argh(option)]
+    reader: Option<String>,
+
+    /// transmission protocol, `t0` or `t1`, detected by the reader if omitted
+    #[argh(option)]
+    protocol: Option<Protocol>,
+
+    /// record all APDU exchanges to a transcript file
+    #[argh(option)]
+    transcript: Option<PathBuf>,
+
+    /// print all APDUs sent to the card
+    #[argh(switch)]
+    trace: bool,
+
+    /// MRZ information: document number, date of birth and date of expiry with check digits
+    #[argh(option)]
+    mrz: Option<String>,
+
+    /// document number, instead of --mrz
+    #[argh(option)]
+    document_number: Option<String>,
+
+    /// date of birth as YYMMDD, instead of --mrz
+    #[argh(option)]
+    date_of_birth: Option<String>,
+
+    /// date of expiry as YYMMDD, instead of --mrz
+    #[argh(option)]
+    date_of_expiry: Option<String>,
+
+    /// card access number, requires PACE
+    #[argh(option)]
+    can: Option<String>,
+
+    /// PIN, requires PACE
+    #[argh(option)]
+    pin: Option<String>,
+
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Dump(DumpArgs),
+    Verify(VerifyArgs),
+    Info(InfoArgs),
+    Keys(KeysArgs),
+}
+
+/// Read all files and write them to a directory.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "dump")]
+struct DumpArgs {
+    /// output directory, created if missing
+    #[argh(option, default = "PathBuf::from(\".\")")]
+    output: PathBuf,
+
+    /// what to write: `raw` files, `extracted` MRZ and face images, or `both`
+    #[argh(option, default = "DumpFormat::Raw")]
+    format: DumpFormat,
+}
+
+/// Perform Passive Authentication of the data groups.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "verify")]
+struct VerifyArgs {
+    /// DER encoded CSCA certificate to trust, can be repeated
+    #[argh(option)]
+    csca: Vec<PathBuf>,
+}
+
+/// Show EF.CardAccess and, with access keys, EF.COM.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "info")]
+struct InfoArgs {}
+
+/// Derive the BAC and PACE keys from the MRZ, without a reader.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "keys")]
+struct KeysArgs {}
+
+#[derive(Clone, Copy, Debug)]
+enum Backend {
+    Proxmark3,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "proxmark3" => Ok(Self::Proxmark3),
+            _ => Err(format!("Unknown backend {s}")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DumpFormat {
+    Raw,
+    Extracted,
+    Both,
+}
+
+impl DumpFormat {
+    fn raw(self) -> bool {
+        matches!(self, Self::Raw | Self::Both)
+    }
+
+    fn extracted(self) -> bool {
+        matches!(self, Self::Extracted | Self::Both)
+    }
+}
+
+impl FromStr for DumpFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Self::Raw),
+            "extracted" => Ok(Self::Extracted),
+            "both" => Ok(Self::Both),
+            _ => Err(format!(
+                "Unknown format {s}, expected raw, extracted or both"
+            )),
+        }
+    }
+}
+
+/// How to obtain access to the chip.
+enum Access {
+    None,
+    Mrz(String),
+    Can(String),
+    Pin(String),
+}
+
+impl Args {
+    fn access(&self) -> Result<Access> {
+        if let Some(mrz) = &self.mrz {
+            return Ok(Access::Mrz(mrz.clone()));
+        }
+        if let (Some(number), Some(birth), Some(expiry)) = (
+            &self.document_number,
+            &self.date_of_birth,
+            &self.date_of_expiry,
+        ) {
+            let mrz = MrzInput::new(number, birth, expiry)?;
+            return Ok(Access::Mrz(mrz.mrz_information()));
+        }
+        if let Some(can) = &self.can {
+            return Ok(Access::Can(can.clone()));
+        }
+        if let Some(pin) = &self.pin {
+            return Ok(Access::Pin(pin.clone()));
+        }
+        Ok(Access::None)
+    }
+}
+
 fn main() -> Result<()> {
-    let mut rng = rand::thread_rng();
+    let args: Args = argh::from_env();
+    let access = args.access()?;
+    if let Command::Keys(_) = args.command {
+        return keys(&access);
+    }
+
+    // Find and open the reader.
+    let mut nfc: Box<dyn NfcReader> = match args.backend {
+        Backend::Proxmark3 => match &args.reader {
+            Some(name) => connect_reader_by_name(name).with_context(|| {
+                format!(
+                    "Available readers: {:?}",
+                    list_readers().unwrap_or_default()
+                )
+            })?,
+            None => connect_reader()?,
+        },
+    };
 
-    // Find and open the Proxmark3 device
-    let mut nfc = connect_reader()?;
+    // Record all exchanges if requested, for replay in tests.
+    if let Some(path) = &args.transcript {
+        nfc = Box::new(Recorder::new(nfc, Box::new(fs::File::create(path)?)));
+    }
 
     // Connect to ISO 14443-A card as reader, keeping the field on.
-    let card = nfc.connect()?;
+    let card = nfc.connect_with(args.protocol)?;
     ensure_err!(card.is_some(), anyhow!("No card found."));
-    dbg!(&card);
+    eprintln!("Card: {:?}", card.unwrap());
 
     let mut card = Emrtd::new(nfc);
+    card.set_trace(args.trace);
+
+    match args.command {
+        Command::Info(_) => info(&mut card, &access),
+        Command::Dump(dump_args) => {
+            let document = read_document(&mut card, &access)?;
+            dump(&mut card, &dump_args, document.as_ref())
+        }
+        Command::Verify(verify_args) => {
+            read_document(&mut card, &access)?;
+            verify(&mut card, &verify_args)
+        }
+        Command::Keys(_) => unreachable!(),
+    }
+}
+
+fn authenticate(card: &mut Emrtd, access: &Access) -> Result<()> {
+    let mut rng = rand::thread_rng();
+    match access {
+        Access::None => bail!("Access keys required, use --mrz or --document-number."),
+        Access::Mrz(mrz) => {
+            // Chips without EF.CardAccess only support BAC.
+            // TODO: Use PACE when supported.
+            if card
+                .pace_supported()
+                .context("Error reading EF.CardAccess.")?
+            {
+                eprintln!("PACE supported, using Basic Access Control.");
+            }
+            card.basic_access_control(&mut rng, mrz)
+                .context("Error during Basic Access Control.")?;
+            eprintln!("Basic Access Control successful.");
+        }
+        Access::Can(_) | Access::Pin(_) => bail!("PACE is not supported yet."),
+    }
+    Ok(())
+}
+
+/// Establish a session and read the LDS1 files, see [`Emrtd::read_document`].
+///
+/// If a data group fails to read, the files read before it remain in the file cache and
+/// `None` is returned, e.g. when the card slipped during DG2.
+fn read_document(card: &mut Emrtd, access: &Access) -> Result<Option<Document>> {
+    let mut rng = rand::thread_rng();
+    let (document, report) = match access {
+        Access::None => bail!("Access keys required, use --mrz or --document-number."),
+        Access::Mrz(mrz) => match card.read_document(&mut rng, mrz) {
+            Ok(document) => {
+                let report = document.report.clone();
+                (Some(document), report)
+            }
+            Err(e) => {
+                let partial = e.downcast::<PartialRead>()?;
+                eprintln!("Warning: {partial} {}", partial.source);
+                (None, partial.report)
+            }
+        },
+        Access::Can(_) | Access::Pin(_) => bail!("PACE is not supported yet."),
+    };
+    log_report(&report);
+    Ok(document)
+}
 
-    // println!("=== Basic Access Control.");
-    let mrz = env::var("MRZ")?;
-    card.basic_access_control(&mut rng, &mrz)
-        .context("Error during Basic Access Control.")?;
+fn log_report(report: &AccessReport) {
+    if report.pace_supported {
+        eprintln!("PACE supported, using Basic Access Control.");
+    }
     eprintln!("Basic Access Control successful.");
+    if !report.eac_required.is_empty() {
+        eprintln!(
+            "Extended Access Control required for DG{:?}.",
+            report.eac_required
+        );
+    }
+}
 
-    // let ef_sod = card.read_cached::<EfSod>()?;
-    // println!("DOCUMENT HASH = 0x{}", hex::encode(ef_sod.document_hash()));
+fn keys(access: &Access) -> Result<()> {
+    let Access::Mrz(mrz) = access else {
+        bail!("MRZ required, use --mrz or --document-number.");
+    };
+    validate_mrz_info(mrz)?;
+    let seed = seed_from_mrz(mrz);
+    println!("MRZ information: {mrz}");
+    println!("K_seed: {}", hex::encode(seed));
+    println!(
+        "K_enc: {}",
+        hex::encode(derive_key(SymmetricCipher::Tdes, &seed, KDF_ENC))
+    );
+    println!(
+        "K_mac: {}",
+        hex::encode(derive_key(SymmetricCipher::Tdes, &seed, KDF_MAC))
+    );
+    for cipher in [
+        SymmetricCipher::Tdes,
+        SymmetricCipher::Aes128,
+        SymmetricCipher::Aes192,
+        SymmetricCipher::Aes256,
+    ] {
+        println!("K_pi {cipher}: {}", hex::encode(pace_key(cipher, mrz)));
+    }
+    Ok(())
+}
 
-    // Should be secured now!
-    // Let's read some files.
+fn info(card: &mut Emrtd, access: &Access) -> Result<()> {
+    match card.read_cached::<EfCardAccess>() {
+        Ok(card_access) => {
+            println!("{}:", FileId::CardAccess);
+            for info in card_access.iter() {
+                println!(" - {:?}", info);
+            }
+        }
+        Err(Error::FileNotFound) => println!("{}: Not Found", FileId::CardAccess),
+        Err(e) => return Err(e.into()),
+    }
+    if matches!(access, Access::None) {
+        return Ok(());
+    }
+    authenticate(card, access)?;
+    let com = card
+        .read_file_cached(FileId::Com)?
+        .ok_or(Error::FileNotFound)?;
+    println!("{}: {}", FileId::Com, hex::encode(com));
+    Ok(())
+}
+
+fn dump(card: &mut Emrtd, args: &DumpArgs, document: Option<&Document>) -> Result<()> {
+    fs::create_dir_all(&args.output)?;
     for file_id in FileId::iter() {
         match card.read_file_cached(file_id) {
-            Ok(Some(data)) => println!("{}: {}", file_id, hex::encode(data)),
+            Ok(Some(data)) if args.format.raw() => {
+                let path = args.output.join(format!("{}.bin", file_name(file_id)));
+                fs::write(&path, data)?;
+                println!("{}: {}", file_id, path.display());
+            }
+            Ok(Some(_)) => {}
             Ok(None) => println!("{}: Not Found", file_id),
             Err(Error::ErrorResponse(StatusWord::ACCESS_DENIED)) => {
                 println!("{}: Access Denied", file_id)
@@ -48,23 +372,70 @@ fn main() -> Result<()> {
             Err(e) => eprintln!("{}: {}", file_id, e),
         }
     }
+    if args.format.extracted() {
+        match document.map(|document| dump_extracted(document, &args.output)) {
+            Some(Ok(())) => {}
+            Some(Err(e)) => eprintln!("Extracting data groups: {e:#}"),
+            None => eprintln!("Extracting data groups: document incomplete"),
+        }
+    }
+    match card.integrity_check() {
+        Ok(results) => {
+            for (number, integrity) in results {
+                println!("DG{number}: {integrity:?}");
+            }
+        }
+        Err(e) => eprintln!("Integrity check: {e}"),
+    }
+    Ok(())
+}
 
-    // TODO: Verify SOD.
-    // https://github.com/worldcoin/nfc-uniqueness-service/blob/d907d9ef33826034665592685c1e24d25bdb1259/src/routes/v1/mod.rs#L102
-
-    // Active Authentication with fixed nonce
-    // // ICAO 9303-11 section 6.1
-    // eprintln!("=== Active Authentication");
-    // let (_status, data) = card.send_apdu(&hex!("00 88 0000  08  00 01 02 03 04 05 06 07  00"))?;
-    // println!("==> Active Authentication: {}", hex::encode(data));
-
-    // Dump SOD
-    let sod: EfSod = card.read_cached()?;
-    println!("DOCUMENT HASH = 0x{}", hex::encode(sod.document_hash()));
+/// Write the MRZ as text and the face images in their own encoding.
+fn dump_extracted(document: &Document, output: &Path) -> Result<()> {
+    let mrz = &document.mrz;
+    // Line length follows from the document type, TD1 has three lines.
+    let line_length = if mrz.len() == 90 { 30 } else { mrz.len() / 2 };
+    let mut text = String::new();
+    for line in mrz.as_bytes().chunks(line_length.max(1)) {
+        text.push_str(&String::from_utf8_lossy(line));
+        text.push('\n');
+    }
+    let path = output.join(format!("{}.txt", file_name(FileId::Dg1)));
+    fs::write(&path, text)?;
+    println!("MRZ: {}", path.display());
+    if let Some(dg2) = document.data_groups.get(&2) {
+        for (index, face) in parse_dg2(dg2)?.iter().enumerate() {
+            let extension = match face.image_format {
+                FaceImageFormat::Jpeg => "jpg",
+                FaceImageFormat::Jpeg2000 => "jp2",
+                FaceImageFormat::Unknown(_) => "img",
+            };
+            let path = output.join(format!("{}_{index}.{extension}", file_name(FileId::Dg2)));
+            fs::write(&path, &face.image)?;
+            println!("Face image {index}: {}", path.display());
+        }
+    }
+    Ok(())
+}
 
-    // Do Chip Authentication
-    card.chip_authenticate(&mut rng)
-        .context("Error during Chip Authentication.")?;
+/// File name for a dumped file, e.g. `EF_DG1` or `EF_ATTR_INFO`.
+fn file_name(file_id: FileId) -> String {
+    file_id.to_string().replace(['.', '/'], "_")
+}
 
+fn verify(card: &mut Emrtd, args: &VerifyArgs) -> Result<()> {
+    let mut trust_store = TrustStore::new();
+    for path in &args.csca {
+        trust_store
+            .add_der(&fs::read(path)?)
+            .with_context(|| format!("Error reading CSCA {}.", path.display()))?;
+    }
+    if trust_store.is_empty() {
+        eprintln!("Warning: no --csca given, the Document Signer is not checked.");
+    }
+    // The document was read above, with partial reads reported.
+    let report = card.verify(&mut rand::thread_rng(), None, &trust_store)?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    ensure!(report.is_valid(), "Verification incomplete or failed.");
     Ok(())
 }
@@ -78,7 +78,12 @@ fn main() -> Result<()> {
         let document: Document = serde_json::from_reader(reader)?;
         println!(
             "Document with hash 0x{}",
-            hex::encode(document.sod.document_hash())
+            hex::encode(
+                document
+                    .sod
+                    .document_hash()
+                    .ok_or_else(|| anyhow!("Missing signer info"))?
+            )
         );
         let signed_data = document.sod.signed_data();
         let certs = signed_data.certificates.as_ref().unwrap();
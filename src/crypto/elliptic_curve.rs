@@ -259,6 +259,39 @@ pub fn ecka<'a>(
     Ok((s_ab, z_ab))
 }
 
+/// Elliptic Curve Digital Signature Algorithm verification
+/// See TR-03111 section 4.2.1.2
+///
+/// Returns `false` for out of range signature values instead of an error.
+pub fn ecdsa_verify(public_key: EllipticCurvePoint, hash: &[u8], (r, s): (Uint, Uint)) -> bool {
+    let curve = public_key.curve();
+    let scalar_field = curve.scalar_field();
+    let order = scalar_field.modulus();
+    if r == Uint::ZERO || r >= order || s == Uint::ZERO || s >= order {
+        return false;
+    }
+
+    // Use the leftmost bits of the hash, as many as the order has.
+    let hash = &hash[..hash.len().min(Uint::BYTES)];
+    let mut e = Uint::from_be_slice(hash);
+    let (hash_bits, order_bits) = (8 * hash.len(), order.bit_len());
+    if hash_bits > order_bits {
+        e >>= hash_bits - order_bits;
+    }
+    let e = scalar_field.el_from_uint(e % order);
+
+    let Some(w) = scalar_field.el_from_uint(s).inv() else {
+        return false;
+    };
+    let u1 = e * w;
+    let u2 = scalar_field.el_from_uint(r) * w;
+    let point = u1 * curve.generator() + u2 * public_key;
+    match point.x() {
+        Some(x) => x.to_uint() % order == r,
+        None => false,
+    }
+}
+
 macro_rules! forward_fmt {
     ($($type:ty),+) => {
         $(
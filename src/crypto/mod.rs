@@ -7,7 +7,8 @@ mod named_curves;
 mod prime_field;
 
 pub use self::{
-    elliptic_curve::{ecka, EllipticCurve, EllipticCurvePoint},
+    elliptic_curve::{ecdsa_verify, ecka, EllipticCurve, EllipticCurvePoint},
+    named_curves::{named_curve_bits, NamedCurve},
     prime_field::{PrimeField, PrimeFieldElement, Uint},
 };
 use {
@@ -15,7 +16,7 @@ use {
     der::asn1::{Int, OctetString},
 };
 
-fn parse_uint(int: &Int) -> Result<Uint> {
+pub fn parse_uint(int: &Int) -> Result<Uint> {
     // Get twos-complement big-endian bytes
     let mut big_endian = int.as_bytes();
 
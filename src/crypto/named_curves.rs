@@ -1,4 +1,8 @@
-use {der::asn1::ObjectIdentifier as Oid, hex_literal::hex};
+use {
+    der::asn1::ObjectIdentifier as Oid,
+    hex_literal::hex,
+    std::fmt::{self, Display, Formatter},
+};
 
 // https://www.rfc-editor.org/rfc/rfc5114
 // https://www.secg.org/sec2-v2.pdf
@@ -16,3 +20,90 @@ pub const ID_BRAINPOOL_P256R1: Oid = Oid::new_unwrap("1.3.36.3.3.2.8.1.1.7");
 pub const ID_BRAINPOOL_P320R1: Oid = Oid::new_unwrap("1.3.36.3.3.2.8.1.1.9");
 pub const ID_BRAINPOOL_P384R1: Oid = Oid::new_unwrap("1.3.36.3.3.2.8.1.1.11");
 pub const ID_BRAINPOOL_P512R1: Oid = Oid::new_unwrap("1.3.36.3.3.2.8.1.1.13");
+
+/// Named curves used in ICAO 9303, see ICAO 9303-11 section 9.5.1.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NamedCurve {
+    NistP192,
+    NistP224,
+    NistP256,
+    NistP384,
+    NistP521,
+    BrainpoolP192r1,
+    BrainpoolP224r1,
+    BrainpoolP256r1,
+    BrainpoolP320r1,
+    BrainpoolP384r1,
+    BrainpoolP512r1,
+}
+
+impl NamedCurve {
+    pub fn from_oid(oid: &Oid) -> Option<Self> {
+        Some(match *oid {
+            ID_SEC_P192R1 => Self::NistP192,
+            ID_SEC_P224R1 => Self::NistP224,
+            ID_SEC_P256R1 => Self::NistP256,
+            ID_SEC_P384R1 => Self::NistP384,
+            ID_SEC_P521R1 => Self::NistP521,
+            ID_BRAINPOOL_P192R1 => Self::BrainpoolP192r1,
+            ID_BRAINPOOL_P224R1 => Self::BrainpoolP224r1,
+            ID_BRAINPOOL_P256R1 => Self::BrainpoolP256r1,
+            ID_BRAINPOOL_P320R1 => Self::BrainpoolP320r1,
+            ID_BRAINPOOL_P384R1 => Self::BrainpoolP384r1,
+            ID_BRAINPOOL_P512R1 => Self::BrainpoolP512r1,
+            _ => return None,
+        })
+    }
+
+    pub fn oid(&self) -> Oid {
+        match self {
+            Self::NistP192 => ID_SEC_P192R1,
+            Self::NistP224 => ID_SEC_P224R1,
+            Self::NistP256 => ID_SEC_P256R1,
+            Self::NistP384 => ID_SEC_P384R1,
+            Self::NistP521 => ID_SEC_P521R1,
+            Self::BrainpoolP192r1 => ID_BRAINPOOL_P192R1,
+            Self::BrainpoolP224r1 => ID_BRAINPOOL_P224R1,
+            Self::BrainpoolP256r1 => ID_BRAINPOOL_P256R1,
+            Self::BrainpoolP320r1 => ID_BRAINPOOL_P320R1,
+            Self::BrainpoolP384r1 => ID_BRAINPOOL_P384R1,
+            Self::BrainpoolP512r1 => ID_BRAINPOOL_P512R1,
+        }
+    }
+
+    /// Size in bits of the prime field.
+    pub fn bits(&self) -> usize {
+        match self {
+            Self::NistP192 | Self::BrainpoolP192r1 => 192,
+            Self::NistP224 | Self::BrainpoolP224r1 => 224,
+            Self::NistP256 | Self::BrainpoolP256r1 => 256,
+            Self::BrainpoolP320r1 => 320,
+            Self::NistP384 | Self::BrainpoolP384r1 => 384,
+            Self::BrainpoolP512r1 => 512,
+            Self::NistP521 => 521,
+        }
+    }
+}
+
+impl Display for NamedCurve {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NistP192 => write!(f, "NIST P-192"),
+            Self::NistP224 => write!(f, "NIST P-224"),
+            Self::NistP256 => write!(f, "NIST P-256"),
+            Self::NistP384 => write!(f, "NIST P-384"),
+            Self::NistP521 => write!(f, "NIST P-521"),
+            Self::BrainpoolP192r1 => write!(f, "BrainpoolP192r1"),
+            Self::BrainpoolP224r1 => write!(f, "BrainpoolP224r1"),
+            Self::BrainpoolP256r1 => write!(f, "BrainpoolP256r1"),
+            Self::BrainpoolP320r1 => write!(f, "BrainpoolP320r1"),
+            Self::BrainpoolP384r1 => write!(f, "BrainpoolP384r1"),
+            Self::BrainpoolP512r1 => write!(f, "BrainpoolP512r1"),
+        }
+    }
+}
+
+/// Size in bits of the prime field of a named curve, if known.
+pub fn named_curve_bits(oid: &Oid) -> Option<usize> {
+    NamedCurve::from_oid(oid).map(|curve| curve.bits())
+}
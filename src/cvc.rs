@@ -0,0 +1,384 @@
+//! Card Verifiable Certificates for Terminal Authentication.
+//!
+//! CV certificates are not X.509, but self-descriptive BER-TLV data objects with
+//! application tags, see BSI TR-03110-3 appendix C and ISO 7816-8.
+
+use {
+    crate::lds::{
+        self,
+        tlv::{parse_tlv, TlvRef},
+    },
+    der::asn1::ObjectIdentifier as Oid,
+    std::time::{SystemTime, UNIX_EPOCH},
+    thiserror::Error,
+};
+
+pub const TAG_CV_CERTIFICATE: u32 = 0x7F21;
+pub const TAG_CERTIFICATE_BODY: u32 = 0x7F4E;
+pub const TAG_PROFILE_IDENTIFIER: u32 = 0x5F29;
+pub const TAG_AUTHORITY_REFERENCE: u32 = 0x42;
+pub const TAG_PUBLIC_KEY: u32 = 0x7F49;
+pub const TAG_HOLDER_REFERENCE: u32 = 0x5F20;
+pub const TAG_HOLDER_AUTHORIZATION: u32 = 0x7F4C;
+pub const TAG_EFFECTIVE_DATE: u32 = 0x5F25;
+pub const TAG_EXPIRATION_DATE: u32 = 0x5F24;
+pub const TAG_EXTENSIONS: u32 = 0x65;
+pub const TAG_SIGNATURE: u32 = 0x5F37;
+pub const TAG_OID: u32 = 0x06;
+pub const TAG_DISCRETIONARY_DATA: u32 = 0x53;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid TLV structure: {0}")]
+    InvalidTlv(#[from] lds::Error),
+
+    #[error("Unsupported certificate profile identifier {0}.")]
+    UnsupportedProfile(u8),
+
+    #[error("Invalid object identifier.")]
+    InvalidOid,
+
+    #[error("Invalid date.")]
+    InvalidDate,
+
+    #[error("Invalid character reference.")]
+    InvalidReference,
+
+    #[error("Certificate is not valid before {0:?}.")]
+    NotYetValid(CvDate),
+
+    #[error("Certificate expired on {0:?}.")]
+    Expired(CvDate),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A Card Verifiable Certificate.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CvCertificate {
+    pub body: CertificateBody,
+    pub signature: Vec<u8>,
+
+    /// The encoded certificate body (including its tag and length), which is what the
+    /// signature is over.
+    pub body_bytes: Vec<u8>,
+}
+
+/// The signed content of a CV certificate.
+///
+/// See BSI TR-03110-3 table C.1.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CertificateBody {
+    pub profile_identifier: u8,
+    /// Certification Authority Reference (CAR).
+    pub authority_reference: String,
+    pub public_key: CvPublicKey,
+    /// Certificate Holder Reference (CHR).
+    pub holder_reference: String,
+    /// Certificate Holder Authorization Template (CHAT).
+    pub holder_authorization: HolderAuthorization,
+    pub effective_date: CvDate,
+    pub expiration_date: CvDate,
+    /// Raw certificate extensions, if present.
+    pub extensions: Option<Vec<u8>>,
+}
+
+/// A public key data object.
+///
+/// The meaning of the context specific elements depends on the algorithm, e.g. for ECDSA
+/// `86` is the public point and `81`–`85`, `87` are the optional domain parameters.
+///
+/// See BSI TR-03110-3 section D.3.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CvPublicKey {
+    pub algorithm: Oid,
+    pub elements: Vec<(u32, Vec<u8>)>,
+}
+
+/// Certificate Holder Authorization Template.
+///
+/// See BSI TR-03110-3 section C.1.5.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HolderAuthorization {
+    /// The terminal type, e.g. `id-IS` for inspection systems.
+    pub role: Oid,
+    /// Role and access rights bit mask.
+    pub template: Vec<u8>,
+}
+
+/// A date encoded as six unpacked BCD digits `YYMMDD`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct CvDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl CvCertificate {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (certificate, _) = parse_tlv(bytes)?;
+        if certificate.tag != TAG_CV_CERTIFICATE {
+            return Err(lds::Error::UnexpectedTag(certificate.tag).into());
+        }
+        let (body, rest) = parse_tlv(certificate.value)?;
+        if body.tag != TAG_CERTIFICATE_BODY {
+            return Err(lds::Error::UnexpectedTag(body.tag).into());
+        }
+        let body_bytes = certificate.value[..certificate.value.len() - rest.len()].to_vec();
+        let signature = certificate.get(TAG_SIGNATURE)?.value.to_vec();
+        Ok(Self {
+            body: CertificateBody::from_tlv(body)?,
+            signature,
+            body_bytes,
+        })
+    }
+}
+
+impl CertificateBody {
+    pub fn from_tlv(body: TlvRef) -> Result<Self> {
+        let profile_identifier = match body.get(TAG_PROFILE_IDENTIFIER)?.value {
+            [0x00] => 0,
+            [version] => return Err(Error::UnsupportedProfile(*version)),
+            _ => return Err(lds::Error::InvalidInteger.into()),
+        };
+        Ok(Self {
+            profile_identifier,
+            authority_reference: reference(body.get(TAG_AUTHORITY_REFERENCE)?)?,
+            public_key: CvPublicKey::from_tlv(body.get(TAG_PUBLIC_KEY)?)?,
+            holder_reference: reference(body.get(TAG_HOLDER_REFERENCE)?)?,
+            holder_authorization: HolderAuthorization::from_tlv(
+                body.get(TAG_HOLDER_AUTHORIZATION)?,
+            )?,
+            effective_date: CvDate::from_bcd(body.get(TAG_EFFECTIVE_DATE)?.value)?,
+            expiration_date: CvDate::from_bcd(body.get(TAG_EXPIRATION_DATE)?.value)?,
+            extensions: body.find(TAG_EXTENSIONS)?.map(|tlv| tlv.value.to_vec()),
+        })
+    }
+
+    /// Check that `today` is within the effective and expiration date, inclusive.
+    ///
+    /// Use [`CvDate::today`] for the current date, or a fixed date to validate as of a
+    /// historical date.
+    pub fn check_validity(&self, today: CvDate) -> Result<()> {
+        if today < self.effective_date {
+            return Err(Error::NotYetValid(self.effective_date));
+        }
+        if today > self.expiration_date {
+            return Err(Error::Expired(self.expiration_date));
+        }
+        Ok(())
+    }
+}
+
+impl CvPublicKey {
+    pub fn from_tlv(public_key: TlvRef) -> Result<Self> {
+        let mut algorithm = None;
+        let mut elements = Vec::new();
+        for element in public_key.children() {
+            let element = element?;
+            if element.tag == TAG_OID {
+                algorithm = Some(oid(element)?);
+            } else {
+                elements.push((element.tag, element.value.to_vec()));
+            }
+        }
+        Ok(Self {
+            algorithm: algorithm.ok_or(lds::Error::MissingTag(TAG_OID))?,
+            elements,
+        })
+    }
+
+    /// The value of the context specific element with the given tag, e.g. `0x86`.
+    pub fn element(&self, tag: u32) -> Option<&[u8]> {
+        self.elements
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, value)| value.as_slice())
+    }
+}
+
+impl HolderAuthorization {
+    pub fn from_tlv(chat: TlvRef) -> Result<Self> {
+        Ok(Self {
+            role: oid(chat.get(TAG_OID)?)?,
+            template: chat.get(TAG_DISCRETIONARY_DATA)?.value.to_vec(),
+        })
+    }
+}
+
+impl CvDate {
+    pub fn from_bcd(digits: &[u8]) -> Result<Self> {
+        let [y1, y2, m1, m2, d1, d2] = *digits else {
+            return Err(Error::InvalidDate);
+        };
+        if digits.iter().any(|&digit| digit > 9) {
+            return Err(Error::InvalidDate);
+        }
+        let date = Self {
+            year: 2000 + (y1 * 10 + y2) as u16,
+            month: m1 * 10 + m2,
+            day: d1 * 10 + d2,
+        };
+        if !(1..=12).contains(&date.month) || !(1..=31).contains(&date.day) {
+            return Err(Error::InvalidDate);
+        }
+        Ok(date)
+    }
+
+    /// The current date in UTC.
+    pub fn today() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    /// The date in UTC, times before 1970 are clamped to the epoch.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let days = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / 86400;
+
+        // Civil date from days since epoch, with years starting in March.
+        // See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+        let z = days + 719468;
+        let era = z / 146097;
+        let doe = z % 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u8;
+        let year = (yoe + era * 400) as u16 + u16::from(month <= 2);
+        Self { year, month, day }
+    }
+}
+
+fn oid(tlv: TlvRef) -> Result<Oid> {
+    Oid::from_bytes(tlv.value).map_err(|_| Error::InvalidOid)
+}
+
+/// Certificate references (CAR, CHR) are ISO 8859-1 strings, in practice ASCII.
+fn reference(tlv: TlvRef) -> Result<String> {
+    if !tlv.value.is_ascii() {
+        return Err(Error::InvalidReference);
+    }
+    Ok(String::from_utf8_lossy(tlv.value).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex, std::time::Duration};
+
+    fn tlv(tag: &[u8], value: &[u8]) -> Vec<u8> {
+        assert!(value.len() < 0x80);
+        [tag, &[value.len() as u8], value].concat()
+    }
+
+    #[test]
+    fn test_parse_cv_certificate() {
+        let public_key = [
+            tlv(&[0x06], &hex!("04007F00070202020203")),
+            tlv(&[0x86], &hex!("04 0102 0304")),
+        ]
+        .concat();
+        let chat = [
+            tlv(&[0x06], &hex!("04007F000703010201")),
+            tlv(&[0x53], &hex!("03")),
+        ]
+        .concat();
+        let body = tlv(
+            &hex!("7F4E"),
+            &[
+                tlv(&hex!("5F29"), &[0x00]),
+                tlv(&[0x42], b"DECVCA00001"),
+                tlv(&hex!("7F49"), &public_key),
+                tlv(&hex!("5F20"), b"DETERM00001"),
+                tlv(&hex!("7F4C"), &chat),
+                tlv(&hex!("5F25"), &hex!("020400010105")),
+                tlv(&hex!("5F24"), &hex!("020400040105")),
+            ]
+            .concat(),
+        );
+        let certificate = tlv(
+            &hex!("7F21"),
+            &[body.clone(), tlv(&hex!("5F37"), &hex!("DEADBEEF"))].concat(),
+        );
+
+        let certificate = CvCertificate::from_bytes(&certificate).unwrap();
+        assert_eq!(certificate.body_bytes, body);
+        assert_eq!(certificate.signature, hex!("DEADBEEF"));
+        let body = certificate.body;
+        assert_eq!(body.authority_reference, "DECVCA00001");
+        assert_eq!(body.holder_reference, "DETERM00001");
+        assert_eq!(
+            body.public_key.algorithm,
+            Oid::new_unwrap("0.4.0.127.0.7.2.2.2.2.3")
+        );
+        assert_eq!(body.public_key.element(0x86), Some(&hex!("0401020304")[..]));
+        assert_eq!(
+            body.holder_authorization.role,
+            Oid::new_unwrap("0.4.0.127.0.7.3.1.2.1")
+        );
+        assert_eq!(body.holder_authorization.template, [0x03]);
+        assert_eq!(
+            body.effective_date,
+            CvDate {
+                year: 2024,
+                month: 1,
+                day: 15
+            }
+        );
+        assert!(body.effective_date < body.expiration_date);
+        assert_eq!(body.extensions, None);
+
+        let date = |year, month, day| CvDate { year, month, day };
+        body.check_validity(date(2024, 1, 15)).unwrap();
+        body.check_validity(date(2024, 4, 15)).unwrap();
+        assert!(matches!(
+            body.check_validity(date(2024, 1, 14)),
+            Err(Error::NotYetValid(_))
+        ));
+        assert!(matches!(
+            body.check_validity(date(2024, 4, 16)),
+            Err(Error::Expired(_))
+        ));
+    }
+
+    #[test]
+    fn test_date_from_system_time() {
+        let at = |secs| CvDate::from_system_time(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(
+            at(0),
+            CvDate {
+                year: 1970,
+                month: 1,
+                day: 1
+            }
+        );
+        // 2024-02-29T23:59:59Z
+        assert_eq!(
+            at(1709251199),
+            CvDate {
+                year: 2024,
+                month: 2,
+                day: 29
+            }
+        );
+        // 2024-03-01T00:00:00Z
+        assert_eq!(
+            at(1709251200),
+            CvDate {
+                year: 2024,
+                month: 3,
+                day: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_date() {
+        assert!(CvDate::from_bcd(&hex!("020401030105")).is_err());
+        assert!(CvDate::from_bcd(&hex!("0204000A0105")).is_err());
+        assert!(CvDate::from_bcd(&hex!("0204")).is_err());
+    }
+}
@@ -0,0 +1,507 @@
+//! The Chip Access Procedure.
+//!
+//! See ICAO 9303-11 section 4.2.
+
+use {
+    super::{Emrtd, Error, FileId},
+    crate::{
+        iso7816::StatusWord,
+        lds::{data_group::parse_dg1, parse_com, Com},
+    },
+    anyhow::{Context, Result},
+    rand::Rng,
+    std::collections::BTreeMap,
+};
+
+/// Decisions taken and files read by [`Emrtd::access_procedure`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AccessReport {
+    /// EF.CardAccess lists PACE. Basic Access Control is used regardless.
+    pub pace_supported: bool,
+
+    /// Content of EF.COM, `None` if the file is absent.
+    pub com: Option<Com>,
+
+    /// EF.SOD is present, so Passive Authentication is possible.
+    pub sod_present: bool,
+
+    /// Numbers of the data groups read.
+    pub data_groups: Vec<u8>,
+
+    /// Numbers of the data groups skipped because they require Extended Access Control.
+    pub eac_required: Vec<u8>,
+}
+
+/// The LDS1 contents read by [`Emrtd::read_document`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Document {
+    /// MRZ from EF.DG1, as a single string without line breaks.
+    pub mrz: String,
+
+    /// Raw content of EF.COM, `None` if the file is absent.
+    pub com: Option<Vec<u8>>,
+
+    /// Raw content of EF.SOD, `None` if the file is absent.
+    pub sod: Option<Vec<u8>>,
+
+    /// Raw content of the data groups read, by number. Always includes DG1.
+    pub data_groups: BTreeMap<u8, Vec<u8>>,
+
+    /// Decisions taken during the Chip Access Procedure.
+    pub report: AccessReport,
+}
+
+/// A data group could not be read, with everything read before it.
+///
+/// Returned by [`Emrtd::access_procedure`] wrapped in [`anyhow::Error`], recover it with
+/// [`anyhow::Error::downcast`]. This lets e.g. a kiosk show the MRZ and ask to retry only
+/// for the photo. The data groups in `report` remain in the file cache, so a retry with the
+/// same session only reads the missing ones.
+#[derive(Debug, thiserror::Error)]
+#[error("Error reading DG{number}.")]
+pub struct PartialRead {
+    /// Number of the data group that failed.
+    pub number: u8,
+
+    /// Decisions taken and data groups read before the failure.
+    pub report: AccessReport,
+
+    #[source]
+    pub source: Error,
+}
+
+impl Emrtd {
+    /// Run the Chip Access Procedure and read the LDS1 data groups.
+    ///
+    /// EF.CardAccess is read to decide on PACE, Secure Messaging is established with Basic
+    /// Access Control, then EF.COM, EF.SOD and the data groups listed in EF.COM are read into
+    /// the file cache. Without EF.COM all data groups are tried. DG3 and DG4 are skipped, as
+    /// Terminal Authentication is not implemented.
+    ///
+    /// Decisions are logged to stderr if tracing is enabled, see [`Emrtd::set_trace`].
+    ///
+    /// If a data group fails to read once Secure Messaging is established, the error is a
+    /// [`PartialRead`] carrying the report so far.
+    pub fn access_procedure(&mut self, rng: &mut impl Rng, mrz: &str) -> Result<AccessReport> {
+        // PACE supported?
+        let pace_supported = self
+            .pace_supported()
+            .context("Error reading EF.CardAccess.")?;
+        if pace_supported {
+            // TODO: Use PACE when supported, selecting the application afterwards.
+            self.log("EF.CardAccess lists PACE, which is not implemented, using BAC.");
+        } else {
+            self.log("No PACE in EF.CardAccess, using BAC.");
+        }
+
+        // Basic Access Control requires the application to be selected first.
+        self.select_lds1()?;
+        self.basic_access_control(rng, mrz)
+            .context("Error during Basic Access Control.")?;
+        self.log("Basic Access Control successful.");
+
+        let com = match self.read_file_cached(FileId::Com)? {
+            Some(bytes) => Some(parse_com(&bytes)?),
+            None => {
+                self.log("EF.COM not found, trying all data groups.");
+                None
+            }
+        };
+        let sod_present = self.read_file_cached(FileId::Sod)?.is_some();
+        if !sod_present {
+            self.log("EF.SOD not found, Passive Authentication is not possible.");
+        }
+
+        let listed = match &com {
+            Some(com) => com.data_groups.clone(),
+            None => (1..=16).collect(),
+        };
+        let mut data_groups = Vec::new();
+        let mut eac_required = Vec::new();
+        for number in listed {
+            // EAC needed?
+            if matches!(number, 3 | 4) {
+                self.log(format!(
+                    "DG{number} requires Extended Access Control, skipping."
+                ));
+                eac_required.push(number);
+                continue;
+            }
+            let file = FileId::data_group(number).unwrap();
+            match self.read_file_cached(file) {
+                Ok(Some(_)) => data_groups.push(number),
+                Ok(None) if com.is_some() => {
+                    self.log(format!("DG{number} listed in EF.COM but not found."))
+                }
+                Ok(None) => {}
+                Err(Error::ErrorResponse(StatusWord::ACCESS_DENIED)) => {
+                    self.log(format!("DG{number} access denied, skipping."));
+                    eac_required.push(number);
+                }
+                Err(source) => {
+                    return Err(PartialRead {
+                        number,
+                        report: AccessReport {
+                            pace_supported,
+                            com,
+                            sod_present,
+                            data_groups,
+                            eac_required,
+                        },
+                        source,
+                    }
+                    .into())
+                }
+            }
+        }
+        if data_groups.contains(&14) {
+            // TODO: Chip Authentication.
+            self.log("DG14 present, Chip Authentication is not performed.");
+        }
+
+        Ok(AccessReport {
+            pace_supported,
+            com,
+            sod_present,
+            data_groups,
+            eac_required,
+        })
+    }
+
+    /// Run the Chip Access Procedure and return the files read.
+    ///
+    /// See [`Emrtd::access_procedure`] for the steps taken, errors are contextualized with
+    /// the step that failed. DG1 is required, other data groups are returned as far as they
+    /// are listed in EF.COM and could be read without Extended Access Control.
+    pub fn read_document(&mut self, rng: &mut impl Rng, mrz: &str) -> Result<Document> {
+        let report = self.access_procedure(rng, mrz)?;
+        let mut data_groups = BTreeMap::new();
+        for &number in &report.data_groups {
+            let file = FileId::data_group(number).unwrap();
+            if let Some(bytes) = self.read_file_cached(file)? {
+                data_groups.insert(number, bytes);
+            }
+        }
+        let dg1 = data_groups
+            .get(&1)
+            .ok_or(Error::FileNotFound)
+            .context("Error reading DG1.")?;
+        let mrz = parse_dg1(dg1).context("Error parsing DG1.")?;
+        Ok(Document {
+            mrz,
+            com: self.read_file_cached(FileId::Com)?,
+            sod: self.read_file_cached(FileId::Sod)?,
+            data_groups,
+            report,
+        })
+    }
+
+    /// Establish Secure Messaging and read only the MRZ from EF.DG1.
+    ///
+    /// A fast path for when only the identity fields are needed: EF.COM, EF.SOD and the
+    /// slow biometric data groups are not read. No Passive Authentication is performed, so
+    /// the MRZ is not verified to be issued by the document signer.
+    pub fn read_mrz_only(&mut self, rng: &mut impl Rng, mrz: &str) -> Result<String> {
+        self.select_lds1()?;
+        self.basic_access_control(rng, mrz)
+            .context("Error during Basic Access Control.")?;
+        let dg1 = self
+            .read_file_cached(FileId::Dg1)
+            .context("Error reading DG1.")?
+            .ok_or(Error::FileNotFound)?;
+        Ok(parse_dg1(&dg1)?)
+    }
+
+    fn log(&self, message: impl AsRef<str>) {
+        if self.trace {
+            eprintln!("# {}", message.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            emrtd::{
+                secure_messaging::{tdes::TDesCipher, Cipher, Encrypted, SecureMessaging},
+                SessionProtocol,
+            },
+            nfc::mock::MockReader,
+        },
+        hex_literal::hex,
+        rand::{CryptoRng, RngCore},
+        std::collections::VecDeque,
+    };
+
+    /// MRZ information of ICAO 9303-11 Appendix D.
+    const MRZ: &str = "L898902C<369080619406236";
+
+    /// EF.CardAccess with PACE-ECDH-GM-AES-CBC-CMAC-128 on brainpoolP256r1.
+    const CARD_ACCESS_PACE: [u8; 22] = hex!("3114 3012 060A04007F00070202040202 020102 02010D");
+
+    /// EF.COM listing DG1, DG2, DG3 and DG14.
+    const COM_EAC: [u8; 24] = hex!("6016 5F010430313036 5F3606303430303030 5C0461756E63");
+
+    const DG1: [u8; 4] = hex!("61 02 0102");
+
+    /// MRZ of the TD3 specimen in ICAO 9303-4.
+    const TD3_MRZ: &str = "P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<\
+                           L898902C36UTO7408122F1204159ZE184226B<<<<<10";
+    const DG2: [u8; 4] = hex!("75 02 0102");
+    const DG14: [u8; 4] = hex!("6E 02 0102");
+    const SOD: [u8; 4] = hex!("77 02 0102");
+
+    fn td3_dg1() -> Vec<u8> {
+        [&hex!("615B 5F1F58")[..], TD3_MRZ.as_bytes()].concat()
+    }
+
+    /// Returns RND.IFD and K.IFD of Appendix D, one byte per `next_u32` as `gen::<u8>`.
+    struct FixedRng(VecDeque<u8>);
+
+    impl FixedRng {
+        fn appendix_d() -> Self {
+            Self(
+                [
+                    &hex!("781723860C06C226")[..],
+                    &hex!("0B795240CB7049B01C19B33E32804F0B"),
+                ]
+                .concat()
+                .into(),
+            )
+        }
+    }
+
+    impl RngCore for FixedRng {
+        fn next_u32(&mut self) -> u32 {
+            self.0.pop_front().expect("FixedRng exhausted") as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.next_u32() as u64
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.iter_mut()
+                .for_each(|byte| *byte = self.next_u32() as u8);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for FixedRng {}
+
+    /// Scripts the chip side of the Appendix D worked example.
+    struct Chip {
+        nfc: MockReader,
+        sm: Encrypted<TDesCipher>,
+    }
+
+    impl Chip {
+        fn new() -> Self {
+            // Session keys and counter from ICAO 9303-11 Appendix D.4.
+            let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+            Self {
+                nfc: MockReader::default(),
+                sm: Encrypted::new(TDesCipher::from_seed(&seed), 0x887022120C06C226),
+            }
+        }
+
+        fn plain(mut self, apdu: &[u8], status: StatusWord, data: &[u8]) -> Self {
+            self.nfc = self.nfc.expect(apdu, status, data);
+            self
+        }
+
+        /// EF.CardAccess read in the clear from the master file, or absent.
+        fn card_access(self, content: Option<&[u8]>) -> Self {
+            match content {
+                Some(content) => self
+                    .plain(&hex!("00 B0 9C00 04"), StatusWord::SUCCESS, &content[..4])
+                    .plain(
+                        &[&hex!("00 B0 0004")[..], &[content.len() as u8 - 4]].concat(),
+                        StatusWord::SUCCESS,
+                        &content[4..],
+                    ),
+                None => self
+                    .plain(&hex!("00 B0 9C00 04"), StatusWord::FILE_NOT_FOUND, &[])
+                    .plain(&hex!("00 A4 020C 02 011C"), StatusWord::FILE_NOT_FOUND, &[]),
+            }
+        }
+
+        /// SELECT of the LDS1 application followed by Basic Access Control.
+        fn bac(self) -> Self {
+            self.plain(
+                &hex!("00 A4 0400 07 A0000002471001 00"),
+                StatusWord::SUCCESS,
+                &[],
+            )
+            .plain(
+                &hex!("00 84 0000 08"),
+                StatusWord::SUCCESS,
+                &hex!("4608F91988702212"),
+            )
+            .plain(
+                &hex!(
+                    "0082000028 72C29C2371CC9BDB65B779B8E8D37B29ECC154AA56A8799FAE2F498F76ED92F2
+                    5F1448EEA8AD90A7 00"
+                ),
+                StatusWord::SUCCESS,
+                &hex!(
+                    "46B9342A41396CD7386BF5803104D7CEDC122B9132139BAF2EEDC94EE178534F
+                    2F2D235D074D7449"
+                ),
+            )
+        }
+
+        /// An exchange under Secure Messaging.
+        fn protected(mut self, apdu: &[u8], status: StatusWord, data: &[u8]) -> Self {
+            let papdu = self.sm.enc_apdu(apdu).unwrap();
+            let response = self.sm.protect_response(status, data);
+            self.nfc = self.nfc.expect(&papdu, status, &response);
+            self
+        }
+
+        /// A short single TLV file, read by short EF identifier.
+        fn file(self, short_id: u8, content: &[u8]) -> Self {
+            let header = content.len().min(4);
+            let chip = self.protected(
+                &[0x00, 0xB0, 0x80 | short_id, 0x00, 0x04],
+                StatusWord::SUCCESS,
+                &content[..header],
+            );
+            if content.len() <= header {
+                return chip;
+            }
+            chip.protected(
+                &[0x00, 0xB0, 0x00, 0x04, (content.len() - 4) as u8],
+                StatusWord::SUCCESS,
+                &content[4..],
+            )
+        }
+
+        fn missing(self, short_id: u8) -> Self {
+            self.protected(
+                &[0x00, 0xB0, 0x80 | short_id, 0x00, 0x04],
+                StatusWord::FILE_NOT_FOUND,
+                &[],
+            )
+        }
+
+        fn card(self) -> Emrtd {
+            Emrtd::new(Box::new(self.nfc))
+        }
+    }
+
+    #[test]
+    fn test_access_procedure_pace_falls_back_to_bac() {
+        let mut card = Chip::new()
+            .card_access(Some(&CARD_ACCESS_PACE))
+            .bac()
+            .file(0x1E, &COM_EAC)
+            .file(0x1D, &SOD)
+            .file(0x01, &DG1)
+            .file(0x02, &DG2)
+            .file(0x0E, &DG14)
+            .card();
+        let report = card
+            .access_procedure(&mut FixedRng::appendix_d(), MRZ)
+            .unwrap();
+        assert!(report.pace_supported);
+        assert_eq!(card.session_info().unwrap().protocol, SessionProtocol::Bac);
+        assert!(report.sod_present);
+        assert_eq!(report.data_groups, [1, 2, 14]);
+        assert_eq!(report.eac_required, [3]);
+    }
+
+    #[test]
+    fn test_access_procedure_without_card_access() {
+        let mut card = Chip::new()
+            .card_access(None)
+            .bac()
+            .file(0x1E, &COM_EAC)
+            .missing(0x1D)
+            .file(0x01, &DG1)
+            .file(0x02, &DG2)
+            .file(0x0E, &DG14)
+            .card();
+        let report = card
+            .access_procedure(&mut FixedRng::appendix_d(), MRZ)
+            .unwrap();
+        assert!(!report.pace_supported);
+        assert!(!report.sod_present);
+        assert_eq!(report.com.unwrap().data_groups, [1, 2, 14, 3]);
+
+        // DG14 is read for Chip Authentication, which is not performed.
+        assert_eq!(report.data_groups, [1, 2, 14]);
+        assert_eq!(card.read_file_cached(FileId::Dg14).unwrap().unwrap(), DG14);
+    }
+
+    #[test]
+    fn test_access_procedure_partial_read() {
+        let mut card = Chip::new()
+            .card_access(None)
+            .bac()
+            .file(0x1E, &COM_EAC)
+            .file(0x1D, &SOD)
+            .file(0x01, &DG1)
+            .protected(&hex!("00 B0 8200 04"), 0x6F00.into(), &[])
+            .card();
+        let error = card
+            .access_procedure(&mut FixedRng::appendix_d(), MRZ)
+            .unwrap_err()
+            .downcast::<PartialRead>()
+            .unwrap();
+        assert_eq!(error.number, 2);
+        assert!(matches!(
+            error.source,
+            Error::ErrorResponse(status) if status == 0x6F00.into()
+        ));
+
+        // Everything before the failure is reported and stays cached.
+        assert!(error.report.sod_present);
+        assert_eq!(error.report.data_groups, [1]);
+        assert_eq!(card.read_file_cached(FileId::Dg1).unwrap().unwrap(), DG1);
+    }
+
+    #[test]
+    fn test_read_mrz_only() {
+        // No EF.CardAccess, EF.COM or EF.SOD, any other APDU fails the mock.
+        let mut card = Chip::new().bac().file(0x01, &td3_dg1()).card();
+        assert_eq!(
+            card.read_mrz_only(&mut FixedRng::appendix_d(), MRZ)
+                .unwrap(),
+            TD3_MRZ
+        );
+        assert_eq!(card.session_info().unwrap().protocol, SessionProtocol::Bac);
+    }
+
+    #[test]
+    fn test_read_document() {
+        let mut card = Chip::new()
+            .card_access(None)
+            .bac()
+            .file(0x1E, &COM_EAC)
+            .file(0x1D, &SOD)
+            .file(0x01, &td3_dg1())
+            .file(0x02, &DG2)
+            .file(0x0E, &DG14)
+            .card();
+        let document = card
+            .read_document(&mut FixedRng::appendix_d(), MRZ)
+            .unwrap();
+        assert_eq!(document.mrz, TD3_MRZ);
+        assert_eq!(document.com.unwrap(), COM_EAC);
+        assert_eq!(document.sod.unwrap(), SOD);
+
+        // DG3 requires Extended Access Control.
+        assert_eq!(
+            document.data_groups,
+            BTreeMap::from([(1, td3_dg1()), (2, DG2.to_vec()), (14, DG14.to_vec())])
+        );
+        assert_eq!(document.report.eac_required, [3]);
+    }
+}
@@ -2,40 +2,103 @@ use {
     super::{
         pad,
         secure_messaging::{tdes::TDesCipher, Cipher, Encrypted},
-        seed_from_mrz, Emrtd,
+        seed_from_mrz, Emrtd, Error, SessionInfo, SessionProtocol, State,
+    },
+    crate::{
+        asn1::security_info::SymmetricCipher,
+        ensure_err,
+        iso7816::{CommandApdu, Le, ResponseApdu},
+        lds::{validate_mrz_info, MrzInput},
     },
     anyhow::{anyhow, ensure, Result},
     rand::Rng,
     std::array,
+    subtle::ConstantTimeEq,
 };
 
+/// Length of the challenge used in Basic Access Control.
+///
+/// See ICAO 9303-11 section 4.3.4.1.
+pub const BAC_CHALLENGE_LENGTH: usize = 8;
+
+/// Failures of the mutual authentication in Basic Access Control.
+///
+/// The MAC and both nonces are checked in constant time and a mismatch of any is reported
+/// as [`AuthError::Mismatch`], so neither timing nor the error reveals which check failed.
+/// A mismatch usually means the MRZ is wrong.
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("EXTERNAL AUTHENTICATE response has length {0}, expected 40.")]
+    InvalidResponseLength(usize),
+
+    #[error("EXTERNAL AUTHENTICATE response did not verify, is the MRZ correct?")]
+    Mismatch,
+}
+
 impl Emrtd {
-    /// Get random nonce for authentication.
+    /// Get random nonce for Basic Access Control.
     ///
     /// See ICAO 9303-11 section 4.3.4.1.
-    pub fn get_challenge(&mut self) -> Result<Vec<u8>> {
-        let (status, data) = self.send_apdu(&[0x00, 0x84, 0x00, 0x00, 0x08])?;
-        if !status.is_success() {
-            return Err(anyhow!("Failed to get challenge: {}", status));
-        }
-        ensure!(status.data_remaining() == None);
-        ensure!(data.len() == 8);
-        Ok(data)
+    pub fn get_challenge(&mut self) -> super::Result<Vec<u8>> {
+        self.get_challenge_with_length(BAC_CHALLENGE_LENGTH)
     }
 
+    /// Get a random nonce of a given length from the chip.
+    ///
+    /// Basic Access Control uses 8 bytes, Terminal Authentication may request
+    /// a different length. T=0 chips returning `61XX` are handled by [`Emrtd::send_apdu`].
+    pub fn get_challenge_with_length(&mut self, length: usize) -> super::Result<Vec<u8>> {
+        ensure_err!(
+            (1..=Le::MAX_SHORT).contains(&length),
+            Error::InvalidChallengeLength(length)
+        );
+        let apdu = CommandApdu::new(0x00, 0x84, 0x00, 0x00).with_le(Le::new(length, false));
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        ensure_err!(response.is_success(), response.status.into());
+        ensure_err!(
+            response.data.len() == length,
+            Error::UnexpectedChallengeLength {
+                expected: length,
+                actual: response.data.len(),
+            }
+        );
+        Ok(response.data)
+    }
+
+    /// Send the 40 bytes of authentication data from the terminal.
     pub fn external_authenticate(&mut self, data: &[u8]) -> Result<Vec<u8>> {
-        assert_eq!(data.len(), 0x28);
-        let mut apdu = vec![0x00, 0x82, 0x00, 0x00, 0x28];
-        apdu.extend_from_slice(data);
-        apdu.push(0x00);
-        let (status, data) = self.send_apdu(&apdu)?;
+        ensure!(
+            data.len() == 0x28,
+            "EXTERNAL AUTHENTICATE data must be 40 bytes, got {}.",
+            data.len()
+        );
+        let ResponseApdu { status, data } = self.send_apdu(&external_authenticate_apdu(data))?;
+        self.record_auth_attempt(status);
+        if let Some(retries) = status.retries_remaining() {
+            return Err(anyhow!(
+                "Failed to authenticate: {status}, {retries} tries remaining."
+            ));
+        }
         if !status.is_success() {
             return Err(anyhow!("Failed to authenticate: {}", status));
         }
         Ok(data)
     }
 
+    /// Basic Access Control using the document number, date of birth and date of expiry.
+    pub fn basic_access_control_with(&mut self, rng: &mut impl Rng, mrz: &MrzInput) -> Result<()> {
+        self.basic_access_control(rng, &mrz.mrz_information())
+    }
+
+    /// Basic Access Control using the MRZ information string, see [`MrzInput::mrz_information`].
+    ///
+    /// The string is validated first, so a malformed MRZ does not cost an authentication
+    /// attempt. Fails without contacting the chip if the limit set with
+    /// [`Emrtd::set_max_auth_attempts`] is reached.
     pub fn basic_access_control(&mut self, rng: &mut impl Rng, mrz: &str) -> Result<()> {
+        validate_mrz_info(mrz)?;
+        self.check_auth_attempts()?;
+
         // Compute local randomness
         let rnd_ifd: [u8; 8] = rng.gen();
         let k_ifd: [u8; 16] = rng.gen();
@@ -48,31 +111,11 @@ impl Emrtd {
         let rnd_ic = self.get_challenge()?;
 
         // Construct authentication data
-        let mut msg = vec![];
-        msg.extend_from_slice(&rnd_ifd);
-        msg.extend_from_slice(&rnd_ic);
-        msg.extend_from_slice(&k_ifd);
-        cipher.enc(0, &mut msg);
-        let mut msg_mac = msg.clone();
-        pad(&mut msg_mac, cipher.block_size());
-        msg.extend(cipher.mac(0, &msg_mac));
+        let msg = authentication_data(&cipher, &rnd_ifd, &rnd_ic, &k_ifd);
 
         // EXTERNAL AUTHENTICATE
-        let mut resp_data = self.external_authenticate(&msg)?;
-        ensure!(resp_data.len() == 40);
-
-        // Check MAC and decrypt response
-        let mut msg_mac = resp_data[..32].to_vec();
-        pad(&mut msg_mac, cipher.block_size());
-        let mac = cipher.mac(0, &msg_mac);
-        ensure!(&resp_data[32..] == &mac[..]);
-        cipher.dec(0, &mut resp_data[..32]);
-        let resp_data = &resp_data[..32];
-
-        // Check nonce consistency
-        ensure!(&resp_data[0..8] == &rnd_ic[..]);
-        ensure!(&resp_data[8..16] == &rnd_ifd[..]);
-        let k_ic: [u8; 16] = resp_data[16..].try_into().unwrap();
+        let resp_data = self.external_authenticate(&msg)?;
+        let k_ic = check_authentication_response(&cipher, &rnd_ifd, &rnd_ic, resp_data)?;
 
         // Construct seed and ssc for session keys
         let seed: [u8; 16] = array::from_fn(|i| k_ifd[i] ^ k_ic[i]);
@@ -86,8 +129,240 @@ impl Emrtd {
 
         // Add TDES session keys to secure messaging
         let tdes = Encrypted::new(TDesCipher::from_seed(&seed), ssc);
-        self.secure_messaging = Box::new(tdes);
+        self.set_secure_messaging(Box::new(tdes));
+        self.state = State::Session;
+        self.session = Some(SessionInfo {
+            protocol: SessionProtocol::Bac,
+            cipher: SymmetricCipher::Tdes,
+            curve: None,
+            key_bits: None,
+        });
+        self.access_key = Some(mrz.to_owned());
 
         Ok(())
     }
 }
+
+/// Intermediate values of Basic Access Control, for checking against test vectors.
+///
+/// The Basic Access Keys open the chip to anyone who has them, like the MRZ they are
+/// derived from.
+#[cfg(feature = "dangerous")]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BacDebug {
+    /// Key seed derived from the MRZ information.
+    pub seed: [u8; 16],
+
+    /// Basic Access Key for encryption.
+    pub kenc: [u8; 16],
+
+    /// Basic Access Key for message authentication.
+    pub kmac: [u8; 16],
+
+    /// The EXTERNAL AUTHENTICATE command APDU as it would be sent to the chip.
+    pub external_authenticate: Vec<u8>,
+}
+
+/// Compute the Basic Access Control keys and the EXTERNAL AUTHENTICATE command for
+/// fixed nonces, without a chip.
+///
+/// See ICAO 9303-11 appendix D.
+#[cfg(feature = "dangerous")]
+pub fn bac_debug(mrz: &str, rnd_ifd: &[u8; 8], rnd_ic: &[u8; 8], k_ifd: &[u8; 16]) -> BacDebug {
+    let seed = seed_from_mrz(mrz);
+    let cipher = TDesCipher::from_seed(&seed);
+    let msg = authentication_data(&cipher, rnd_ifd, rnd_ic, k_ifd);
+    BacDebug {
+        seed,
+        kenc: *cipher.kenc(),
+        kmac: *cipher.kmac(),
+        external_authenticate: external_authenticate_apdu(&msg),
+    }
+}
+
+/// Encrypted and MACed `RND.IFD || RND.IC || K.IFD`.
+///
+/// See ICAO 9303-11 section 4.3.3.
+fn authentication_data(
+    cipher: &TDesCipher,
+    rnd_ifd: &[u8],
+    rnd_ic: &[u8],
+    k_ifd: &[u8],
+) -> Vec<u8> {
+    let mut msg = vec![];
+    msg.extend_from_slice(rnd_ifd);
+    msg.extend_from_slice(rnd_ic);
+    msg.extend_from_slice(k_ifd);
+    cipher.enc(0, &mut msg);
+    let mut msg_mac = msg.clone();
+    pad(&mut msg_mac, cipher.block_size());
+    msg.extend(cipher.mac(0, &msg_mac));
+    msg
+}
+
+/// Verify the MAC and nonces of the EXTERNAL AUTHENTICATE response and return `K.IC`.
+///
+/// See ICAO 9303-11 section 4.3.3.
+fn check_authentication_response(
+    cipher: &TDesCipher,
+    rnd_ifd: &[u8],
+    rnd_ic: &[u8],
+    mut resp_data: Vec<u8>,
+) -> std::result::Result<[u8; 16], AuthError> {
+    if resp_data.len() != 40 {
+        return Err(AuthError::InvalidResponseLength(resp_data.len()));
+    }
+
+    // Check MAC and decrypt response. Decryption happens regardless of the MAC, so the
+    // work done does not depend on which check fails.
+    let mut msg_mac = resp_data[..32].to_vec();
+    pad(&mut msg_mac, cipher.block_size());
+    let mac = cipher.mac(0, &msg_mac);
+    let mut valid = resp_data[32..].ct_eq(&mac[..]);
+    cipher.dec(0, &mut resp_data[..32]);
+
+    // Check nonce consistency
+    valid &= resp_data[0..8].ct_eq(rnd_ic);
+    valid &= resp_data[8..16].ct_eq(rnd_ifd);
+    if !bool::from(valid) {
+        return Err(AuthError::Mismatch);
+    }
+    Ok(resp_data[16..32].try_into().unwrap())
+}
+
+/// The EXTERNAL AUTHENTICATE command, the 40 bytes of `data` always fit a short APDU.
+fn external_authenticate_apdu(data: &[u8]) -> Vec<u8> {
+    CommandApdu {
+        data: data.to_vec(),
+        ..CommandApdu::new(0x00, 0x82, 0x00, 0x00).with_le(Le::max(false))
+    }
+    .to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{iso7816::StatusWord, nfc::mock::MockReader},
+        hex_literal::hex,
+    };
+
+    #[test]
+    fn test_get_challenge_get_response() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 84 00 00 08"), 0x6108.into(), &[])
+            .expect(
+                &hex!("00 C0 00 00 08"),
+                StatusWord::SUCCESS,
+                &hex!("4608F91988702212"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert_eq!(card.get_challenge().unwrap(), hex!("4608F91988702212"));
+    }
+
+    #[test]
+    fn test_get_challenge_invalid_length() {
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        for length in [0, 257] {
+            assert!(matches!(
+                card.get_challenge_with_length(length),
+                Err(Error::InvalidChallengeLength(l)) if l == length
+            ));
+        }
+    }
+
+    #[test]
+    fn test_max_auth_attempts() {
+        let mut apdu = hex!("00 82 0000 28").to_vec();
+        apdu.extend_from_slice(&[0; 0x28]);
+        apdu.push(0x00);
+        let nfc = MockReader::default().expect(&apdu, 0x63C2.into(), &[]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.set_max_auth_attempts(Some(1));
+        let err = card.external_authenticate(&[0; 0x28]).unwrap_err();
+        assert!(err.to_string().ends_with("2 tries remaining."));
+        assert_eq!(card.failed_auth_attempts(), 1);
+        assert_eq!(card.auth_retries_remaining(), Some(2));
+
+        // Refused before GET CHALLENGE, no further APDUs are expected.
+        let err = card
+            .basic_access_control(&mut rand::thread_rng(), "L898902C<369080619406236")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::AuthAttemptsExceeded { attempts: 1 })
+        ));
+
+        card.reset_auth_attempts();
+        assert!(card.check_auth_attempts().is_ok());
+    }
+
+    /// Example from ICAO 9303-11 section D.2
+    #[test]
+    fn test_seed_from_mrz_input() {
+        let mrz = MrzInput::new("L898902C", "690806", "940623").unwrap();
+        assert_eq!(
+            seed_from_mrz(&mrz.mrz_information()),
+            hex!("239AB9CB282DAF66231DC5A4DF6BFBAE")
+        );
+    }
+
+    #[test]
+    fn test_external_authenticate_length() {
+        // Rejected without reaching the chip.
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        assert!(card.external_authenticate(&[0; 39]).is_err());
+    }
+
+    /// Example from ICAO 9303-11 section D.3
+    #[test]
+    #[cfg(feature = "dangerous")]
+    fn test_bac_debug() {
+        let debug = bac_debug(
+            "L898902C<369080619406236",
+            &hex!("781723860C06C226"),
+            &hex!("4608F91988702212"),
+            &hex!("0B795240CB7049B01C19B33E32804F0B"),
+        );
+        assert_eq!(debug.seed, hex!("239AB9CB282DAF66231DC5A4DF6BFBAE"));
+        assert_eq!(debug.kenc, hex!("AB94FDECF2674FDFB9B391F85D7F76F2"));
+        assert_eq!(debug.kmac, hex!("7962D9ECE03D1ACD4C76089DCE131543"));
+        assert_eq!(
+            debug.external_authenticate,
+            hex!(
+                "00820000 28
+                72C29C2371CC9BDB65B779B8E8D37B29ECC154AA56A8799FAE2F498F76ED92F2
+                5F1448EEA8AD90A7
+                00"
+            )
+        );
+    }
+
+    /// Example from ICAO 9303-11 section D.3
+    #[test]
+    fn test_check_authentication_response() {
+        let cipher = TDesCipher::from_seed(&seed_from_mrz("L898902C<369080619406236"));
+        let rnd_ifd = hex!("781723860C06C226");
+        let rnd_ic = hex!("4608F91988702212");
+        let resp_data = hex!(
+            "46B9342A41396CD7386BF5803104D7CEDC122B9132139BAF2EEDC94EE178534F
+            2F2D235D074D7449"
+        );
+        let k_ic =
+            check_authentication_response(&cipher, &rnd_ifd, &rnd_ic, resp_data.to_vec()).unwrap();
+        assert_eq!(k_ic, hex!("0B4F80323EB3191CB04970CB4052790B"));
+
+        let mut bad_mac = resp_data.to_vec();
+        bad_mac[39] ^= 1;
+        assert!(matches!(
+            check_authentication_response(&cipher, &rnd_ifd, &rnd_ic, bad_mac),
+            Err(AuthError::Mismatch)
+        ));
+
+        let wrong_rnd_ic = hex!("0000000000000000");
+        assert!(matches!(
+            check_authentication_response(&cipher, &rnd_ifd, &wrong_rnd_ic, resp_data.to_vec()),
+            Err(AuthError::Mismatch)
+        ));
+    }
+}
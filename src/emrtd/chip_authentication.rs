@@ -1,5 +1,5 @@
 use {
-    super::Emrtd,
+    super::{Emrtd, State},
     crate::{
         asn1::{
             security_info::{KeyAgreement, SymmetricCipher},
@@ -7,22 +7,31 @@ use {
         },
         crypto::{ecka, EllipticCurve},
         emrtd::secure_messaging::construct_secure_messaging,
+        iso7816::CommandApdu,
     },
-    anyhow::{ensure, Result},
+    anyhow::{bail, ensure, Result},
     der::asn1::ObjectIdentifier as Oid,
     rand::{CryptoRng, RngCore},
 };
 
 impl Emrtd {
     pub fn chip_authenticate(&mut self, rng: impl CryptoRng + RngCore) -> Result<()> {
-        // TODO: Some passports only have ChipAuthenticationPublicKeyInfo but no ChipAuthenticationInfo. In this case, CA_(EC)DH_3DES_CBC_CBC should be assumed.
+        self.require_state(State::Session)?;
 
         // Read EF.DG14
         let ef_dg14 = self.read_cached::<EfDg14>()?;
         dbg!(&ef_dg14);
 
-        // Find the Chip Authentication Info in DG14
-        let (ca, pk) = ef_dg14.chip_authentication().unwrap();
+        // Find the Chip Authentication Info in DG14, paired with its public key by key id.
+        let keys = ef_dg14.chip_authentication_keys();
+        ensure!(
+            !keys.is_empty(),
+            "DG14 has no Chip Authentication public key."
+        );
+        let Some((ca, pk)) = ef_dg14.chip_authentication() else {
+            let key_ids: Vec<_> = keys.iter().map(|(_, pk)| pk.key_id).collect();
+            bail!("No usable ChipAuthenticationInfo for public keys with key ids {key_ids:?}.");
+        };
         println!("Using algorithm: {}", ca.protocol);
 
         // Make sure protocols matches the key.
@@ -66,53 +75,48 @@ impl Emrtd {
         let data = self.general_authenticate(&public_key.to_bytes())?;
         println!("==> General Authenticate: {}", hex::encode(data));
 
-        // Keys should now have been changed.
-        let cipher = SymmetricCipher::Aes256;
+        // Keys should now have been changed, derive them with the negotiated cipher.
+        let cipher = ca.protocol.cipher.unwrap_or(SymmetricCipher::Tdes);
         self.set_secure_messaging(construct_secure_messaging(cipher, &z, 0));
+        self.state = State::ChipAuthenticated;
+        // TODO: Report the session, see `SessionProtocol::ChipAuthentication`.
 
         Ok(())
     }
 
     pub fn mset_at(&mut self, protocol: Oid, key_id: Option<u64>) -> Result<()> {
         // Send MSE Set AT to select the Chip Authentication protocol.
-        let mut apdu = vec![0x00, 0x22, 0x41, 0xA4];
-        apdu.push(0x00); // Placeholder length
-
         // Cryptographic mechanism: 0x80 <len> <OID>
         let protocol = protocol.as_bytes();
-        apdu.push(0x80);
-        apdu.push(protocol.len().try_into()?);
-        apdu.extend_from_slice(protocol);
+        let mut data = vec![0x80, protocol.len().try_into()?];
+        data.extend_from_slice(protocol);
 
         // If the pivate key to be used has a reference, include it.
         if let Some(id) = key_id {
-            apdu.push(0x84);
-            apdu.push(0x01); // Assume id < 256
-            apdu.push(id.try_into()?);
+            data.push(0x84);
+            data.push(0x01); // Assume id < 256
+            data.push(id.try_into()?);
         }
 
-        // Update length
-        apdu[4] = (apdu.len() - 5).try_into()?;
-
         // Send MSE Set AT command to chip
-        let (status, data) = self.send_apdu(&apdu)?;
-        ensure!(status.is_success());
-        ensure!(data.is_empty());
+        let apdu = CommandApdu::new(0x00, 0x22, 0x41, 0xA4).with_data(data)?;
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        ensure!(response.is_success());
+        ensure!(response.data.is_empty());
         Ok(())
     }
 
     pub fn general_authenticate(&mut self, public_key: &[u8]) -> Result<Vec<u8>> {
         // Send General Authenticate command to chip
-        let mut apdu = vec![0x00, 0x86, 0x00, 0x00];
-        apdu.push((public_key.len() + 4).try_into()?);
-        apdu.push(0x7C);
-        apdu.push((public_key.len() + 2).try_into()?);
-        apdu.push(0x80);
-        apdu.push(public_key.len().try_into()?);
-        apdu.extend_from_slice(public_key);
-
-        let (status, data) = self.send_apdu(&apdu)?;
-        ensure!(status.is_success());
-        Ok(data)
+        // Dynamic authentication data with the ephemeral public key.
+        let mut data = vec![0x7C, (public_key.len() + 2).try_into()?];
+        data.push(0x80);
+        data.push(public_key.len().try_into()?);
+        data.extend_from_slice(public_key);
+
+        let apdu = CommandApdu::new(0x00, 0x86, 0x00, 0x00).with_data(data)?;
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        ensure!(response.is_success());
+        Ok(response.data)
     }
 }
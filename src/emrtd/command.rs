@@ -0,0 +1,193 @@
+//! Raw commands for debugging a chip.
+
+use {
+    super::{Emrtd, Result},
+    crate::{
+        ensure_err,
+        iso7816::{self, CommandApdu, Le, ResponseApdu},
+    },
+};
+
+/// A command sent in the clear by default, or under the current session if [`Self::protected`].
+///
+/// ```no_run
+/// # use icao_9303_nfc::emrtd::Emrtd;
+/// # fn example(card: &mut Emrtd) -> icao_9303_nfc::emrtd::Result<()> {
+/// let response = card
+///     .command(0x00, 0xB0, 0x9E, 0x00)
+///     .with_le(0x04)?
+///     .protected()
+///     .send(card)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Command {
+    pub apdu: CommandApdu,
+    pub protected: bool,
+}
+
+impl Emrtd {
+    /// Start a raw command, see [`Command`].
+    pub fn command(&self, cla: u8, ins: u8, p1: u8, p2: u8) -> Command {
+        Command {
+            apdu: CommandApdu::new(cla, ins, p1, p2),
+            protected: false,
+        }
+    }
+}
+
+impl Command {
+    /// Fails for more than 65535 bytes.
+    pub fn with_data(mut self, data: impl Into<Vec<u8>>) -> Result<Self> {
+        self.apdu = self.apdu.with_data(data)?;
+        Ok(self)
+    }
+
+    /// Expect up to `length` bytes, using extended length above 256.
+    ///
+    /// Fails for zero or more than 65536 bytes.
+    pub fn with_le(mut self, length: usize) -> Result<Self> {
+        ensure_err!(
+            (1..=Le::MAX_EXTENDED).contains(&length),
+            iso7816::Error::LeOutOfRange(length).into()
+        );
+        self.apdu = self.apdu.with_le(Le::new(length, length > Le::MAX_SHORT));
+        Ok(self)
+    }
+
+    /// Protect the command with the active Secure Messaging session, if any.
+    pub fn protected(mut self) -> Self {
+        self.protected = true;
+        self
+    }
+
+    /// Send the command, collecting `61XX` response data in the same mode.
+    pub fn send(self, card: &mut Emrtd) -> Result<ResponseApdu> {
+        let apdu = self.apdu.to_bytes();
+        if self.protected {
+            card.send_apdu(&apdu)
+        } else {
+            card.send_apdu_plain(&apdu)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            emrtd::{
+                secure_messaging::{tdes::TDesCipher, Cipher, Encrypted, SecureMessaging},
+                Error,
+            },
+            iso7816::StatusWord,
+            nfc::mock::MockReader,
+        },
+        hex_literal::hex,
+    };
+
+    #[test]
+    fn test_command_protected_and_plain() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+
+        let mut chip = Encrypted::new(TDesCipher::from_seed(&seed), ssc);
+        let read = chip.enc_apdu(&hex!("00 B0 9E 00 04")).unwrap();
+        let resp = chip.protect_response(StatusWord::SUCCESS, &hex!("6E 02 0101"));
+        let nfc = MockReader::default()
+            .expect(&read, StatusWord::SUCCESS, &resp)
+            .expect(
+                &hex!("00 B0 9C 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("31 02 0000"),
+            );
+
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.set_secure_messaging(Box::new(Encrypted::new(TDesCipher::from_seed(&seed), ssc)));
+        let response = card
+            .command(0x00, 0xB0, 0x9E, 0x00)
+            .with_le(4)
+            .unwrap()
+            .protected()
+            .send(&mut card)
+            .unwrap();
+        assert_eq!(response.data, hex!("6E 02 0101"));
+
+        // EF.CardAccess is read in the clear.
+        let response = card
+            .command(0x00, 0xB0, 0x9C, 0x00)
+            .with_le(4)
+            .unwrap()
+            .send(&mut card)
+            .unwrap();
+        assert_eq!(response.status, StatusWord::SUCCESS);
+        assert_eq!(response.data, hex!("31 02 0000"));
+    }
+
+    #[test]
+    fn test_command_not_supported() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+
+        // Unprotected `6D00` in a session, and a protected `6E00`.
+        let mut chip = Encrypted::new(TDesCipher::from_seed(&seed), ssc);
+        let internal_authenticate = chip
+            .enc_apdu(&hex!("00 88 0000 08 0102030405060708 00"))
+            .unwrap();
+        let get_data = chip.enc_apdu(&hex!("00 CA 0101 00")).unwrap();
+        let resp = chip.protect_response(StatusWord::CLA_NOT_SUPPORTED, &[]);
+        let nfc = MockReader::default()
+            .expect(&internal_authenticate, StatusWord::INS_NOT_SUPPORTED, &[])
+            .expect(&get_data, StatusWord::CLA_NOT_SUPPORTED, &resp);
+
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.set_secure_messaging(Box::new(Encrypted::new(TDesCipher::from_seed(&seed), ssc)));
+        let error = card
+            .command(0x00, 0x88, 0x00, 0x00)
+            .with_data(hex!("0102030405060708"))
+            .unwrap()
+            .with_le(256)
+            .unwrap()
+            .protected()
+            .send(&mut card)
+            .unwrap_err();
+        assert!(matches!(error, Error::NotSupported { ins: 0x88 }));
+        assert!(!error.ends_session());
+
+        let error = card
+            .command(0x00, 0xCA, 0x01, 0x01)
+            .with_le(256)
+            .unwrap()
+            .protected()
+            .send(&mut card)
+            .unwrap_err();
+        assert!(matches!(error, Error::NotSupported { ins: 0xCA }));
+    }
+
+    #[test]
+    fn test_apdu_too_short() {
+        // Rejected without reaching the chip.
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        for apdu in [&[][..], &[0x00], &hex!("00 B0 00")] {
+            assert!(matches!(
+                card.send_apdu_plain(apdu),
+                Err(Error::InvalidApdu(iso7816::Error::ApduTooShort))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_command_le_out_of_range() {
+        let card = Emrtd::new(Box::new(MockReader::default()));
+        for length in [0, 0x10001] {
+            assert!(matches!(
+                card.command(0x00, 0xB0, 0x00, 0x00).with_le(length),
+                Err(Error::InvalidApdu(iso7816::Error::LeOutOfRange(l))) if l == length
+            ));
+        }
+        let command = card.command(0x00, 0xB0, 0x00, 0x00).with_le(0x101).unwrap();
+        assert_eq!(command.apdu.le, Some(Le::Extended(0x101)));
+    }
+}
@@ -0,0 +1,141 @@
+use {
+    super::{Emrtd, Error, FileId, Result, State},
+    crate::{
+        iso7816::StatusWord,
+        lds::{
+            data_group::parse_dg1, face::parse_dg2, fingerprint::parse_dg3, parse_data_group,
+            public_key::parse_dg15, DataGroup, FaceImage, FingerprintRecord, MrzInput,
+            ParseOptions, PublicKeyInfo,
+        },
+    },
+};
+
+impl Emrtd {
+    /// Read and parse data group `number`.
+    ///
+    /// Returns Ok(None) if the data group is not present.
+    pub fn read_data_group(&mut self, number: u8, opts: ParseOptions) -> Result<Option<DataGroup>> {
+        let file = FileId::data_group(number).ok_or(Error::InvalidShortFileId)?;
+        self.require_state(State::Session)?;
+        let Some(bytes) = self.read_file_cached(file)? else {
+            return Ok(None);
+        };
+        Ok(Some(parse_data_group(number, &bytes, opts)?))
+    }
+
+    /// Read the facial images from EF.DG2.
+    ///
+    /// The images are returned as stored, JPEG or JPEG 2000 as indicated by
+    /// [`FaceImage::image_format`].
+    ///
+    /// See ICAO 9303-10 section 4.7.2.
+    pub fn read_dg2(&mut self) -> Result<Vec<FaceImage>> {
+        self.require_state(State::Session)?;
+        let dg2 = self
+            .read_file_cached(FileId::Dg2)?
+            .ok_or(Error::FileNotFound)?;
+        Ok(parse_dg2(&dg2)?)
+    }
+
+    /// Read the finger images from EF.DG3.
+    ///
+    /// DG3 is protected by Extended Access Control, so Terminal Authentication must have been
+    /// performed before reading.
+    ///
+    /// See ICAO 9303-10 section 4.7.3.
+    pub fn read_dg3(&mut self) -> Result<Vec<FingerprintRecord>> {
+        self.require_state(State::Session)?;
+        let dg3 = match self.read_file_cached(FileId::Dg3) {
+            Ok(Some(dg3)) => dg3,
+            Ok(None) => return Err(Error::FileNotFound),
+            Err(Error::ErrorResponse(StatusWord::ACCESS_DENIED)) => {
+                return Err(Error::TerminalAuthenticationRequired)
+            }
+            Err(e) => return Err(e),
+        };
+        Ok(parse_dg3(&dg3)?)
+    }
+
+    /// Read the Active Authentication public key from EF.DG15.
+    ///
+    /// This does not perform Active Authentication, the key can be shown or pinned on its
+    /// own. See ICAO 9303-10 section 4.7.15.
+    pub fn read_dg15(&mut self) -> Result<PublicKeyInfo> {
+        self.require_state(State::Session)?;
+        let dg15 = self
+            .read_file_cached(FileId::Dg15)?
+            .ok_or(Error::FileNotFound)?;
+        Ok(parse_dg15(&dg15)?)
+    }
+
+    /// Read the raw EF.SOD without parsing or verifying it.
+    ///
+    /// For handing the Document Security Object to a verifier elsewhere, this does not
+    /// need the `passive-auth` feature.
+    pub fn read_sod_raw(&mut self) -> Result<Vec<u8>> {
+        self.require_state(State::Session)?;
+        self.read_file_cached(FileId::Sod)?
+            .ok_or(Error::FileNotFound)
+    }
+
+    /// Check that the MRZ in EF.DG1 matches the MRZ information used for access control.
+    ///
+    /// A mismatch means the chip and the scanned data page disagree, e.g. because the chip
+    /// was swapped. Returns `false` if the session was not established with an MRZ.
+    pub fn mrz_matches_access_key(&mut self) -> Result<bool> {
+        self.require_state(State::Session)?;
+        let Some(access_key) = self.access_key.clone() else {
+            return Ok(false);
+        };
+        let dg1 = self
+            .read_file_cached(FileId::Dg1)?
+            .ok_or(Error::FileNotFound)?;
+        let mrz = MrzInput::from_mrz(&parse_dg1(&dg1)?)?;
+        Ok(mrz.mrz_information() == access_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::nfc::mock::MockReader, hex_literal::hex};
+
+    #[test]
+    fn test_read_data_group_requires_session() {
+        // No APDUs are expected, the precondition fails first.
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        assert!(matches!(
+            card.read_data_group(1, ParseOptions::default()),
+            Err(Error::InvalidState {
+                required: State::Session,
+                actual: State::MasterFile,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_read_sod_raw() {
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        card.state = State::Session;
+        card.file_cache.insert(FileId::Sod, None);
+        assert!(matches!(card.read_sod_raw(), Err(Error::FileNotFound)));
+        card.file_cache
+            .insert(FileId::Sod, Some(hex!("77 03 300100").to_vec()));
+        assert_eq!(card.read_sod_raw().unwrap(), hex!("77 03 300100"));
+    }
+
+    #[test]
+    fn test_mrz_matches_access_key() {
+        let mut dg1 = hex!("61 5B 5F1F 58").to_vec();
+        dg1.extend_from_slice(b"P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<");
+        dg1.extend_from_slice(b"L898902C36UTO7408122F1204159ZE184226B<<<<<10");
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        card.state = State::Session;
+        card.file_cache.insert(FileId::Dg1, Some(dg1));
+        assert!(!card.mrz_matches_access_key().unwrap());
+
+        card.access_key = Some("L898902C3674081221204159".to_owned());
+        assert!(card.mrz_matches_access_key().unwrap());
+        card.access_key = Some("D23145890774081221204159".to_owned());
+        assert!(!card.mrz_matches_access_key().unwrap());
+    }
+}
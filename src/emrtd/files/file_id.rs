@@ -7,8 +7,10 @@ use {
 
 pub const EMRTD_LDS1_AID: &[u8] = &[0xA0, 0x00, 0x00, 0x02, 0x47, 0x10, 0x01];
 
-// Unsupported optional AIDs, mostly requiring terminal authentication.
+/// LDS2 travel records application, see ICAO 9303-10 section 5.
 pub const EMRTD_TRAVEL_AID: &[u8] = &[0xA0, 0x00, 0x00, 0x02, 0x47, 0x20, 0x01];
+
+// Unsupported optional AIDs, mostly requiring terminal authentication.
 pub const EMRTD_VISA_AID: &[u8] = &[0xA0, 0x00, 0x00, 0x02, 0x47, 0x20, 0x02];
 pub const EMRTD_BIOMETRICS_AID: &[u8] = &[0xA0, 0x00, 0x00, 0x02, 0x47, 0x20, 0x03];
 
@@ -16,6 +18,7 @@ pub const EMRTD_BIOMETRICS_AID: &[u8] = &[0xA0, 0x00, 0x00, 0x02, 0x47, 0x20, 0x
 pub enum DedicatedId {
     MasterFile,
     EmrtdLds1,
+    TravelRecords,
     UnknownApplication(Vec<u8>),
 }
 
@@ -50,6 +53,7 @@ impl DedicatedId {
     pub fn from_aid(aid: &[u8]) -> Self {
         match aid {
             EMRTD_LDS1_AID => Self::EmrtdLds1,
+            EMRTD_TRAVEL_AID => Self::TravelRecords,
             _ => Self::UnknownApplication(aid.to_vec()),
         }
     }
@@ -58,6 +62,7 @@ impl DedicatedId {
         match self {
             Self::MasterFile => None,
             Self::EmrtdLds1 => Some(EMRTD_LDS1_AID),
+            Self::TravelRecords => Some(EMRTD_TRAVEL_AID),
             Self::UnknownApplication(aid) => Some(aid),
         }
     }
@@ -93,6 +98,29 @@ impl FileId {
         .copied()
     }
 
+    /// The file for data group `number`, if it is in 1..=16.
+    pub fn data_group(number: u8) -> Option<FileId> {
+        match number {
+            1 => Some(Self::Dg1),
+            2 => Some(Self::Dg2),
+            3 => Some(Self::Dg3),
+            4 => Some(Self::Dg4),
+            5 => Some(Self::Dg5),
+            6 => Some(Self::Dg6),
+            7 => Some(Self::Dg7),
+            8 => Some(Self::Dg8),
+            9 => Some(Self::Dg9),
+            10 => Some(Self::Dg10),
+            11 => Some(Self::Dg11),
+            12 => Some(Self::Dg12),
+            13 => Some(Self::Dg13),
+            14 => Some(Self::Dg14),
+            15 => Some(Self::Dg15),
+            16 => Some(Self::Dg16),
+            _ => None,
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self {
             Self::Com => "EF.COM",
@@ -130,6 +158,13 @@ impl FileId {
         }
     }
 
+    /// Whether the file content is a single TLV structure.
+    ///
+    /// EF.DIR and EF.ATR/INFO hold a sequence of data objects instead.
+    pub fn is_single_tlv(&self) -> bool {
+        !matches!(self, Self::Dir | Self::AttrInfo)
+    }
+
     pub fn file_id(&self) -> u16 {
         // CardAccess and Sod are the same, but live in different applications.
         match self {
@@ -198,3 +233,21 @@ impl Display for FileId {
         write!(f, "{}", self.name())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_group() {
+        for number in 1..=16 {
+            let file = FileId::data_group(number).unwrap();
+            assert_eq!(file.file_id(), 0x0100 + number as u16);
+            assert_eq!(file.short_id(), number);
+        }
+        // EF.SOD and EF.COM share the numbering, but are no data groups.
+        for number in [0, 17, 29, 30, 255] {
+            assert_eq!(FileId::data_group(number), None);
+        }
+    }
+}
@@ -2,23 +2,43 @@ mod file_id;
 
 pub use self::file_id::{DedicatedId, FileId};
 use {
-    super::{Emrtd, Error, Result},
+    self::file_id::EMRTD_LDS1_AID,
+    super::{Emrtd, Error, Result, State},
     crate::{
-        asn1::{EfCardAccess, EfDg14, EfSod},
+        asn1::{EfCardAccess, EfDg14, EfDg15},
         ensure_err,
-        iso7816::StatusWord,
+        iso7816::{CommandApdu, Le, ResponseApdu, StatusWord},
+        lds::{
+            atr_info::parse_atr_info,
+            dir::parse_dir,
+            fci::parse_fci_lenient,
+            tlv::{object_length, ObjectLength},
+            ApplicationTemplate, CardCapabilities, FileControlInfo,
+        },
     },
-    der::{Decode, ErrorKind, Reader, SliceReader},
+    der::Decode,
     std::collections::HashMap,
 };
 
 pub type FileCache = HashMap<FileId, Option<Vec<u8>>>;
 
+/// Number of bytes first read from a single TLV file to learn its length.
+///
+/// This covers a one byte tag and a length of up to three bytes.
+const HEADER_READ_LENGTH: usize = 4;
+
+/// Default number of bytes requested per READ BINARY.
+///
+/// Secure Messaging adds padding, DO'87', DO'99' and DO'8E' to the response. Reading 223
+/// bytes keeps the protected response within the 256 bytes of a short APDU.
+pub const DEFAULT_MAX_LE: usize = 223;
+
 pub trait HasFileId {
     const FILE_ID: FileId;
 }
 
-impl HasFileId for EfSod {
+#[cfg(feature = "passive-auth")]
+impl HasFileId for crate::asn1::EfSod {
     const FILE_ID: FileId = FileId::Sod;
 }
 
@@ -30,7 +50,33 @@ impl HasFileId for EfDg14 {
     const FILE_ID: FileId = FileId::Dg14;
 }
 
+impl HasFileId for EfDg15 {
+    const FILE_ID: FileId = FileId::Dg15;
+}
+
 impl Emrtd {
+    /// Read the card capabilities from EF.ATR/INFO.
+    ///
+    /// The result can be used to decide on extended length and the maximum read size.
+    ///
+    /// See ICAO 9303-10 section 3.11.1.
+    pub fn read_atr_info(&mut self) -> Result<CardCapabilities> {
+        let data = self
+            .read_file_cached(FileId::AttrInfo)?
+            .ok_or(Error::FileNotFound)?;
+        Ok(parse_atr_info(&data)?)
+    }
+
+    /// Read the list of applications on the card from EF.DIR.
+    ///
+    /// See ICAO 9303-10 section 3.11.3.
+    pub fn read_dir(&mut self) -> Result<Vec<ApplicationTemplate>> {
+        let data = self
+            .read_file_cached(FileId::Dir)?
+            .ok_or(Error::FileNotFound)?;
+        Ok(parse_dir(&data)?)
+    }
+
     pub fn read_cached<T: HasFileId + for<'a> Decode<'a>>(&mut self) -> Result<T> {
         let der = self
             .read_file_cached(T::FILE_ID)?
@@ -40,146 +86,974 @@ impl Emrtd {
 
     /// Retrieves a file with caching.
     ///
-    /// Assumes the file is a single TLV structure.
-    ///
     /// Returns Ok(None) if the file is not found.
     pub fn read_file_cached(&mut self, file: FileId) -> Result<Option<Vec<u8>>> {
         // Try cache first.
-        if let Some(entry) = self.file_cache.get(&file) {
-            return Ok(entry.clone());
+        if self.file_cache_enabled {
+            if let Some(entry) = self.file_cache.get(&file) {
+                return Ok(entry.clone());
+            }
         }
 
+        let result = self.read_file(file)?;
+
+        // Insert in cache
+        if self.file_cache_enabled {
+            self.file_cache.insert(file, result.clone());
+        }
+        Ok(result)
+    }
+
+    /// Reads a file from the card, bypassing the cache.
+    ///
+    /// Most files are a single TLV structure and are read up to the length in its header.
+    /// Others (see [`FileId::is_single_tlv`]) are read until the end of the file.
+    ///
+    /// Returns Ok(None) if the file is not found.
+    ///
+    /// EF.CardAccess is selected by file identifier if the short EF read fails with `6A82`
+    /// or `6700`, as some chips do not support short EF identifiers for it. Any file is
+    /// selected by file identifier if the chip rejects the short EF read with `6B00`. If
+    /// reading the selected file fails with `6B00` as well, it is empty.
+    ///
+    /// The first chunk is read by short EF, which has no room for larger offsets. The rest
+    /// is read by offset from the current file, the short EF read made it current. Chips
+    /// that respond `6986` (no current EF) get the file selected by file identifier.
+    pub fn read_file(&mut self, file: FileId) -> Result<Option<Vec<u8>>> {
         // Select parent file if necessary.
         if self.parent != file.parent() {
-            if let Some(application_id) = file.parent().aid() {
+            if file.parent() == DedicatedId::EmrtdLds1 {
+                self.select_lds1()?;
+            } else if let Some(application_id) = file.parent().aid() {
                 self.select_dedicated_file(application_id)?;
             } else {
                 self.select_master_file()?;
             }
         }
 
-        // Read file by short EF.
-        let mut result: Option<Vec<u8>> = match self.read_binary_short_ef(file.short_id()) {
+        // Read file by short EF, only the header if the length follows from it.
+        // Note b8 of p1 must be set to 1 to indicate that a short file id is used.
+        let max = if file.is_single_tlv() {
+            HEADER_READ_LENGTH
+        } else {
+            usize::MAX
+        };
+        let lowered_le = self.lowered_le;
+        let mut result: Option<Vec<u8>> = match self.read_binary(0x80 | self.short_id(file), 0, max)
+        {
             Ok(data) => Some(data),
+            Err(Error::ErrorResponse(StatusWord::FILE_NOT_FOUND | StatusWord::WRONG_LENGTH))
+                if file == FileId::CardAccess =>
+            {
+                // The short EF failure says nothing about supported lengths.
+                self.lowered_le = lowered_le;
+                return self.select_and_read_file(file);
+            }
             Err(Error::ErrorResponse(StatusWord::FILE_NOT_FOUND)) => None,
+            // Nothing was read yet, so this rejects the short EF rather than the offset.
+            Err(Error::ErrorResponse(StatusWord::WRONG_P1P2)) => {
+                return self.select_and_read_file(file)
+            }
             Err(e) => return Err(e),
         };
         if let Some(result) = result.as_mut() {
-            loop {
-                // Check if we are done by parsing the header.
-                if sniff_len(result)? <= Some(result.len()) {
-                    break;
-                }
-                let chunk = self.read_binary_offset(result.len())?;
-                if chunk.is_empty() {
-                    break;
+            match self.read_rest(result, file.is_single_tlv()) {
+                Err(Error::ErrorResponse(StatusWord::NO_CURRENT_EF)) => {
+                    self.select_elementary_file(file.file_id())?;
+                    self.read_rest(result, file.is_single_tlv())?;
                 }
-                result.extend(&chunk);
+                other => other?,
             }
+        }
+        Ok(result)
+    }
 
-            // Some (e.g. Polish) passports will zero-extend the file on READ BINARY OFFSET
-            // commands. Trim the file to the actual length.
-            let expected_len = sniff_len(result)?.ok_or(Error::ResponseDataUnexpected)?;
-            ensure_err!(result.len() >= expected_len, Error::ResponseDataUnexpected);
-            result.truncate(expected_len);
+    /// Select `file` by file identifier and read it, `None` if it is not found.
+    fn select_and_read_file(&mut self, file: FileId) -> Result<Option<Vec<u8>>> {
+        match self.select_and_read(file.file_id()) {
+            Ok(data) => Ok(Some(data)),
+            Err(Error::ErrorResponse(StatusWord::FILE_NOT_FOUND)) => Ok(None),
+            Err(e) => Err(e),
         }
+    }
 
-        // Insert in cache
-        self.file_cache.insert(file, result.clone());
+    /// Short EF identifier of `file`, as advertised in the application FCI or standard.
+    fn short_id(&self, file: FileId) -> u8 {
+        self.fci_short_ef_ids
+            .iter()
+            .find(|&&(file_id, _)| file_id == file.file_id())
+            .map_or(file.short_id(), |&(_, short_id)| short_id)
+    }
+
+    /// Select an elementary file in the current application by file identifier and read it.
+    ///
+    /// Use this for files without a short EF identifier. Both commands go through the active
+    /// Secure Messaging session, if any. Known single TLV files are read up to the length in
+    /// their header, others until the end of the file.
+    pub fn select_and_read(&mut self, file: u16) -> Result<Vec<u8>> {
+        self.select_elementary_file(file)?;
+        let single_tlv = FileId::iter()
+            .find(|id| id.file_id() == file && id.parent() == self.parent)
+            .is_some_and(|id| id.is_single_tlv());
+        let max = if single_tlv {
+            HEADER_READ_LENGTH
+        } else {
+            usize::MAX
+        };
+        let mut result = self.read_chunk(0, max)?;
+        self.read_rest(&mut result, single_tlv)?;
         Ok(result)
     }
 
-    pub fn select_master_file(&mut self) -> Result<()> {
+    /// Continue reading the current file after the first chunk in `result`.
+    fn read_rest(&mut self, result: &mut Vec<u8>, single_tlv: bool) -> Result<()> {
+        if single_tlv {
+            self.read_tlv_remainder(result)
+        } else {
+            self.read_remainder(result)
+        }
+    }
+
+    /// Continue reading the current file until the TLV structure is complete.
+    ///
+    /// The total length is decoded from the header, after which exactly the remaining bytes
+    /// are requested. Objects with indefinite length are read until the end of the file.
+    /// An empty file is returned as is.
+    fn read_tlv_remainder(&mut self, result: &mut Vec<u8>) -> Result<()> {
+        // Provisioned but unpopulated files have no header to decode.
+        if result.is_empty() {
+            return Ok(());
+        }
+        let expected_len = loop {
+            match object_length(result)? {
+                ObjectLength::Definite(length) => break length,
+                ObjectLength::Indefinite => return self.read_to_end(result),
+                ObjectLength::Incomplete => {
+                    let chunk = self.read_chunk(result.len(), HEADER_READ_LENGTH)?;
+                    ensure_err!(!chunk.is_empty(), Error::ResponseDataUnexpected);
+                    result.extend(&chunk);
+                }
+            }
+        };
+        while result.len() < expected_len {
+            let chunk = self.read_chunk(result.len(), expected_len - result.len())?;
+            if chunk.is_empty() {
+                break;
+            }
+            result.extend(&chunk);
+        }
+
+        // Some (e.g. Polish) passports will zero-extend the file on READ BINARY OFFSET
+        // commands. Trim the file to the actual length.
+        ensure_err!(result.len() >= expected_len, Error::ResponseDataUnexpected);
+        result.truncate(expected_len);
+        Ok(())
+    }
+
+    /// Continue reading the current file until the end of file is reached.
+    ///
+    /// Only continues if the previous read returned a full chunk.
+    fn read_remainder(&mut self, result: &mut Vec<u8>) -> Result<()> {
+        if result.len() == self.max_chunk() {
+            self.read_to_end(result)?;
+        }
+        Ok(())
+    }
+
+    /// Read full chunks until the end of file is reached.
+    ///
+    /// The end is signalled by a short (or empty) read, or by the chip rejecting the offset
+    /// with `6B00` when the file length is a multiple of the chunk size.
+    fn read_to_end(&mut self, result: &mut Vec<u8>) -> Result<()> {
+        loop {
+            let chunk = self.read_chunk(result.len(), usize::MAX)?;
+            let full = chunk.len() == self.max_chunk();
+            result.extend(&chunk);
+            if !full {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read at most `max` bytes at `offset`, returning no data past the end of file.
+    ///
+    /// The file is current, so `6B00` means the offset is past its end. At offset zero the
+    /// file is empty.
+    fn read_chunk(&mut self, offset: usize, max: usize) -> Result<Vec<u8>> {
+        match self.read_binary_at(offset, max) {
+            Err(Error::ErrorResponse(StatusWord::WRONG_P1P2)) => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    /// Select the master file, returning its FCI if the chip sends one.
+    pub fn select_master_file(&mut self) -> Result<FileControlInfo> {
         // Select by file identifier
         // See ISO/IEC 7816-4 section 11.2.2
-        let (status, data) = self.send_apdu(&[0x00, 0xA4, 0x00, 0x0C, 0x02, 0x3F, 0x00])?;
-        ensure_err!(status.is_success(), status.into());
+        let apdu = CommandApdu::new(0x00, 0xA4, 0x00, 0x0C);
+        let mut response = self.send_apdu(&apdu.clone().with_data([0x3F, 0x00])?.to_bytes())?;
+        if response.is_not_found() || response.status == StatusWord::INCORRECT_P1P2 {
+            // Some chips do not expose 3F00 as a file identifier, but accept an empty
+            // data field to select the MF.
+            response = self.send_apdu(&apdu.to_bytes())?;
+        }
+        ensure_err!(response.is_success(), response.status.into());
+        let fci = parse_fci_lenient(&response.data);
         self.parent = DedicatedId::MasterFile;
-        ensure_err!(data.is_empty(), Error::ResponseDataUnexpected);
-        Ok(())
+        self.fci_short_ef_ids.clear();
+        self.selected(State::MasterFile);
+        Ok(fci)
     }
 
-    pub fn select_dedicated_file(&mut self, application_id: &[u8]) -> Result<()> {
+    /// Select an application by AID, returning its FCI.
+    ///
+    /// Fails with [`Error::ApplicationNotFound`] if the chip answers `6999` (applet
+    /// selection failed).
+    pub fn select_dedicated_file(&mut self, application_id: &[u8]) -> Result<FileControlInfo> {
         if application_id.len() > 16 {
             return Err(Error::InvalidApplicationId);
         }
-        let mut apdu = vec![0x00, 0xA4, 0x04, 0x0C, application_id.len() as u8];
-        apdu.extend_from_slice(application_id);
-        let (status, data) = self.send_apdu(&apdu)?;
-        ensure_err!(status.is_success(), status.into());
+        // Request the FCI, it may indicate the Secure Messaging cipher.
+        // See ISO/IEC 7816-4 section 11.2.2
+        let mut apdu = CommandApdu::new(0x00, 0xA4, 0x04, 0x00)
+            .with_data(application_id)?
+            .with_le(Le::max(false));
+        let mut response = self.send_apdu(&apdu.to_bytes())?;
+        if matches!(
+            response.status,
+            StatusWord::INCORRECT_P1P2 | StatusWord::WRONG_P1P2
+        ) {
+            // Some chips only support selection without response data.
+            apdu.p2 = 0x0C;
+            apdu.le = None;
+            response = self.send_apdu(&apdu.to_bytes())?;
+        }
+        ensure_err!(
+            response.status != StatusWord::APPLET_SELECT_FAILED,
+            Error::ApplicationNotFound(application_id.to_vec())
+        );
+        ensure_err!(response.is_success(), response.status.into());
+        // The selection succeeded, an FCI that is not understood is no reason to fail it.
+        let fci = parse_fci_lenient(&response.data);
         self.parent = DedicatedId::from_aid(application_id);
-        ensure_err!(data.is_empty(), Error::ResponseDataUnexpected);
-        Ok(())
+        self.selected(State::Application);
+        self.fci_sm_cipher = fci.sm_cipher;
+        self.fci_short_ef_ids = fci.short_ef_ids.clone();
+        Ok(fci)
+    }
+
+    /// Select the eMRTD LDS1 application, falling back to the applications in EF.DIR.
+    ///
+    /// Some chips answer `6999` (applet selection failed) or `6A82` to the standard AID,
+    /// but list a longer AID starting with it in EF.DIR. An application selected that way
+    /// is treated as LDS1. Other applications in EF.DIR, e.g. the LDS2 applications, are
+    /// not considered.
+    pub fn select_lds1(&mut self) -> Result<FileControlInfo> {
+        match self.select_dedicated_file(EMRTD_LDS1_AID) {
+            Err(
+                Error::ApplicationNotFound(_) | Error::ErrorResponse(StatusWord::FILE_NOT_FOUND),
+            ) => {}
+            result => return result,
+        }
+        let error = Error::ApplicationNotFound(EMRTD_LDS1_AID.to_vec());
+        let Ok(applications) = self.read_dir() else {
+            return Err(error);
+        };
+        for application in applications {
+            let aid = application.aid;
+            if aid.len() <= EMRTD_LDS1_AID.len() || !aid.starts_with(EMRTD_LDS1_AID) {
+                continue;
+            }
+            match self.select_dedicated_file(&aid) {
+                Ok(fci) => {
+                    self.parent = DedicatedId::EmrtdLds1;
+                    return Ok(fci);
+                }
+                Err(
+                    Error::ApplicationNotFound(_)
+                    | Error::ErrorResponse(StatusWord::FILE_NOT_FOUND),
+                ) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(error)
     }
 
-    pub fn select_elementary_file(&mut self, file: u16) -> Result<()> {
+    /// Select an elementary file without requesting its FCP, see [`Self::select_elementary_file_fcp`].
+    pub fn select_elementary_file(&mut self, file: u16) -> Result<FileControlInfo> {
         // Select by elementary file by file identifier.
         // Not the application DF has to be previously selected.
         // See ISO/IEC 7816-4 section 11.2.2
         // See ICAO 9303-10 section 3.6.2
-        let file_bytes = file.to_be_bytes();
-        let (status, data) =
-            self.send_apdu(&[0x00, 0xA4, 0x02, 0x0C, 0x02, file_bytes[0], file_bytes[1]])?;
-        ensure_err!(status.is_success(), status.into());
-        ensure_err!(data.is_empty(), Error::ResponseDataUnexpected);
-        Ok(())
+        let apdu = CommandApdu::new(0x00, 0xA4, 0x02, 0x0C).with_data(file.to_be_bytes())?;
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        ensure_err!(response.is_success(), response.status.into());
+        Ok(parse_fci_lenient(&response.data))
+    }
+
+    /// Select an elementary file and request its FCP, e.g. for the file size.
+    ///
+    /// Falls back to selection without response data if the chip rejects the request, the
+    /// result is then empty.
+    pub fn select_elementary_file_fcp(&mut self, file: u16) -> Result<FileControlInfo> {
+        let apdu = CommandApdu::new(0x00, 0xA4, 0x02, 0x04)
+            .with_data(file.to_be_bytes())?
+            .with_le(Le::max(false));
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        if matches!(
+            response.status,
+            StatusWord::INCORRECT_P1P2 | StatusWord::WRONG_P1P2
+        ) {
+            return self.select_elementary_file(file);
+        }
+        ensure_err!(response.is_success(), response.status.into());
+        Ok(parse_fci_lenient(&response.data))
     }
 
-    /// Read binary data from an elementary file using a Short EF identifier.
+    /// Read binary data from an elementary file using a Short EF identifier, from `offset`.
     ///
-    /// This is the recommended way to read data from an elementary file.
+    /// This is the recommended way to read data from an elementary file. The short EF form
+    /// only has room for offsets up to 255 in P2. Larger offsets select the file by file
+    /// identifier first, which requires a known file of the selected application.
     ///
     /// See ICAO 9303-10 section 3.6.3.2 and ISO 7816-4 section 11.3.3.
     // TODO: Check for extended length support before using.
     // See ICAO 9303-10 section 3.6.4.2.
-    pub fn read_binary_short_ef(&mut self, file: u8) -> Result<Vec<u8>> {
+    pub fn read_binary_short_ef(&mut self, file: u8, offset: usize) -> Result<Vec<u8>> {
         if file > 0x1F {
             return Err(Error::InvalidShortFileId);
         }
-        // Note b8 of p2 must be set to 1 to indicate that a short file id is used.
-        // Setting P2 to 0 means 'offset zero'.
-        // Setting Le to 0x00 means read up to 256 / 65536.
-        let apdu = if self.extended_length {
-            // Setting Le to 0x000000 means 'read all' with extended length.
-            &[0x00, 0xB0, 0x80 | file, 0x00, 0x00, 0x00, 0x00][..]
-        } else {
-            // Setting Le to 0x00 means 'read all'.
-            &[0x00, 0xB0, 0x80 | file, 0x00, 0x00][..]
-        };
-        let (status, data) = self.send_apdu(apdu)?;
-        ensure_err!(status.is_success(), status.into());
-        Ok(data)
+        if let Ok(offset) = u8::try_from(offset) {
+            // Note b8 of p1 must be set to 1 to indicate that a short file id is used.
+            return self.read_binary(0x80 | file, offset, usize::MAX);
+        }
+        let file_id = FileId::iter()
+            .find(|&id| id.parent() == self.parent && self.short_id(id) == file)
+            .ok_or(Error::InvalidShortFileId)?
+            .file_id();
+        self.select_elementary_file(file_id)?;
+        self.read_binary_offset(offset)
     }
 
     /// Reads the current file at a given offset.
     pub fn read_binary_offset(&mut self, offset: usize) -> Result<Vec<u8>> {
+        self.read_binary_at(offset, usize::MAX)
+    }
+
+    fn read_binary_at(&mut self, offset: usize, max: usize) -> Result<Vec<u8>> {
         // TODO: use B1 for large offsets.
         ensure_err!(offset < (1 << 15), Error::ResponseTooLong);
         let offset = (offset as u16).to_be_bytes();
-
-        // Setting Le to 0x00 means 'read all'.
-        // See ISO 7816-4 section 11.3.3.
         // NOTE: Polish passports will zero-pad the response to 256 bytes, going beyond EOF.
-        let (status, data) = self.send_apdu(&[0x00, 0xB0, offset[0], offset[1], 0x00])?;
-        ensure_err!(status.is_success(), status.into());
-        Ok(data)
+        self.read_binary(offset[0], offset[1], max)
     }
-}
 
-/// Sniff the size of a TLV encoded data structure.
-fn sniff_len(bytes: &[u8]) -> Result<Option<usize>> {
-    // Check if we are done by parsing the header.
-    match SliceReader::new(bytes)?.peek_header() {
-        Ok(header) => {
-            let total_len: u32 = header.length.for_tlv()?.into();
-            Ok(Some(total_len as usize))
-        }
-        Err(e) => {
-            if let ErrorKind::Incomplete { .. } = e.kind() {
-                Ok(None)
-            } else {
-                return Err(e.into());
+    /// Send READ BINARY, requesting at most `max` and [`Self::max_chunk`] bytes.
+    ///
+    /// Some chips respond with `6700` (wrong length) instead of `6CXX` when asked for more
+    /// than their buffer holds. In that case Le is halved until the chip accepts it, and the
+    /// result is remembered for subsequent reads.
+    ///
+    /// See ISO 7816-4 section 11.3.3.
+    fn read_binary(&mut self, p1: u8, p2: u8, max: usize) -> Result<Vec<u8>> {
+        loop {
+            let le = self.max_chunk().min(max);
+            let apdu =
+                CommandApdu::new(0x00, 0xB0, p1, p2).with_le(Le::new(le, self.extended_length));
+            let ResponseApdu { status, data } = self.send_apdu(&apdu.to_bytes())?;
+            if status == StatusWord::WRONG_LENGTH && le > 1 {
+                self.lowered_le = Some(le / 2);
+                continue;
             }
+            // End of file before Le bytes still returns the data that was read.
+            ensure_err!(
+                status.is_success() || status == StatusWord::END_OF_FILE,
+                status.into()
+            );
+            return Ok(data);
         }
     }
+
+    /// The number of bytes requested per READ BINARY.
+    fn max_chunk(&self) -> usize {
+        let limit = if self.extended_length { 0x10000 } else { 0x100 };
+        let max_le = self.max_le.map_or(limit, |max_le| max_le.clamp(1, limit));
+        self.lowered_le.map_or(max_le, |le| le.clamp(1, max_le))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            asn1::security_info::SymmetricCipher,
+            emrtd::secure_messaging::{tdes::TDesCipher, Cipher, Encrypted, SecureMessaging},
+            lds::tlv::parse_tlv,
+            nfc::mock::MockReader,
+        },
+        hex_literal::hex,
+    };
+
+    #[test]
+    fn test_select_and_read() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 A4 02 0C 02 01 1E"), StatusWord::SUCCESS, &[])
+            .expect(
+                &hex!("00 B0 00 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("60 03 5F01"),
+            )
+            // Zero-extended beyond the requested length.
+            .expect(
+                &hex!("00 B0 00 04 01"),
+                StatusWord::SUCCESS,
+                &hex!("00 0000"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        assert_eq!(card.select_and_read(0x011E).unwrap(), hex!("60 03 5F0100"));
+    }
+
+    #[test]
+    fn test_select_and_read_chunks() {
+        // Read in chunks of the default size until a short read.
+        let file: Vec<u8> = (0..600).map(|i| i as u8).collect();
+        let nfc = MockReader::default()
+            .expect(&hex!("00 A4 02 0C 02 0120"), StatusWord::SUCCESS, &[])
+            .expect(&hex!("00 B0 00 00 DF"), StatusWord::SUCCESS, &file[..0xDF])
+            .expect(
+                &hex!("00 B0 00 DF DF"),
+                StatusWord::SUCCESS,
+                &file[0xDF..0x1BE],
+            )
+            .expect(&hex!("00 B0 01 BE DF"), StatusWord::SUCCESS, &file[0x1BE..]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        assert_eq!(card.select_and_read(0x0120).unwrap(), file);
+    }
+
+    #[test]
+    fn test_read_binary_short_ef_offset() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 9E 10 DF"), StatusWord::SUCCESS, &[0x42; 0x10])
+            // Beyond the short EF offset range, EF.COM is selected by file identifier.
+            .expect(&hex!("00 A4 02 0C 02 01 1E"), StatusWord::SUCCESS, &[])
+            .expect(&hex!("00 B0 01 00 DF"), StatusWord::SUCCESS, &[0x42; 0x10]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        assert_eq!(card.read_binary_short_ef(0x1E, 0x10).unwrap(), [0x42; 0x10]);
+        assert_eq!(
+            card.read_binary_short_ef(0x1E, 0x100).unwrap(),
+            [0x42; 0x10]
+        );
+        assert!(matches!(
+            card.read_binary_short_ef(0x1F, 0x100),
+            Err(Error::InvalidShortFileId)
+        ));
+    }
+
+    #[test]
+    fn test_read_binary_get_response_chained() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 9E 00 DF"), 0x6140.into(), &[])
+            .expect(&hex!("00 C0 00 00 40"), 0x6120.into(), &[0x42; 0x40])
+            .expect(&hex!("00 C0 00 00 20"), StatusWord::SUCCESS, &[0x43; 0x20]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        let data = card.read_binary_short_ef(0x1E, 0).unwrap();
+        assert_eq!(data, [[0x42; 0x40].as_slice(), &[0x43; 0x20]].concat());
+    }
+
+    #[test]
+    fn test_get_response_limit() {
+        let mut nfc = MockReader::default().expect(&hex!("00 B0 9E 00 DF"), 0x6101.into(), &[0x42]);
+        for _ in 0..256 {
+            nfc = nfc.expect(&hex!("00 C0 00 00 01"), 0x6101.into(), &[0x42]);
+        }
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert!(matches!(
+            card.read_binary_short_ef(0x1E, 0),
+            Err(Error::ResponseTooLong)
+        ));
+    }
+
+    #[test]
+    fn test_read_binary_wrong_length() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 9E 00 00"), StatusWord::WRONG_LENGTH, &[])
+            .expect(&hex!("00 B0 9E 00 80"), StatusWord::WRONG_LENGTH, &[])
+            .expect(&hex!("00 B0 9E 00 40"), StatusWord::SUCCESS, &[0x42; 0x40])
+            .expect(&hex!("00 B0 00 40 40"), StatusWord::SUCCESS, &[0x42; 0x10]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.set_max_le(None);
+        assert_eq!(card.read_binary_short_ef(0x1E, 0).unwrap(), [0x42; 0x40]);
+        assert_eq!(card.max_chunk(), 0x40);
+        assert_eq!(card.read_binary_offset(0x40).unwrap(), [0x42; 0x10]);
+
+        // Reconnecting forgets the lowered Le, but not the configured limit.
+        card.connect().unwrap();
+        assert_eq!(card.max_chunk(), 0x100);
+        card.set_max_le(Some(0x20));
+        card.connect().unwrap();
+        assert_eq!(card.max_chunk(), 0x20);
+    }
+
+    #[test]
+    fn test_read_card_access_without_short_ef() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 9C 00 04"), StatusWord::WRONG_LENGTH, &[])
+            .expect(&hex!("00 B0 9C 00 02"), StatusWord::WRONG_LENGTH, &[])
+            .expect(&hex!("00 B0 9C 00 01"), StatusWord::WRONG_LENGTH, &[])
+            .expect(&hex!("00 A4 02 0C 02 011C"), StatusWord::SUCCESS, &[])
+            .expect(
+                &hex!("00 B0 00 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("31 02 0000"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert_eq!(
+            card.read_file(FileId::CardAccess).unwrap(),
+            Some(hex!("31 02 0000").to_vec())
+        );
+        assert_eq!(card.max_chunk(), DEFAULT_MAX_LE);
+    }
+
+    #[test]
+    fn test_select_master_file_fallback() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 A4 00 0C 02 3F00"),
+                StatusWord::FILE_NOT_FOUND,
+                &[],
+            )
+            .expect(&hex!("00 A4 00 0C"), StatusWord::SUCCESS, &[]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.select_master_file().unwrap();
+        assert_eq!(card.parent, DedicatedId::MasterFile);
+    }
+
+    #[test]
+    fn test_select_elementary_file_fcp() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 A4 02 04 02 011E 00"),
+                StatusWord::SUCCESS,
+                &hex!("62 0D 80 02 001A 82 01 01 83 02 011E 88 00"),
+            )
+            .expect(
+                &hex!("00 A4 02 04 02 0101 00"),
+                StatusWord::INCORRECT_P1P2,
+                &[],
+            )
+            .expect(&hex!("00 A4 02 0C 02 0101"), StatusWord::SUCCESS, &[]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        let fcp = card.select_elementary_file_fcp(0x011E).unwrap();
+        assert_eq!(fcp.file_size, Some(26));
+        assert_eq!(fcp.file_id, Some(0x011E));
+        assert_eq!(fcp.short_file_id, None);
+        let fcp = card.select_elementary_file_fcp(0x0101).unwrap();
+        assert_eq!(fcp, FileControlInfo::default());
+    }
+
+    #[test]
+    fn test_select_unknown_response_data() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 A4 00 0C 02 3F00"),
+                StatusWord::SUCCESS,
+                &hex!("9F"),
+            )
+            .expect(
+                &hex!("00 A4 02 04 02 011E 00"),
+                StatusWord::SUCCESS,
+                &hex!("62 07 80 02 001A 83 01 01"),
+            )
+            .expect(
+                &hex!("00 A4 02 0C 02 0101"),
+                StatusWord::SUCCESS,
+                &hex!("A5 00"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        card.state = State::Application;
+        assert_eq!(
+            card.select_master_file().unwrap(),
+            FileControlInfo::default()
+        );
+        assert_eq!(card.parent, DedicatedId::MasterFile);
+        let fcp = card.select_elementary_file_fcp(0x011E).unwrap();
+        assert_eq!(fcp.file_size, Some(26));
+        assert_eq!(
+            card.select_elementary_file(0x0101).unwrap(),
+            FileControlInfo::default()
+        );
+    }
+
+    #[test]
+    fn test_select_lds1_from_dir() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 A4 04 00 07 A0000002471001 00"),
+                StatusWord::APPLET_SELECT_FAILED,
+                &[],
+            )
+            .expect(
+                &hex!("00 B0 9E 00 DF"),
+                StatusWord::SUCCESS,
+                &hex!("61 0A 4F 08 A000000247100101"),
+            )
+            .expect(
+                &hex!("00 A4 04 00 08 A000000247100101 00"),
+                StatusWord::SUCCESS,
+                &[],
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.select_lds1().unwrap();
+        assert_eq!(card.parent, DedicatedId::EmrtdLds1);
+
+        let nfc = MockReader::default().expect(
+            &hex!("00 A4 04 00 07 A0000002472001 00"),
+            StatusWord::APPLET_SELECT_FAILED,
+            &[],
+        );
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert!(matches!(
+            card.select_dedicated_file(&hex!("A0000002472001")),
+            Err(Error::ApplicationNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_select_lds1_skips_other_applications() {
+        // EF.DIR lists the LDS2 visa and biometrics applications, but no LDS1 extension.
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 A4 04 00 07 A0000002471001 00"),
+                StatusWord::FILE_NOT_FOUND,
+                &[],
+            )
+            .expect(
+                &hex!("00 B0 9E 00 DF"),
+                StatusWord::SUCCESS,
+                &hex!("61 09 4F 07 A0000002472002 61 09 4F 07 A0000002472003"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert!(matches!(
+            card.select_lds1(),
+            Err(Error::ApplicationNotFound(aid)) if aid == EMRTD_LDS1_AID
+        ));
+        assert_eq!(card.parent, DedicatedId::MasterFile);
+
+        // Outside of the fallback, `6A82` is reported as is.
+        let nfc = MockReader::default().expect(
+            &hex!("00 A4 04 00 07 A0000002472001 00"),
+            StatusWord::FILE_NOT_FOUND,
+            &[],
+        );
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert!(matches!(
+            card.select_dedicated_file(&hex!("A0000002472001")),
+            Err(Error::ErrorResponse(StatusWord::FILE_NOT_FOUND))
+        ));
+    }
+
+    #[test]
+    fn test_read_file_fci_short_ef_id() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 A4 04 00 07 A0000002471001 00"),
+                StatusWord::SUCCESS,
+                &hex!("6F 09 62 07 83 02 0101 88 01 28"),
+            )
+            .expect(
+                &hex!("00 B0 85 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("61 02 0000"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert_eq!(
+            card.read_file(FileId::Dg1).unwrap(),
+            Some(hex!("61 02 0000").to_vec())
+        );
+    }
+
+    #[test]
+    fn test_select_application_unknown_fci() {
+        let nfc = MockReader::default().expect(
+            &hex!("00 A4 04 00 07 A0000002471001 00"),
+            StatusWord::SUCCESS,
+            &hex!("A5 03 80 01 00"),
+        );
+        let mut card = Emrtd::new(Box::new(nfc));
+        let fci = card.select_dedicated_file(&hex!("A0000002471001")).unwrap();
+        assert_eq!(fci, FileControlInfo::default());
+        assert_eq!(card.parent, DedicatedId::EmrtdLds1);
+        assert_eq!(card.state(), State::Application);
+    }
+
+    #[test]
+    fn test_select_application_fci_cipher() {
+        // FCI indicating id-PACE-ECDH-GM-AES-CBC-CMAC-128.
+        let fci = hex!("6F 10 A5 0E B4 0C 80 0A 04007F00070202040202");
+        let nfc = MockReader::default().expect(
+            &hex!("00 A4 04 00 07 A0000002471001 00"),
+            StatusWord::SUCCESS,
+            &fci,
+        );
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.select_dedicated_file(&hex!("A0000002471001")).unwrap();
+        assert_eq!(card.parent, DedicatedId::EmrtdLds1);
+        assert_eq!(card.fci_sm_cipher(), Some(SymmetricCipher::Aes128));
+        assert!(card.warnings().is_empty());
+
+        card.set_secure_messaging(Box::new(Encrypted::new(TDesCipher::from_seed(&[0; 16]), 0)));
+        assert_eq!(card.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_read_file_cached() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 B0 9C 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("31 02 0101"),
+            )
+            // A different card after reconnecting.
+            .expect(
+                &hex!("00 B0 9C 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("31 02 0202"),
+            )
+            // Without a cache every read reaches the chip.
+            .expect(
+                &hex!("00 B0 9C 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("31 02 0303"),
+            )
+            .expect(
+                &hex!("00 B0 9C 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("31 02 0404"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        for _ in 0..2 {
+            assert_eq!(
+                card.read_file_cached(FileId::CardAccess).unwrap(),
+                Some(hex!("31 02 0101").to_vec())
+            );
+        }
+
+        card.connect().unwrap();
+        for _ in 0..2 {
+            assert_eq!(
+                card.read_file_cached(FileId::CardAccess).unwrap(),
+                Some(hex!("31 02 0202").to_vec())
+            );
+        }
+
+        card.set_file_cache_enabled(false);
+        for expected in [hex!("31 02 0303"), hex!("31 02 0404")] {
+            assert_eq!(
+                card.read_file_cached(FileId::CardAccess).unwrap(),
+                Some(expected.to_vec())
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_file_length() {
+        // Three byte length, the remainder is requested exactly.
+        let mut dg1 = hex!("61 82 0100").to_vec();
+        dg1.extend_from_slice(&[0x42; 0x100]);
+        // Two byte length, split over the header read.
+        let mut dg11 = hex!("6B 81 90").to_vec();
+        dg11.extend_from_slice(&[0x42; 0x90]);
+        // Indefinite length, read until a short chunk.
+        let mut dg2 = hex!("75 80").to_vec();
+        dg2.extend_from_slice(&[0x42; 0x12]);
+
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 81 00 04"), StatusWord::SUCCESS, &dg1[..4])
+            .expect(&hex!("00 B0 00 04 DF"), StatusWord::SUCCESS, &dg1[4..0xE3])
+            .expect(&hex!("00 B0 00 E3 21"), StatusWord::SUCCESS, &dg1[0xE3..])
+            .expect(&hex!("00 B0 8B 00 04"), StatusWord::SUCCESS, &dg11[..4])
+            .expect(&hex!("00 B0 00 04 8F"), StatusWord::SUCCESS, &dg11[4..])
+            .expect(&hex!("00 B0 82 00 04"), StatusWord::SUCCESS, &dg2[..4])
+            .expect(&hex!("00 B0 00 04 DF"), StatusWord::SUCCESS, &dg2[4..]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        assert_eq!(card.read_file(FileId::Dg1).unwrap(), Some(dg1));
+        assert_eq!(card.read_file(FileId::Dg11).unwrap(), Some(dg11));
+        assert_eq!(card.read_file(FileId::Dg2).unwrap(), Some(dg2));
+    }
+
+    #[test]
+    fn test_read_file_no_current_ef() {
+        let mut dg2 = hex!("75 82 011C").to_vec();
+        dg2.extend_from_slice(&[0x42; 0x11C]);
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 82 00 04"), StatusWord::SUCCESS, &dg2[..4])
+            .expect(&hex!("00 B0 00 04 DF"), StatusWord::NO_CURRENT_EF, &[])
+            .expect(&hex!("00 A4 02 0C 02 0102"), StatusWord::SUCCESS, &[])
+            .expect(&hex!("00 B0 00 04 DF"), StatusWord::SUCCESS, &dg2[4..0xE3])
+            .expect(&hex!("00 B0 00 E3 3D"), StatusWord::SUCCESS, &dg2[0xE3..]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        assert_eq!(card.read_file(FileId::Dg2).unwrap(), Some(dg2));
+    }
+
+    #[test]
+    fn test_read_empty_file() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 8B 00 04"), StatusWord::SUCCESS, &[])
+            // Offset zero is beyond the end of the selected empty file.
+            .expect(&hex!("00 B0 8C 00 04"), StatusWord::WRONG_P1P2, &[])
+            .expect(&hex!("00 A4 02 0C 02 010C"), StatusWord::SUCCESS, &[])
+            .expect(&hex!("00 B0 00 00 04"), StatusWord::WRONG_P1P2, &[]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        assert_eq!(card.read_file(FileId::Dg11).unwrap(), Some(vec![]));
+        assert_eq!(card.read_file(FileId::Dg12).unwrap(), Some(vec![]));
+    }
+
+    #[test]
+    fn test_read_file_short_ef_rejected() {
+        // `6B00` on the first short EF read falls back to selection by file identifier.
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 8C 00 04"), StatusWord::WRONG_P1P2, &[])
+            .expect(&hex!("00 A4 02 0C 02 010C"), StatusWord::SUCCESS, &[])
+            .expect(
+                &hex!("00 B0 00 00 04"),
+                StatusWord::SUCCESS,
+                &hex!("6C 02 0000"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        assert_eq!(
+            card.read_file(FileId::Dg12).unwrap(),
+            Some(hex!("6C 02 0000").to_vec())
+        );
+    }
+
+    #[test]
+    fn test_read_end_of_file() {
+        let nfc = MockReader::default()
+            // Offset past the end of a file that is a multiple of the chunk size.
+            .expect(&hex!("00 B0 00 00 04"), StatusWord::SUCCESS, &[0x42; 4])
+            .expect(&hex!("00 B0 00 04 04"), StatusWord::WRONG_P1P2, &[])
+            // End of file reached before Le bytes.
+            .expect(&hex!("00 B0 00 00 04"), StatusWord::SUCCESS, &[0x42; 4])
+            .expect(&hex!("00 B0 00 04 04"), StatusWord::END_OF_FILE, &[0x42; 2]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.set_max_le(Some(4));
+        for expected in [4, 6] {
+            let mut result = card.read_binary_offset(0).unwrap();
+            card.read_remainder(&mut result).unwrap();
+            assert_eq!(result, vec![0x42; expected]);
+        }
+    }
+
+    #[test]
+    fn test_secure_messaging_get_response() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+        let data = [0x42; 0x30];
+
+        // The chip returns `6110`, the remainder follows a protected GET RESPONSE.
+        let mut chip = Encrypted::new(TDesCipher::from_seed(&seed), ssc);
+        let read = chip.enc_apdu(&hex!("00 B0 00 00 30")).unwrap();
+        let first = chip.protect_response(0x6110.into(), &data[..0x20]);
+        let get_response = chip.enc_apdu(&hex!("00 C0 00 00 10")).unwrap();
+        let rest = chip.protect_response(StatusWord::SUCCESS, &data[0x20..]);
+        let nfc = MockReader::default()
+            .expect(&read, 0x6110.into(), &first)
+            .expect(&get_response, StatusWord::SUCCESS, &rest);
+
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.set_secure_messaging(Box::new(Encrypted::new(TDesCipher::from_seed(&seed), ssc)));
+        let response = card.send_apdu(&hex!("00 B0 00 00 30")).unwrap();
+        assert_eq!(response.status, StatusWord::SUCCESS);
+        assert_eq!(response.data, data);
+    }
+
+    #[test]
+    fn test_secure_messaging_get_response_limit() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+
+        // Every protected GET RESPONSE is answered with `6101` and one more byte.
+        let mut chip = Encrypted::new(TDesCipher::from_seed(&seed), ssc);
+        let read = chip.enc_apdu(&hex!("00 B0 00 00 30")).unwrap();
+        let first = chip.protect_response(0x6101.into(), &[0x42]);
+        let mut nfc = MockReader::default().expect(&read, 0x6101.into(), &first);
+        for _ in 0..256 {
+            let get_response = chip.enc_apdu(&hex!("00 C0 00 00 01")).unwrap();
+            let part = chip.protect_response(0x6101.into(), &[0x42]);
+            nfc = nfc.expect(&get_response, 0x6101.into(), &part);
+        }
+
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.set_secure_messaging(Box::new(Encrypted::new(TDesCipher::from_seed(&seed), ssc)));
+        assert!(matches!(
+            card.send_apdu(&hex!("00 B0 00 00 30")),
+            Err(Error::ResponseTooLong)
+        ));
+    }
+
+    #[test]
+    fn test_secure_messaging_mac_failure_ends_session() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+
+        let mut chip = Encrypted::new(TDesCipher::from_seed(&seed), ssc);
+        let read = chip.enc_apdu(&hex!("00 B0 00 00 DF")).unwrap();
+        let mut resp = chip.protect_response(StatusWord::SUCCESS, &[0x42; 4]);
+        *resp.last_mut().unwrap() ^= 1;
+        let nfc = MockReader::default().expect(&read, StatusWord::SUCCESS, &resp);
+
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        card.state = State::Session;
+        card.set_secure_messaging(Box::new(Encrypted::new(TDesCipher::from_seed(&seed), ssc)));
+        let error = card.read_binary_offset(0).unwrap_err();
+        assert!(matches!(error, Error::SMResponseMacFailed));
+        assert!(error.ends_session());
+
+        // Later commands fail fast without reaching the chip.
+        assert_eq!(card.state(), State::Application);
+        assert!(matches!(card.read_dg2(), Err(Error::InvalidState { .. })));
+    }
+
+    #[test]
+    fn test_select_and_read_secure_messaging() {
+        // Session keys and counter from ICAO 9303-11 Appendix D.4.
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+        let ef_com = hex!("60145F0104303130365F36063034303030305C026175");
+
+        // Protect the exchanges as the chip would.
+        let mut chip = Encrypted::new(TDesCipher::from_seed(&seed), ssc);
+        let mut nfc = MockReader::default();
+        for (apdu, data) in [
+            (&hex!("00 A4 02 0C 02 01 1E")[..], &[][..]),
+            // The same commands as the worked example, EF.COM has a one byte length.
+            (&hex!("00 B0 00 00 04"), &ef_com[..4]),
+            (&hex!("00 B0 00 04 12"), &ef_com[4..]),
+        ] {
+            let papdu = chip.enc_apdu(apdu).unwrap();
+            let resp = chip.protect_response(StatusWord::SUCCESS, data);
+            nfc = nfc.expect(&papdu, StatusWord::SUCCESS, &resp);
+        }
+
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.parent = DedicatedId::EmrtdLds1;
+        card.set_secure_messaging(Box::new(Encrypted::new(TDesCipher::from_seed(&seed), ssc)));
+        let data = card.select_and_read(0x011E).unwrap();
+        assert_eq!(data, ef_com);
+
+        // Tag list of DG1 and DG2.
+        let (com, _) = parse_tlv(&data).unwrap();
+        assert_eq!(com.get(0x5C).unwrap().value, hex!("61 75"));
+    }
 }
@@ -1,23 +1,54 @@
 //! Library for interacting with an ICAO 9303 compliant eMRTD.
 
+mod access;
 mod bac;
 mod chip_authentication;
+mod command;
+mod data_groups;
 mod files;
 mod pace;
 pub mod secure_messaging;
-
-pub use self::files::{DedicatedId, FileId, HasFileId};
+mod travel_records;
+#[cfg(feature = "passive-auth")]
+mod verify;
+
+#[cfg(feature = "passive-auth")]
+pub use self::verify::{Check, DataGroupCheck, DgIntegrity, VerificationReport};
+pub use self::{
+    access::{AccessReport, Document, PartialRead},
+    bac::AuthError,
+    command::Command,
+    files::{DedicatedId, FileId, HasFileId, DEFAULT_MAX_LE},
+    pace::{domain_parameters, pace_key, standardized_parameters, DomainParameters},
+    travel_records::TravelRecords,
+};
+#[cfg(feature = "dangerous")]
+pub use self::{
+    bac::{bac_debug, BacDebug},
+    pace::{pace_debug, PaceDebug},
+};
 use {
     self::secure_messaging::{PlainText, SecureMessaging},
     crate::{
-        iso7816::{self, StatusWord},
-        nfc::NfcReader,
+        asn1::security_info::SymmetricCipher,
+        crypto::NamedCurve,
+        ensure_err,
+        iso7816::{self, CommandApdu, Le, ResponseApdu, StatusWord},
+        lds,
+        nfc::{CardType, NfcReader, Protocol},
+        utils::Redacted,
     },
     files::FileCache,
     sha1::{Digest, Sha1},
+    std::fmt,
     thiserror::Error,
 };
 
+/// A session with a single eMRTD through one reader.
+///
+/// `Emrtd` is [`Send`], so sessions can run on worker threads, one per reader. It is not
+/// [`Sync`]: APDUs form a strict sequence under Secure Messaging, so a session must not be
+/// shared between threads. Instances bound to distinct readers are fully independent.
 pub struct Emrtd {
     /// NFC Reader connected to card.
     nfc: Box<dyn NfcReader>,
@@ -31,8 +62,114 @@ pub struct Emrtd {
     /// Currently selected parent.
     parent: DedicatedId,
 
+    /// Progress of the access procedure.
+    state: State,
+
     /// Cache of files read from the card.
     file_cache: FileCache,
+
+    /// If false, files are always read fresh from the card.
+    file_cache_enabled: bool,
+
+    /// If true, APDUs and status words are logged to stderr.
+    trace: bool,
+
+    /// Maximum Le for READ BINARY, if smaller than the APDU format allows.
+    ///
+    /// Defaults to [`DEFAULT_MAX_LE`], files are read in chunks of this size. Kept when
+    /// reconnecting.
+    max_le: Option<usize>,
+
+    /// Le the chip accepted after responding `6700` (wrong length) to larger ones.
+    ///
+    /// Lowers `max_le` until the next connect.
+    lowered_le: Option<usize>,
+
+    /// Secure Messaging cipher indicated in the FCI of the selected application.
+    fci_sm_cipher: Option<SymmetricCipher>,
+
+    /// Short EF identifiers advertised in the FCI of the selected application.
+    fci_short_ef_ids: Vec<(u16, u8)>,
+
+    /// Non-fatal inconsistencies observed during the session.
+    warnings: Vec<String>,
+
+    /// How the current Secure Messaging session was established.
+    session: Option<SessionInfo>,
+
+    /// MRZ information used for access control, see [`Emrtd::mrz_matches_access_key`].
+    access_key: Option<String>,
+
+    /// Limit on rejected authentication attempts, see [`Emrtd::set_max_auth_attempts`].
+    max_auth_attempts: Option<usize>,
+
+    /// Authentication attempts rejected by the chip since the last success or reset.
+    failed_auth_attempts: usize,
+
+    /// Retries the chip reported left on the last rejected authentication.
+    auth_retries_remaining: Option<u8>,
+}
+
+/// Shows the session state, the access key is redacted.
+impl fmt::Debug for Emrtd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Emrtd")
+            .field("parent", &self.parent)
+            .field("state", &self.state)
+            .field("session", &self.session)
+            .field("extended_length", &self.extended_length)
+            .field("cached_files", &self.file_cache.len())
+            .field("access_key", &self.access_key.as_ref().map(Redacted))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Progress of the access procedure, see ICAO 9303-11 section 4.2.
+///
+/// States are ordered, later states meet the preconditions of earlier ones. Selecting a
+/// file after a session is established does not change the state.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum State {
+    /// No card present.
+    Idle,
+
+    /// The master file is selected, as after reset.
+    MasterFile,
+
+    /// The eMRTD or another application is selected.
+    Application,
+
+    /// Secure Messaging was established with BAC or PACE.
+    Session,
+
+    /// Secure Messaging was restarted with Chip Authentication keys.
+    ChipAuthenticated,
+}
+
+/// How the current Secure Messaging session was established, see [`Emrtd::session_info`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SessionInfo {
+    pub protocol: SessionProtocol,
+
+    /// Cipher protecting APDUs.
+    pub cipher: SymmetricCipher,
+
+    /// Curve of the key agreement, `None` without one or for DH and explicit parameters.
+    pub curve: Option<NamedCurve>,
+
+    /// Size in bits of the key agreement group, `None` without key agreement or if unknown.
+    pub key_bits: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SessionProtocol {
+    /// Basic Access Control, session keys derived from the MRZ without key agreement.
+    Bac,
+
+    Pace,
+
+    /// Not reported yet, as Chip Authentication is not implemented.
+    ChipAuthentication,
 }
 
 #[derive(Debug, Error)]
@@ -52,6 +189,13 @@ pub enum Error {
     #[error("Invalid DER: {0}")]
     InvalidDer(#[from] der::Error),
 
+    #[error("Invalid LDS data: {0}")]
+    InvalidLds(#[from] lds::Error),
+
+    #[cfg(feature = "passive-auth")]
+    #[error("Passive Authentication: {0}")]
+    PassiveAuthentication(#[from] crate::passive_authentication::Error),
+
     #[error("Response exceeds maximum length.")]
     ResponseTooLong,
 
@@ -61,21 +205,68 @@ pub enum Error {
     #[error("Secure Messasing Response failed MAC.")]
     SMResponseMacFailed,
 
+    #[error(
+        "Secure Messaging Response MAC is valid for SSC {actual:016X}, expected {expected:016X}."
+    )]
+    SscMismatch { expected: u64, actual: u64 },
+
     #[error("Response data is unexpected.")]
     ResponseDataUnexpected,
 
     #[error("Invalid Application ID")]
     InvalidApplicationId,
 
+    #[error("Application {} not found.", hex::encode_upper(.0))]
+    ApplicationNotFound(Vec<u8>),
+
     #[error("Invalid Short File ID")]
     InvalidShortFileId,
 
     #[error("File not found.")]
     FileNotFound,
+
+    #[error("Instruction {ins:02X} not supported by the chip.")]
+    NotSupported { ins: u8 },
+
+    #[error("Access denied, Terminal Authentication required.")]
+    TerminalAuthenticationRequired,
+
+    #[error("Challenge has unexpected length (expected {expected}, got {actual}).")]
+    UnexpectedChallengeLength { expected: usize, actual: usize },
+
+    #[error("Challenge length {0} is out of range, must be 1 to 256.")]
+    InvalidChallengeLength(usize),
+
+    #[error("Refusing to authenticate after {attempts} rejected attempts.")]
+    AuthAttemptsExceeded { attempts: usize },
+
+    #[error("Invalid state {actual:?}, {required:?} required.")]
+    InvalidState { required: State, actual: State },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// True if the error closed the Secure Messaging session.
+    ///
+    /// The chip aborts the session on `6987` and `6988`, and a response that fails its MAC
+    /// can not be trusted to continue from. Access control has to be repeated before
+    /// protected files can be read again. A [`Error::SscMismatch`] does not end the session,
+    /// the counter is re-synchronized and the command can be retried.
+    pub fn ends_session(&self) -> bool {
+        matches!(
+            self,
+            Self::SecureMessagingError(_) | Self::SMResponseInvalid | Self::SMResponseMacFailed
+        )
+    }
+}
+
+// Keep sessions movable between threads.
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<Emrtd>();
+};
+
 impl From<StatusWord> for Error {
     fn from(status: StatusWord) -> Self {
         Error::ErrorResponse(status)
@@ -91,50 +282,313 @@ impl Emrtd {
 
             // On Reset chip is always in master file.
             parent: DedicatedId::MasterFile,
+            state: State::MasterFile,
             file_cache: FileCache::new(),
+            file_cache_enabled: true,
+            trace: false,
+            max_le: Some(DEFAULT_MAX_LE),
+            lowered_le: None,
+            fci_sm_cipher: None,
+            fci_short_ef_ids: Vec::new(),
+            warnings: Vec::new(),
+            session: None,
+            access_key: None,
+            max_auth_attempts: None,
+            failed_auth_attempts: 0,
+            auth_retries_remaining: None,
+        }
+    }
+
+    /// Progress of the access procedure.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Fail with [`Error::InvalidState`] unless at least `required` was reached.
+    pub fn require_state(&self, required: State) -> Result<()> {
+        ensure_err!(
+            self.state >= required,
+            Error::InvalidState {
+                required,
+                actual: self.state,
+            }
+        );
+        Ok(())
+    }
+
+    /// Advance to `state` on a successful selection, unless a session is established.
+    fn selected(&mut self, state: State) {
+        if self.state < State::Session {
+            self.state = state;
+        }
+    }
+
+    /// Log all APDUs with a human readable description to stderr.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    /// (Re)connect to the card.
+    ///
+    /// This starts a new session: secure messaging is reset and the file cache is
+    /// cleared, as a different card may have been presented.
+    pub fn connect(&mut self) -> Result<Option<CardType>> {
+        self.connect_with(None)
+    }
+
+    /// (Re)connect to the card using `protocol`, or the reader's default if `None`.
+    ///
+    /// Forcing [`Protocol::T1`] avoids `61XX` and GET RESPONSE on chips that support it.
+    pub fn connect_with(&mut self, protocol: Option<Protocol>) -> Result<Option<CardType>> {
+        let card = self.nfc.connect_with(protocol).map_err(Error::NfcError)?;
+        self.secure_messaging = Box::new(PlainText);
+        self.parent = DedicatedId::MasterFile;
+        self.state = if card.is_some() {
+            State::MasterFile
+        } else {
+            State::Idle
+        };
+        self.file_cache.clear();
+        self.lowered_le = None;
+        self.fci_sm_cipher = None;
+        self.fci_short_ef_ids.clear();
+        self.warnings.clear();
+        self.session = None;
+        self.access_key = None;
+        Ok(card)
+    }
+
+    /// Limit the number of bytes requested per READ BINARY, [`DEFAULT_MAX_LE`] by default.
+    ///
+    /// `None` requests as much as the APDU format allows (256, or 65536 with extended length).
+    /// The limit is kept when reconnecting, unlike one found by the chip rejecting Le.
+    pub fn set_max_le(&mut self, max_le: Option<usize>) {
+        self.max_le = max_le;
+        self.lowered_le = None;
+    }
+
+    /// Enable or disable caching of files read from the card.
+    ///
+    /// Files can not change during a session, so caching is enabled by default. Tools that
+    /// want to observe fresh reads can disable it, which also clears the cache.
+    pub fn set_file_cache_enabled(&mut self, enabled: bool) {
+        self.file_cache_enabled = enabled;
+        if !enabled {
+            self.file_cache.clear();
+        }
+    }
+
+    /// Export the current Secure Messaging session keys and send sequence counter.
+    ///
+    /// Returns `None` if no secure session is established. This is only meant for
+    /// validating the handshake against reference implementations.
+    #[cfg(feature = "dangerous")]
+    pub fn dangerous_export_session_keys(&self) -> Option<secure_messaging::SessionKeys> {
+        self.secure_messaging.export_session_keys()
+    }
+
+    /// Protocol, cipher and key agreement of the current session, `None` without one.
+    pub fn session_info(&self) -> Option<SessionInfo> {
+        self.session
+    }
+
+    /// Non-fatal inconsistencies observed since connecting, e.g. an unexpected cipher.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Refuse to authenticate once `limit` attempts were rejected by the chip.
+    ///
+    /// Chips with a retry counter block access after a number of wrong passwords, this
+    /// keeps a retry loop from locking the document. Off by default. The count is kept when
+    /// reconnecting, like the counter on the chip, and is reset by a successful
+    /// authentication or [`Emrtd::reset_auth_attempts`].
+    pub fn set_max_auth_attempts(&mut self, limit: Option<usize>) {
+        self.max_auth_attempts = limit;
+    }
+
+    /// Allow authenticating again after [`Error::AuthAttemptsExceeded`].
+    ///
+    /// Only call this on explicit request, e.g. after the user corrected the MRZ.
+    pub fn reset_auth_attempts(&mut self) {
+        self.failed_auth_attempts = 0;
+    }
+
+    /// Authentication attempts rejected by the chip since the last success or reset.
+    pub fn failed_auth_attempts(&self) -> usize {
+        self.failed_auth_attempts
+    }
+
+    /// Retries left according to the chip's `63CX` response to the last rejected attempt.
+    ///
+    /// `None` if the last attempt succeeded or the chip does not report a counter.
+    pub fn auth_retries_remaining(&self) -> Option<u8> {
+        self.auth_retries_remaining
+    }
+
+    /// Fail with [`Error::AuthAttemptsExceeded`] if the limit of rejected attempts is reached.
+    fn check_auth_attempts(&self) -> Result<()> {
+        if let Some(limit) = self.max_auth_attempts {
+            ensure_err!(
+                self.failed_auth_attempts < limit,
+                Error::AuthAttemptsExceeded {
+                    attempts: self.failed_auth_attempts,
+                }
+            );
         }
+        Ok(())
+    }
+
+    /// Count the chip's response `status` to an authentication command.
+    fn record_auth_attempt(&mut self, status: StatusWord) {
+        if status.is_success() {
+            self.failed_auth_attempts = 0;
+            self.auth_retries_remaining = None;
+        } else {
+            self.failed_auth_attempts += 1;
+            self.auth_retries_remaining = status.retries_remaining();
+        }
+    }
+
+    /// Secure Messaging cipher the selected application indicated in its FCI, if any.
+    pub fn fci_sm_cipher(&self) -> Option<SymmetricCipher> {
+        self.fci_sm_cipher
     }
 
     pub fn set_secure_messaging(&mut self, secure_messaging: Box<dyn SecureMessaging>) {
+        // A mismatch with the FCI makes every protected command fail with `6988`.
+        if let (Some(expected), Some(actual)) = (self.fci_sm_cipher, secure_messaging.cipher()) {
+            if expected != actual {
+                self.warnings.push(format!(
+                    "Secure Messaging uses {actual}, but the application FCI indicates {expected}."
+                ));
+            }
+        }
         self.secure_messaging = secure_messaging;
     }
 
-    pub fn send_apdu(&mut self, apdu: &[u8]) -> Result<(StatusWord, Vec<u8>)> {
-        let protected_apdu = self.secure_messaging.enc_apdu(apdu)?;
+    /// Drop Secure Messaging after the session failed, see [`Error::ends_session`].
+    ///
+    /// The selected file is kept, so the state returns to before access control.
+    fn end_session(&mut self) {
+        self.secure_messaging = Box::new(PlainText);
+        self.session = None;
+        self.state = match self.parent {
+            DedicatedId::MasterFile => State::MasterFile,
+            _ => State::Application,
+        };
+    }
 
-        // TODO: Apply command chaining and `GET RESPONSE` handling.
-        // This goes after enctyption (`GET RESPONSE` is always plaintext).
+    /// Send an APDU, protected by the current Secure Messaging.
+    pub fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
+        self.exchange(apdu, true)
+    }
 
-        let (status, data) = self
+    /// Send an APDU in the clear, even if a Secure Messaging session is established.
+    ///
+    /// Most chips abort the session on an unprotected command, see [`Error::ends_session`].
+    pub fn send_apdu_plain(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
+        self.exchange(apdu, false)
+    }
+
+    fn exchange(&mut self, apdu: &[u8], protected: bool) -> Result<ResponseApdu> {
+        let mut response = self.transmit(apdu, protected)?;
+
+        // Collect remaining response data with `GET RESPONSE`, as used by T=0 chips.
+        // Under Secure Messaging each GET RESPONSE is protected and each part decrypted.
+        // See ISO 7816-4 section 5.3.4.
+        let mut get_responses = 0;
+        while let Some(remaining) = response.bytes_remaining() {
+            // A chip that keeps answering `61XX` does not hold up the caller forever.
+            get_responses += 1;
+            ensure_err!(get_responses <= MAX_GET_RESPONSES, Error::ResponseTooLong);
+            // `6100` means 256 or more bytes remain.
+            let length = if remaining == 0 {
+                Le::MAX_SHORT
+            } else {
+                remaining
+            };
+            let get_response =
+                CommandApdu::new(0x00, 0xC0, 0x00, 0x00).with_le(Le::new(length, false));
+            let next = self.transmit(&get_response.to_bytes(), protected)?;
+            response.status = next.status;
+            response.data.extend_from_slice(&next.data);
+        }
+        Ok(response)
+    }
+
+    /// Send a single APDU, protected by the current Secure Messaging if `protected`.
+    fn transmit(&mut self, apdu: &[u8], protected: bool) -> Result<ResponseApdu> {
+        // Responses refer to the instruction byte.
+        ensure_err!(apdu.len() >= 4, iso7816::Error::ApduTooShort.into());
+        if self.trace {
+            eprintln!("> {}", iso7816::describe_apdu(apdu));
+        }
+        let protected_apdu = if protected {
+            self.secure_messaging.enc_apdu(apdu)?
+        } else {
+            apdu.to_vec()
+        };
+
+        // TODO: Apply command chaining.
+        // This goes after encryption.
+        let ResponseApdu { status, data } = self
             .nfc
             .send_apdu(&protected_apdu)
             .map_err(Error::NfcError)?;
-        //eprintln!("Status word: {}", status);
-        //eprintln!("Encrypted response APDU: {}", hex::encode(&data));
 
-        match status {
+        let not_supported = matches!(
+            status,
+            StatusWord::INS_NOT_SUPPORTED | StatusWord::CLA_NOT_SUPPORTED
+        );
+        let data = match status {
             StatusWord::SECURE_MESSAGING_INCORRECT | StatusWord::SECURE_MESSAGING_INCOMPLETE => {
-                // Reset secure messaging.
-                self.set_secure_messaging(Box::new(PlainText));
-
-                return Err(Error::SecureMessagingError(status));
+                Err(Error::SecureMessagingError(status))
             }
-            _ => {}
+            // Rejected before Secure Messaging processing, the session is unaffected.
+            _ if not_supported && data.is_empty() => Err(Error::NotSupported { ins: apdu[1] }),
+            _ if protected => self.secure_messaging.dec_response(status, &data),
+            _ => Ok(data),
+        };
+        let data = match data {
+            Err(error) if error.ends_session() => {
+                // Fail fast on later protected commands instead of cascading MAC errors.
+                self.end_session();
+                return Err(error);
+            }
+            result => result?,
+        };
+        if self.trace {
+            eprintln!(
+                "< {} ({} bytes): {}",
+                status,
+                data.len(),
+                hex::encode(&data)
+            );
         }
+        ensure_err!(!not_supported, Error::NotSupported { ins: apdu[1] });
 
-        // TODO: On SM error card will revert to plain APDU. Check for SM error.
-        let data = self.secure_messaging.dec_response(status, &data)?;
-        // eprintln!("Decrypted response APDU: {}", hex::encode(&data));
-
-        Ok((status, data))
+        Ok(ResponseApdu { data, status })
     }
 }
 
+/// Maximum number of `GET RESPONSE` for a single command.
+///
+/// Enough to collect the 64 KiB of an extended length response in parts of 256 bytes.
+const MAX_GET_RESPONSES: usize = Le::MAX_EXTENDED / Le::MAX_SHORT;
+
 pub fn pad(bytes: &mut Vec<u8>, block_size: usize) {
     bytes.push(0x80);
     bytes.resize(bytes.len().next_multiple_of(block_size), 0x00);
 }
 
+/// Key seed for Basic Access Control, the first 16 bytes of the SHA-1 of `mrz`.
+///
+/// `mrz` is the MRZ information, not the full MRZ: the document number, date of birth and
+/// date of expiry, each followed by its check digit. Build it from its parts with
+/// [`lds::MrzInput::mrz_information`].
+///
+/// See ICAO 9303-11 section 9.7.2.
 pub fn seed_from_mrz(mrz: &str) -> [u8; 16] {
     let mut hasher = Sha1::new();
     hasher.update(mrz.as_bytes());
@@ -1,25 +1,192 @@
 use {
-    super::Emrtd,
-    crate::{asn1::public_key::EcParameters, emrtd::secure_messaging::aes::kdf_128},
-    anyhow::Result,
+    super::{Emrtd, FileId},
+    crate::{
+        asn1::{
+            public_key::{ECAlgoParameters, EcParameters, PubkeyAlgorithmIdentifier},
+            security_info::{
+                PaceDomainParameterInfo, PaceInfo, SecurityInfo, SecurityInfos, SymmetricCipher,
+            },
+            EfCardAccess,
+        },
+        crypto::NamedCurve,
+        emrtd::secure_messaging::{derive_key, KDF_PACE},
+        iso7816::{CommandApdu, Le},
+        lds::tlv::parse_tlv,
+    },
+    aes::{Aes128, Aes192, Aes256},
+    anyhow::{anyhow, bail, ensure, Result},
+    cbc::Decryptor as CbcDec,
+    cipher::{block_padding::NoPadding, BlockDecryptMut, KeyIvInit},
+    der::{asn1::ObjectIdentifier as Oid, Decode, Encode},
+    des::TdesEde2,
     rand::{CryptoRng, RngCore},
     sha1::{Digest, Sha1},
 };
 
-pub const KDF_PACE: u32 = 3;
-
 impl Emrtd {
+    /// Check whether the chip supports PACE.
+    ///
+    /// PACE support is signalled by PaceInfo in EF.CardAccess. The file is absent on
+    /// chips that only support Basic Access Control, which is not an error.
+    ///
+    /// See ICAO 9303-11 section 4.4 and 9.2.
+    pub fn pace_supported(&mut self) -> super::Result<bool> {
+        let Some(der) = self.read_file_cached(FileId::CardAccess)? else {
+            return Ok(false);
+        };
+        let card_access = EfCardAccess::from_der(&der)?;
+        Ok(card_access
+            .iter()
+            .any(|info| matches!(info, SecurityInfo::Pace(_))))
+    }
+
+    /// Protocols advertised in EF.CardAccess, empty if the chip has no such file.
+    ///
+    /// Lets a caller choose between PACE and Basic Access Control. Entries with unknown
+    /// protocols are included, see [`SecurityInfo::Unknown`].
+    pub fn card_access_protocols(&mut self) -> super::Result<Vec<Oid>> {
+        let Some(der) = self.read_file_cached(FileId::CardAccess)? else {
+            return Ok(Vec::new());
+        };
+        let card_access = EfCardAccess::from_der(&der)?;
+        Ok(card_access.iter().map(SecurityInfo::protocol).collect())
+    }
+
     pub fn pace(&mut self, rng: impl CryptoRng + RngCore, mrz: &str) -> Result<()> {
+        self.check_auth_attempts()?;
+
+        // Select the first PACEInfo with a cipher.
+        // TODO: Prefer the strongest supported protocol.
+        let card_access = self.read_cached::<EfCardAccess>()?;
+        let Some(pace_info) = card_access.iter().find_map(|info| match info {
+            SecurityInfo::Pace(pace_info) if pace_info.protocol.cipher.is_some() => {
+                Some(pace_info.clone())
+            }
+            _ => None,
+        }) else {
+            bail!("No PACEInfo in EF.CardAccess");
+        };
+        let cipher = pace_info.protocol.cipher.unwrap();
+
         // Derive symmetric key K_pi
-        let k = k_from_mrz(mrz);
-        let k_pi = kdf_128(&k[..], KDF_PACE);
+        let k_pi = pace_key(cipher, mrz);
 
         // Send MSE:Set AT.
+        self.pace_set_at(pace_info.protocol.into(), pace_info.parameter_id)?;
 
-        // Send GENERAL AUTHENTICATE
+        // Send GENERAL AUTHENTICATE and decrypt the nonce.
+        let encrypted_nonce = self.pace_encrypted_nonce()?;
+        let _nonce = decrypt_nonce(cipher, &k_pi, &encrypted_nonce)?;
 
+        // TODO: Map the nonce, perform key agreement and mutual authentication. Compare the
+        // authentication token in constant time and fail uniformly, as in Basic Access Control.
+        // Count the status of the final GENERAL AUTHENTICATE with `record_auth_attempt`.
         todo!()
     }
+
+    /// Select PACE with the MRZ as password, see ICAO 9303-11 section 4.4.4.1.
+    fn pace_set_at(&mut self, protocol: Oid, parameter_id: Option<u64>) -> Result<()> {
+        // Cryptographic mechanism: 0x80 <len> <OID>
+        let protocol = protocol.as_bytes();
+        let mut data = vec![0x80, protocol.len().try_into()?];
+        data.extend_from_slice(protocol);
+
+        // Password reference, MRZ
+        data.extend_from_slice(&[0x83, 0x01, PASSWORD_MRZ]);
+
+        // Domain parameters, required if there are several.
+        if let Some(id) = parameter_id {
+            data.extend_from_slice(&[0x84, 0x01, id.try_into()?]);
+        }
+
+        let apdu = CommandApdu::new(0x00, 0x22, 0xC1, 0xA4).with_data(data)?;
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        ensure!(
+            response.is_success(),
+            "MSE:Set AT failed: {}",
+            response.status
+        );
+        ensure!(response.data.is_empty());
+        Ok(())
+    }
+
+    /// First GENERAL AUTHENTICATE, returning the encrypted nonce.
+    ///
+    /// See ICAO 9303-11 section 4.4.4.2.
+    fn pace_encrypted_nonce(&mut self) -> Result<Vec<u8>> {
+        // Chained command with an empty dynamic authentication data object.
+        let apdu = CommandApdu::new(0x10, 0x86, 0x00, 0x00)
+            .with_data([DYNAMIC_AUTHENTICATION_DATA as u8, 0x00])?
+            .with_le(Le::max(false));
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        ensure!(
+            response.is_success(),
+            "GENERAL AUTHENTICATE failed: {}",
+            response.status
+        );
+        let (template, _) = parse_tlv(&response.data)?;
+        ensure!(template.tag == DYNAMIC_AUTHENTICATION_DATA);
+        Ok(template.get(ENCRYPTED_NONCE)?.value.to_vec())
+    }
+}
+
+/// Password reference for the MRZ, see ICAO 9303-11 section 4.4.4.1.
+const PASSWORD_MRZ: u8 = 0x01;
+
+/// Dynamic authentication data object of GENERAL AUTHENTICATE.
+const DYNAMIC_AUTHENTICATION_DATA: u32 = 0x7C;
+
+/// Encrypted nonce in the dynamic authentication data.
+const ENCRYPTED_NONCE: u32 = 0x80;
+
+/// Intermediate values of PACE, for conformance logging against test vectors.
+///
+/// Only `K_pi` and the nonce are recorded. The mapped generator, shared secret, session
+/// keys and authentication tokens are missing until the mapping is implemented.
+///
+/// Anyone holding these can derive the session, only enable for debugging.
+#[cfg(feature = "dangerous")]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PaceDebug {
+    /// Password key derived from the MRZ information.
+    pub k_pi: Vec<u8>,
+
+    /// Nonce `s` decrypted from the chip's encrypted nonce.
+    pub nonce: Vec<u8>,
+}
+
+/// Compute the PACE password key and decrypt the nonce, without a chip.
+///
+/// The mapping, key agreement and authentication tokens are not implemented yet.
+///
+/// See ICAO 9303-11 appendix G.
+#[cfg(feature = "dangerous")]
+pub fn pace_debug(cipher: SymmetricCipher, mrz: &str, encrypted_nonce: &[u8]) -> Result<PaceDebug> {
+    let k_pi = pace_key(cipher, mrz);
+    let nonce = decrypt_nonce(cipher, &k_pi, encrypted_nonce)?;
+    Ok(PaceDebug { k_pi, nonce })
+}
+
+/// Decrypt the PACE nonce with `K_pi` in CBC mode with a zero IV.
+///
+/// ICAO 9303-11 section 4.4.3.1
+pub fn decrypt_nonce(cipher: SymmetricCipher, k_pi: &[u8], encrypted: &[u8]) -> Result<Vec<u8>> {
+    match cipher {
+        SymmetricCipher::Tdes => cbc_decrypt::<CbcDec<TdesEde2>>(k_pi, encrypted),
+        SymmetricCipher::Aes128 => cbc_decrypt::<CbcDec<Aes128>>(k_pi, encrypted),
+        SymmetricCipher::Aes192 => cbc_decrypt::<CbcDec<Aes192>>(k_pi, encrypted),
+        SymmetricCipher::Aes256 => cbc_decrypt::<CbcDec<Aes256>>(k_pi, encrypted),
+    }
+}
+
+fn cbc_decrypt<D: KeyIvInit + BlockDecryptMut>(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let iv = vec![0; D::iv_size()];
+    let decryptor = D::new_from_slices(key, &iv).map_err(|_| anyhow!("Invalid key length"))?;
+    let mut data = data.to_vec();
+    decryptor
+        .decrypt_padded_mut::<NoPadding>(&mut data)
+        .map_err(|_| anyhow!("Encrypted nonce is not a multiple of the block size"))?;
+    Ok(data)
 }
 
 pub fn k_from_mrz(mrz: &str) -> [u8; 20] {
@@ -28,20 +195,235 @@ pub fn k_from_mrz(mrz: &str) -> [u8; 20] {
     hasher.finalize().into()
 }
 
+/// Derive the password key `K_pi` from the MRZ information.
+///
+/// The key has the full length of the cipher, e.g. 32 bytes from SHA-256 for AES-256.
+///
+/// ICAO 9303-11 section 9.7.3
+pub fn pace_key(cipher: SymmetricCipher, mrz: &str) -> Vec<u8> {
+    derive_key(cipher, &k_from_mrz(mrz), KDF_PACE)
+}
+
+/// Domain parameters for PACE.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DomainParameters {
+    /// Standardized domain parameters, see [`standardized_parameters`].
+    Standardized(u64),
+
+    /// Named elliptic curve from a PACEDomainParameterInfo.
+    NamedCurve(Oid),
+
+    /// Explicit elliptic curve parameters from a PACEDomainParameterInfo.
+    Explicit(EcParameters),
+}
+
+/// Standardized domain parameter IDs below this value, others are proprietary.
+///
 /// ICAO 9303-11 9.5.1
-pub fn standardized_parameters(id: u64) -> Option<EcParameters> {
-    match id {
-        0 => todo!(),
-        1 => todo!(),
-        2 => todo!(),
-        3..=7 => todo!(),
+const PROPRIETARY_PARAMETER_ID: u64 = 32;
+
+/// Find the domain parameters for a PACEInfo.
+///
+/// Standardized parameter IDs are used directly. Otherwise the parameters come from the
+/// PACEDomainParameterInfo with a matching key agreement and parameter ID, which may be
+/// omitted if there is only one.
+///
+/// ICAO 9303-11 9.2.1 and 9.2.2
+pub fn domain_parameters(
+    pace_info: &PaceInfo,
+    security_infos: &SecurityInfos,
+) -> Result<DomainParameters> {
+    if let Some(id) = pace_info.parameter_id {
+        if id < PROPRIETARY_PARAMETER_ID {
+            ensure!(
+                matches!(id, 0..=2 | 8..=18),
+                "Reserved standardized domain parameter ID {id}"
+            );
+            return Ok(DomainParameters::Standardized(id));
+        }
+    }
+    let mut candidates = security_infos.iter().filter_map(|info| match info {
+        SecurityInfo::PaceDomainParameter(info)
+            if info.protocol.key_agreement == pace_info.protocol.key_agreement
+                && (pace_info.parameter_id.is_none()
+                    || info.parameter_id == pace_info.parameter_id) =>
+        {
+            Some(info)
+        }
         _ => None,
+    });
+    let Some(info) = candidates.next() else {
+        bail!("No PACEDomainParameterInfo for {}", pace_info.protocol);
+    };
+    ensure!(
+        candidates.next().is_none(),
+        "Ambiguous PACEDomainParameterInfo, parameter ID required"
+    );
+    explicit_domain_parameters(info)
+}
+
+fn explicit_domain_parameters(info: &PaceDomainParameterInfo) -> Result<DomainParameters> {
+    let algorithm = PubkeyAlgorithmIdentifier::from_der(&info.domain_parameter.to_der()?)?;
+    match algorithm {
+        PubkeyAlgorithmIdentifier::Ec(ECAlgoParameters::EcParameters(params)) => {
+            Ok(DomainParameters::Explicit(params))
+        }
+        PubkeyAlgorithmIdentifier::Ec(ECAlgoParameters::NamedCurve(oid)) => {
+            Ok(DomainParameters::NamedCurve(oid))
+        }
+        PubkeyAlgorithmIdentifier::Ec(ECAlgoParameters::ImplicitlyCA(_)) => {
+            bail!("Implicit CA domain parameters are not supported")
+        }
+        PubkeyAlgorithmIdentifier::Unknown(algorithm) => {
+            bail!(
+                "Unsupported domain parameter algorithm {}",
+                algorithm.algorithm
+            )
+        }
     }
 }
 
+/// The elliptic curve of a standardized domain parameter ID.
+///
+/// Returns `None` for the MODP groups 0 to 2, which are not supported, and for reserved IDs.
+///
+/// ICAO 9303-11 9.5.1
+pub fn standardized_parameters(id: u64) -> Option<NamedCurve> {
+    Some(match id {
+        8 => NamedCurve::NistP192,
+        9 => NamedCurve::BrainpoolP192r1,
+        10 => NamedCurve::NistP224,
+        11 => NamedCurve::BrainpoolP224r1,
+        12 => NamedCurve::NistP256,
+        13 => NamedCurve::BrainpoolP256r1,
+        14 => NamedCurve::BrainpoolP320r1,
+        15 => NamedCurve::NistP384,
+        16 => NamedCurve::BrainpoolP384r1,
+        17 => NamedCurve::BrainpoolP512r1,
+        18 => NamedCurve::NistP521,
+        _ => return None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::emrtd::secure_messaging::aes::kdf_128, hex_literal::hex};
+    use {
+        super::*,
+        crate::{
+            asn1::{public_key::ID_EC_PUBLIC_KEY, AnyAlgorithmIdentifier, OrderedSet},
+            iso7816::StatusWord,
+            nfc::mock::MockReader,
+        },
+        der::Any,
+        hex_literal::hex,
+    };
+
+    #[test]
+    fn test_proprietary_domain_parameters() {
+        let brainpool_p256r1 = Oid::new_unwrap("1.3.36.3.3.2.8.1.1.7");
+        let pace_info = PaceInfo {
+            protocol: Oid::new_unwrap("0.4.0.127.0.7.2.2.4.2.2")
+                .try_into()
+                .unwrap(),
+            version: 2,
+            parameter_id: Some(32),
+        };
+        let domain_parameter_info = PaceDomainParameterInfo {
+            protocol: Oid::new_unwrap("0.4.0.127.0.7.2.2.4.2").try_into().unwrap(),
+            domain_parameter: AnyAlgorithmIdentifier {
+                algorithm: ID_EC_PUBLIC_KEY,
+                parameters: Some(Any::encode_from(&brainpool_p256r1).unwrap()),
+            },
+            parameter_id: Some(32),
+        };
+        let mut infos = OrderedSet(vec![SecurityInfo::Pace(pace_info.clone())]);
+        assert!(domain_parameters(&pace_info, &infos).is_err());
+
+        infos
+            .0
+            .push(SecurityInfo::PaceDomainParameter(domain_parameter_info));
+        assert_eq!(
+            domain_parameters(&pace_info, &infos).unwrap(),
+            DomainParameters::NamedCurve(brainpool_p256r1)
+        );
+
+        let standardized = PaceInfo {
+            parameter_id: Some(13),
+            ..pace_info
+        };
+        assert_eq!(
+            domain_parameters(&standardized, &infos).unwrap(),
+            DomainParameters::Standardized(13)
+        );
+        assert_eq!(
+            standardized_parameters(13),
+            Some(NamedCurve::BrainpoolP256r1)
+        );
+        assert_eq!(standardized_parameters(18), Some(NamedCurve::NistP521));
+        assert_eq!(standardized_parameters(2), None);
+    }
+
+    #[test]
+    fn test_pace_supported_without_card_access() {
+        let nfc = MockReader::default()
+            .expect(&hex!("00 B0 9C 00 04"), StatusWord::FILE_NOT_FOUND, &[])
+            .expect(
+                &hex!("00 A4 02 0C 02 011C"),
+                StatusWord::FILE_NOT_FOUND,
+                &[],
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert!(!card.pace_supported().unwrap());
+        // The absence is cached, no further APDUs are sent.
+        assert!(!card.pace_supported().unwrap());
+    }
+
+    #[test]
+    fn test_card_access_protocols() {
+        // ChipAuthenticationInfo for ECDH with AES-128 and the PACEInfo of ICAO 9303-11
+        // Appendix G.1.
+        let card_access = hex!(
+            "3125
+            300F 060A 04007F00070202030202 020101
+            3012 060A 04007F00070202040202 020102 02010D"
+        );
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        card.file_cache
+            .insert(FileId::CardAccess, Some(card_access.to_vec()));
+        assert_eq!(
+            card.card_access_protocols().unwrap(),
+            [
+                Oid::new_unwrap("0.4.0.127.0.7.2.2.3.2.2"),
+                Oid::new_unwrap("0.4.0.127.0.7.2.2.4.2.2"),
+            ]
+        );
+        assert!(card.pace_supported().unwrap());
+
+        card.file_cache.insert(FileId::CardAccess, None);
+        assert!(card.card_access_protocols().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pace_commands() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 22 C1A4 12 800A04007F00070202040202 830101 84010D"),
+                StatusWord::SUCCESS,
+                &[],
+            )
+            .expect(
+                &hex!("10 86 0000 02 7C00 00"),
+                StatusWord::SUCCESS,
+                &hex!("7C0A 8008 0102030405060708"),
+            );
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.pace_set_at(Oid::new_unwrap("0.4.0.127.0.7.2.2.4.2.2"), Some(13))
+            .unwrap();
+        assert_eq!(
+            card.pace_encrypted_nonce().unwrap(),
+            hex!("0102030405060708")
+        );
+    }
 
     // ICAO 9303-11, Appendix G
     #[test]
@@ -49,10 +431,35 @@ mod tests {
         let mrz = "T22000129364081251010318";
         let k = k_from_mrz(mrz);
         assert_eq!(k, hex!("7E2D2A41 C74EA0B3 8CD36F86 3939BFA8 E9032AAD"));
-        let k_pi = kdf_128(&k[..], 3);
+        let k_pi = derive_key(SymmetricCipher::Aes128, &k[..], KDF_PACE);
         assert_eq!(k_pi, hex!("89DED1B2 6624EC1E 634C1989 302849DD"));
+        assert_eq!(pace_key(SymmetricCipher::Aes128, mrz), k_pi);
 
         // let pace_info = PaceInfo::from_der(&hex!("3012060A 04007F00 07020204 02020201 0202010D"));
         // dbg!(pace_info);
     }
+
+    // ICAO 9303-11, Appendix G.1
+    #[test]
+    fn test_decrypt_nonce() {
+        let k_pi = pace_key(SymmetricCipher::Aes128, "T22000129364081251010318");
+        let encrypted_nonce = hex!("95A3A016 522EE98D 01E76CB6 B98B42C3");
+        assert_eq!(
+            decrypt_nonce(SymmetricCipher::Aes128, &k_pi, &encrypted_nonce).unwrap(),
+            hex!("3F00C4D3 9D153F2B 2A214A07 8D899B22")
+        );
+        assert!(decrypt_nonce(SymmetricCipher::Aes128, &k_pi, &encrypted_nonce[..8]).is_err());
+    }
+
+    #[test]
+    fn test_pace_key_aes256() {
+        // Same MRZ as Appendix G, but with SHA-256 for AES-192 and AES-256.
+        let mrz = "T22000129364081251010318";
+        let k_pi = pace_key(SymmetricCipher::Aes256, mrz);
+        assert_eq!(
+            k_pi,
+            hex!("D79A23C1 26202AC9 051FEBFB C0E8A03B 1C6645D8 5752B4B7 1408FA22 9AB6D56B")
+        );
+        assert_eq!(pace_key(SymmetricCipher::Aes192, mrz), k_pi[..24]);
+    }
 }
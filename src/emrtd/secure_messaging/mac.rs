@@ -0,0 +1,130 @@
+//! Message authentication codes for Secure Messaging.
+//!
+//! See ICAO 9303-11 section 9.8.3 and 9.8.6.
+
+use {
+    crate::emrtd::pad,
+    aes::{Aes128, Aes192, Aes256},
+    cipher::{BlockDecrypt as _, BlockEncrypt as _, KeyInit},
+    cmac::{Cmac, Mac as _},
+    des::Des,
+};
+
+/// Length of the MACs in Secure Messaging. AES-CMAC is truncated to this length.
+pub const MAC_LENGTH: usize = 8;
+
+/// A MAC over messages already padded to whole blocks.
+pub trait Mac {
+    fn block_size(&self) -> usize;
+
+    fn mac(&self, data: &[u8]) -> [u8; MAC_LENGTH];
+
+    /// Pad `data` with ISO 9797-1 padding method 2 and MAC it.
+    fn mac_padded(&self, data: &[u8]) -> [u8; MAC_LENGTH] {
+        let mut data = data.to_vec();
+        pad(&mut data, self.block_size());
+        self.mac(&data)
+    }
+}
+
+/// Retail MAC, ISO 9797-1 MAC algorithm 3 with DES and a two-key 3DES key.
+///
+/// The message is DES-CBC encrypted with the first key, the last block is then decrypted
+/// with the second key and encrypted with the first.
+// See <https://crypto.stackexchange.com/questions/18951/what-are-options-to-compute-des-retail-mac-aka-iso-9797-1-mode-3-under-pkcs11>
+pub struct Retail3Des<'a> {
+    key: &'a [u8; 16],
+}
+
+/// AES-CMAC truncated to 8 bytes, see NIST SP 800-38B.
+pub enum AesCmac<'a> {
+    Aes128(&'a [u8; 16]),
+    Aes192(&'a [u8; 24]),
+    Aes256(&'a [u8; 32]),
+}
+
+impl<'a> Retail3Des<'a> {
+    pub fn new(key: &'a [u8; 16]) -> Self {
+        Self { key }
+    }
+}
+
+impl Mac for Retail3Des<'_> {
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn mac(&self, data: &[u8]) -> [u8; MAC_LENGTH] {
+        assert_eq!(data.len() % 8, 0);
+        let des1 = Des::new_from_slice(&self.key[..8]).unwrap();
+        let des2 = Des::new_from_slice(&self.key[8..]).unwrap();
+        let mut state = [0_u8; 8];
+        for block in data.chunks_exact(8) {
+            for i in 0..8 {
+                state[i] ^= block[i];
+            }
+            des1.encrypt_block((&mut state).into());
+        }
+        des2.decrypt_block((&mut state).into());
+        des1.encrypt_block((&mut state).into());
+        state
+    }
+}
+
+impl Mac for AesCmac<'_> {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn mac(&self, data: &[u8]) -> [u8; MAC_LENGTH] {
+        assert_eq!(data.len() % 16, 0);
+        let mac = match *self {
+            Self::Aes128(key) => {
+                let mut cmac = <Cmac<Aes128> as KeyInit>::new(key.into());
+                cmac.update(data);
+                cmac.finalize().into_bytes()
+            }
+            Self::Aes192(key) => {
+                let mut cmac = <Cmac<Aes192> as KeyInit>::new(key.into());
+                cmac.update(data);
+                cmac.finalize().into_bytes()
+            }
+            Self::Aes256(key) => {
+                let mut cmac = <Cmac<Aes256> as KeyInit>::new(key.into());
+                cmac.update(data);
+                cmac.finalize().into_bytes()
+            }
+        };
+        mac[..MAC_LENGTH].try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    /// Example from ICAO 9303-11 section D.3
+    #[test]
+    fn test_retail_3des() {
+        let key = hex!("7962D9ECE03D1ACD4C76089DCE131543");
+        let mac = Retail3Des::new(&key);
+        assert_eq!(
+            mac.mac_padded(&hex!(
+                "72C29C2371CC9BDB65B779B8E8D37B29ECC154AA56A8799FAE2F498F76ED92F2"
+            )),
+            hex!("5F1448EEA8AD90A7")
+        );
+    }
+
+    // NIST SP 800-38B section D.1 to D.3
+    #[test]
+    fn test_aes_cmac() {
+        let msg = hex!("6bc1bee2 2e409f96 e93d7e11 7393172a");
+        let key = hex!("2b7e1516 28aed2a6 abf71588 09cf4f3c");
+        assert_eq!(AesCmac::Aes128(&key).mac(&msg), hex!("070a16b4 6b4d4144"));
+        let key = hex!("8e73b0f7 da0e6452 c810f32b 809079e5 62f8ead2 522c6b7b");
+        assert_eq!(AesCmac::Aes192(&key).mac(&msg), hex!("9e99a7bf 31e71090"));
+        let key = hex!("603deb10 15ca71be 2b73aef0 857d7781 1f352c07 3b6108d7 2d9810a3 0914dff4");
+        assert_eq!(AesCmac::Aes256(&key).mac(&msg), hex!("28a7023f 452e8f82"));
+    }
+}
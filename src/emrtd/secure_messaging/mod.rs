@@ -1,35 +1,92 @@
 //! Secure Messaging
 
 pub mod aes;
+pub mod mac;
 pub mod tdes;
 
 use {
     self::{
-        aes::{Aes128Cipher, Aes192Cipher, Aes256Cipher},
-        tdes::TDesCipher,
+        aes::{kdf_128, kdf_192, kdf_256, Aes128Cipher, Aes192Cipher, Aes256Cipher},
+        tdes::{kdf_tdes, TDesCipher},
     },
     super::{pad, Error, Result},
     crate::{
         asn1::security_info::SymmetricCipher,
         ensure_err,
-        iso7816::{parse_apdu, StatusWord},
+        iso7816::{parse_apdu, Le, StatusWord},
     },
+    std::fmt,
 };
 
+// Key derivation counters, see ICAO 9303-11 section 9.7.1.
+/// Counter for the encryption key `K_enc`.
 pub const KDF_ENC: u32 = 1;
+/// Counter for the MAC key `K_mac`.
 pub const KDF_MAC: u32 = 2;
+/// Counter for the PACE password key `K_pi`.
+pub const KDF_PACE: u32 = 3;
 
-pub trait SecureMessaging {
+/// How far from the expected send sequence counter to look when a response MAC fails.
+const SSC_WINDOW: u64 = 2;
+
+/// Protection of APDUs and responses.
+///
+/// Implementations are [`Send`] so a session can move to another thread with its reader.
+pub trait SecureMessaging: Send {
     fn enc_apdu(&mut self, apdu: &[u8]) -> Result<Vec<u8>>;
     fn dec_response(&mut self, status: StatusWord, resp: &[u8]) -> Result<Vec<u8>>;
+
+    /// The cipher protecting APDUs, `None` for plain text.
+    fn cipher(&self) -> Option<SymmetricCipher> {
+        None
+    }
+
+    /// Export the session keys and send sequence counter, if any.
+    #[cfg(feature = "dangerous")]
+    fn export_session_keys(&self) -> Option<SessionKeys> {
+        None
+    }
 }
 
-pub trait Cipher {
+pub trait Cipher: Send {
+    const CIPHER: SymmetricCipher;
+
     fn from_seed(seed: &[u8]) -> Self;
     fn block_size(&self) -> usize;
     fn enc(&self, ssc: u64, data: &mut [u8]);
     fn dec(&self, ssc: u64, data: &mut [u8]);
+
+    /// MAC over `data`, which must already be padded to whole blocks.
+    ///
+    /// Implementations delegate to the [`mac::Mac`] of their cipher suite.
     fn mac(&self, ssc: u64, data: &[u8]) -> [u8; 8];
+
+    /// The cipher and its `(kenc, kmac)` keys.
+    #[cfg(feature = "dangerous")]
+    fn export_keys(&self) -> (SymmetricCipher, Vec<u8>, Vec<u8>);
+}
+
+/// Secure Messaging session keys, for validating against other implementations.
+///
+/// Anyone holding these can read and forge the session, never use this in production.
+/// The keys are zeroized on drop.
+#[cfg(feature = "dangerous")]
+#[derive(Clone, Debug)]
+pub struct SessionKeys {
+    pub cipher: SymmetricCipher,
+    pub ksenc: Vec<u8>,
+    pub ksmac: Vec<u8>,
+    pub ssc: u64,
+}
+
+#[cfg(feature = "dangerous")]
+impl Drop for SessionKeys {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.ksenc.zeroize();
+        self.ksmac.zeroize();
+        self.ssc.zeroize();
+    }
 }
 
 /// Secure Messaging protocol that passes APDUs and responses as-is.
@@ -41,6 +98,15 @@ pub struct Encrypted<C: Cipher> {
     ssc: u64,
 }
 
+impl<C: Cipher + fmt::Debug> fmt::Debug for Encrypted<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encrypted")
+            .field("cipher", &self.cipher)
+            .field("ssc", &self.ssc)
+            .finish()
+    }
+}
+
 pub fn construct_secure_messaging(
     cipher: SymmetricCipher,
     seed: &[u8],
@@ -54,6 +120,18 @@ pub fn construct_secure_messaging(
     }
 }
 
+/// Derive a key for the given cipher from a shared secret and counter.
+///
+/// ICAO 9303-11 section 9.7.1
+pub fn derive_key(cipher: SymmetricCipher, seed: &[u8], counter: u32) -> Vec<u8> {
+    match cipher {
+        SymmetricCipher::Tdes => kdf_tdes(seed, counter).to_vec(),
+        SymmetricCipher::Aes128 => kdf_128(seed, counter).to_vec(),
+        SymmetricCipher::Aes192 => kdf_192(seed, counter).to_vec(),
+        SymmetricCipher::Aes256 => kdf_256(seed, counter).to_vec(),
+    }
+}
+
 impl SecureMessaging for PlainText {
     fn enc_apdu(&mut self, apdu: &[u8]) -> Result<Vec<u8>> {
         Ok(apdu.to_vec())
@@ -68,9 +146,115 @@ impl<C: Cipher> Encrypted<C> {
     pub fn new(cipher: C, ssc: u64) -> Self {
         Self { cipher, ssc }
     }
+
+    /// MAC input `N` for a protected command without DO'8E'.
+    ///
+    /// The SSC and the masked header are padded to a block boundary, then the data objects
+    /// following Lc are appended and padded again, both with ISO 9797-1 padding method 2.
+    /// The MAC is then computed over whole blocks, without further padding.
+    ///
+    /// See ICAO 9303-11 section 9.8.5.1.
+    fn command_mac_input(&self, ssc: u64, papdu: &[u8], extended_length: bool) -> Vec<u8> {
+        let mut n = vec![0; self.cipher.block_size() - 8];
+        n.extend_from_slice(&ssc.to_be_bytes());
+        n.extend_from_slice(&papdu[..4]);
+        pad(&mut n, self.cipher.block_size());
+        n.extend_from_slice(&papdu[if extended_length { 7 } else { 5 }..]);
+        pad(&mut n, self.cipher.block_size());
+        n
+    }
+
+    /// MAC over the response data objects for a given send sequence counter.
+    fn response_mac(&self, ssc: u64, resp: &[u8]) -> [u8; 8] {
+        let mut n = vec![0; self.cipher.block_size() - 8];
+        n.extend_from_slice(&ssc.to_be_bytes());
+        n.extend_from_slice(resp);
+        pad(&mut n, self.cipher.block_size());
+        self.cipher.mac(ssc, &n)
+    }
+
+    /// Decrypt a leading DO'85' or DO'87' object, returning the plaintext and remaining data.
+    fn decrypt_data_object<'a>(&self, resp: &'a [u8]) -> Result<(Vec<u8>, &'a [u8])> {
+        ensure_err!(resp.len() >= 11, Error::SMResponseInvalid);
+        ensure_err!(resp[0] == 0x85 || resp[0] == 0x87, Error::SMResponseInvalid);
+        // Parse BER-TLV length
+        let (tl_len, length) = match resp[1] {
+            0x00..=0x7F => (2, resp[1] as usize),
+            0x81 => (3, resp[2] as usize),
+            0x82 => (4, u16::from_be_bytes([resp[2], resp[3]]) as usize),
+            0x83 => (
+                5,
+                u32::from_be_bytes([0, resp[2], resp[3], resp[4]]) as usize,
+            ),
+            0x84 => (
+                6,
+                u32::from_be_bytes([resp[2], resp[3], resp[4], resp[5]]) as usize,
+            ),
+            _ => {
+                return Err(Error::SMResponseInvalid);
+            }
+        };
+        let resp = &resp[tl_len..];
+        ensure_err!(
+            length >= 1 && resp.len() >= length,
+            Error::SMResponseInvalid
+        );
+        let (object, rest) = resp.split_at(length);
+        ensure_err!(object[0] == 0x01, Error::SMResponseInvalid);
+        let mut data = object[1..].to_vec();
+        ensure_err!(
+            data.len() % self.cipher.block_size() == 0,
+            Error::SMResponseInvalid
+        );
+        self.cipher.dec(self.ssc, &mut data);
+        let length = data
+            .iter()
+            .rposition(|&x| x == 0x80)
+            .ok_or(Error::SMResponseInvalid)?; // Unpadding failed
+        data.truncate(length);
+        Ok((data, rest))
+    }
+}
+
+/// Append a BER-TLV length, data objects of extended length APDUs exceed 127 bytes.
+fn push_length(out: &mut Vec<u8>, length: usize) {
+    match length {
+        0x00..=0x7F => out.push(length as u8),
+        0x80..=0xFF => out.extend_from_slice(&[0x81, length as u8]),
+        _ => out.extend_from_slice(&[0x82, (length >> 8) as u8, length as u8]),
+    }
+}
+
+#[cfg(test)]
+impl<C: Cipher> Encrypted<C> {
+    /// Protect a response as the chip would, for testing the terminal side.
+    ///
+    /// Call after [`SecureMessaging::enc_apdu`] on the same instance.
+    pub fn protect_response(&mut self, status: StatusWord, data: &[u8]) -> Vec<u8> {
+        self.ssc = self.ssc.wrapping_add(1);
+        let mut resp = Vec::new();
+        if !data.is_empty() {
+            let mut payload = data.to_vec();
+            pad(&mut payload, self.cipher.block_size());
+            self.cipher.enc(self.ssc, &mut payload);
+            resp.push(0x87);
+            push_length(&mut resp, payload.len() + 1);
+            resp.push(0x01);
+            resp.extend_from_slice(&payload);
+        }
+        resp.extend_from_slice(&[0x99, 0x02, status.sw1(), status.sw2()]);
+        let mac = self.response_mac(self.ssc, &resp);
+        resp.extend_from_slice(&[0x8E, 0x08]);
+        resp.extend_from_slice(&mac);
+        resp
+    }
 }
 
 impl<C: Cipher> SecureMessaging for Encrypted<C> {
+    fn cipher(&self) -> Option<SymmetricCipher> {
+        Some(C::CIPHER)
+    }
+
     fn enc_apdu(&mut self, apdu: &[u8]) -> Result<Vec<u8>> {
         // Increment send sequence counter
         let ssc = self.ssc.wrapping_add(1);
@@ -97,38 +281,27 @@ impl<C: Cipher> SecureMessaging for Encrypted<C> {
             pad(&mut payload, self.cipher.block_size());
             self.cipher.enc(ssc, &mut payload);
             papdu.push(if ins_even { 0x87 } else { 0x85 });
-            papdu.push((payload.len() + 1) as u8);
+            push_length(&mut papdu, payload.len() + 1);
             papdu.push(0x01); // Tag for 80 00* padding
             papdu.extend_from_slice(&payload);
         }
 
-        // Write Le
-        if !apdu.le.is_empty() {
+        // Write protected Le, only if the command has an Le. The MAC covers the data
+        // objects in this order, DO'85' or DO'87' before DO'97'.
+        if let Some(le) = apdu.expected_length() {
+            let le = le.to_bytes();
             papdu.push(0x97);
-            papdu.push(apdu.le.len() as u8);
-            papdu.extend_from_slice(apdu.le);
+            papdu.push(le.len() as u8);
+            papdu.extend_from_slice(&le);
         }
 
         // Write MAC (mandatory)
-        {
-            // Prepare MAC input
-            let mut message = vec![0; self.cipher.block_size() - 8];
-            message.extend_from_slice(&ssc.to_be_bytes());
-            message.extend_from_slice(&papdu[..4]);
-            pad(&mut message, self.cipher.block_size());
-            if extended_length {
-                message.extend_from_slice(&papdu[7..]);
-            } else {
-                message.extend_from_slice(&papdu[5..]);
-            }
-            pad(&mut message, self.cipher.block_size());
-
-            // Compute MAC and append to papdu
-            let mac = self.cipher.mac(ssc, &message);
-            papdu.push(0x8E);
-            papdu.push(mac.len() as u8);
-            papdu.extend_from_slice(&mac);
-        }
+        let mac = self
+            .cipher
+            .mac(ssc, &self.command_mac_input(ssc, &papdu, extended_length));
+        papdu.push(0x8E);
+        papdu.push(mac.len() as u8);
+        papdu.extend_from_slice(&mac);
 
         // Patch data length
         if extended_length {
@@ -139,12 +312,8 @@ impl<C: Cipher> SecureMessaging for Encrypted<C> {
             papdu[4] = (papdu.len() - 5) as u8;
         }
 
-        // Write Le
-        if extended_length {
-            papdu.extend_from_slice(&[0x00, 0x00]);
-        } else {
-            papdu.extend_from_slice(&[0x00]);
-        };
+        // Write Le, the protected response length is not known in advance.
+        Le::max(extended_length).append_to(&mut papdu);
 
         // Commit SSC
         self.ssc = ssc;
@@ -152,7 +321,7 @@ impl<C: Cipher> SecureMessaging for Encrypted<C> {
     }
 
     fn dec_response(&mut self, status: StatusWord, resp: &[u8]) -> Result<Vec<u8>> {
-        ensure_err!(resp.len() >= 14, Error::SMResponseInvalid);
+        ensure_err!(resp.len() >= 10, Error::SMResponseInvalid);
 
         // Split off DO'8E object containing MAC
         let (resp, mac) = resp.split_at(resp.len() - 10);
@@ -162,64 +331,54 @@ impl<C: Cipher> SecureMessaging for Encrypted<C> {
 
         // Compute and verify MAC
         self.ssc = self.ssc.wrapping_add(1);
-        let mut n = vec![0; self.cipher.block_size() - 8];
-        n.extend_from_slice(&self.ssc.to_be_bytes());
-        n.extend_from_slice(resp);
-        pad(&mut n, self.cipher.block_size());
-        let mac2 = self.cipher.mac(self.ssc, &n);
-        ensure_err!(mac == mac2, Error::SMResponseMacFailed);
-
-        // Split off DO'99 object and check (redundant) status word.
-        // TODO: DO'99 is optional, so we should check if it's present.
-        // TODO: DO'99 is allowed to be empty.
-        let (resp, do99) = resp.split_at(resp.len() - 4);
-        ensure_err!(do99[0] == 0x99, Error::SMResponseInvalid);
-        ensure_err!(do99[1] == 0x02, Error::SMResponseInvalid);
-        ensure_err!(do99[2] == status.sw1(), Error::SMResponseInvalid);
-        ensure_err!(do99[3] == status.sw2(), Error::SMResponseInvalid);
-
-        // If no data remaining there was no response data
-        if resp.is_empty() {
-            return Ok(Vec::new());
+        if mac != self.response_mac(self.ssc, resp) {
+            // Diagnose replayed or skipped responses by trying nearby counters.
+            let expected = self.ssc;
+            let actual = (1..=SSC_WINDOW)
+                .flat_map(|d| [expected.wrapping_sub(d), expected.wrapping_add(d)])
+                .find(|&ssc| mac == self.response_mac(ssc, resp));
+            return Err(match actual {
+                Some(actual) => {
+                    // The chip is ahead, continue from its counter. A replayed response
+                    // leaves the counter as is, it must never be reused.
+                    if actual.wrapping_sub(expected) <= SSC_WINDOW {
+                        self.ssc = actual;
+                    }
+                    Error::SscMismatch { expected, actual }
+                }
+                None => Error::SMResponseMacFailed,
+            });
         }
 
-        // Decrypt DO'87 response data object
-        // TODO: Allow for trailing data.
-        ensure_err!(resp.len() >= 11, Error::SMResponseInvalid);
-        ensure_err!(resp[0] == 0x85 || resp[0] == 0x87, Error::SMResponseInvalid);
-        // Parse BER-TLV length
-        let (tl_len, length) = match resp[1] {
-            0x00..=0x7F => (2, resp[1] as usize),
-            0x81 => (3, resp[2] as usize),
-            0x82 => (4, u16::from_be_bytes([resp[2], resp[3]]) as usize),
-            0x83 => (
-                5,
-                u32::from_be_bytes([0, resp[2], resp[3], resp[4]]) as usize,
-            ),
-            0x84 => (
-                6,
-                u32::from_be_bytes([resp[2], resp[3], resp[4], resp[5]]) as usize,
-            ),
-            _ => {
-                return Err(Error::SMResponseInvalid);
-            }
+        // Decrypt DO'85' or DO'87' response data object, if present.
+        let (data, resp) = match resp.first() {
+            Some(0x85 | 0x87) => self.decrypt_data_object(resp)?,
+            _ => (Vec::new(), resp),
         };
-        let resp = &resp[tl_len..];
-        ensure_err!(resp.len() == length, Error::SMResponseInvalid);
-        ensure_err!(resp[0] == 0x01, Error::SMResponseInvalid);
-        let mut resp = resp[1..].to_vec();
-        ensure_err!(
-            resp.len() % self.cipher.block_size() == 0,
-            Error::SMResponseInvalid
-        );
-        self.cipher.dec(self.ssc, &mut resp);
-        let length = resp
-            .iter()
-            .rposition(|&x| x == 0x80)
-            .ok_or(Error::SMResponseInvalid)?; // Unpadding failed
-        resp.truncate(length);
 
-        Ok(resp)
+        // Check the (redundant) status word in DO'99', if present.
+        // Some chips omit it, in which case the status word of the response applies.
+        match resp {
+            [] | [0x99, 0x00] => {}
+            [0x99, 0x02, sw1, sw2] => {
+                ensure_err!(*sw1 == status.sw1(), Error::SMResponseInvalid);
+                ensure_err!(*sw2 == status.sw2(), Error::SMResponseInvalid);
+            }
+            _ => return Err(Error::SMResponseInvalid),
+        }
+
+        Ok(data)
+    }
+
+    #[cfg(feature = "dangerous")]
+    fn export_session_keys(&self) -> Option<SessionKeys> {
+        let (cipher, ksenc, ksmac) = self.cipher.export_keys();
+        Some(SessionKeys {
+            cipher,
+            ksenc,
+            ksmac,
+            ssc: self.ssc,
+        })
     }
 }
 
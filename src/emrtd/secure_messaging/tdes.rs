@@ -1,14 +1,19 @@
 //! 3DES cipher for Secure Messaging
 
 use {
-    super::{Cipher, KDF_ENC, KDF_MAC},
+    super::{
+        mac::{Mac as _, Retail3Des},
+        Cipher, SymmetricCipher, KDF_ENC, KDF_MAC,
+    },
+    crate::utils::Redacted,
     cbc::{Decryptor as CbcDec, Encryptor as CbcEnc},
     cipher::{
-        block_padding::NoPadding, BlockDecrypt as _, BlockDecryptMut as _, BlockEncrypt as _,
-        BlockEncryptMut as _, InnerIvInit as _, KeyInit as _,
+        block_padding::NoPadding, BlockDecryptMut as _, BlockEncryptMut as _, InnerIvInit as _,
+        KeyInit as _,
     },
-    des::{Des, TdesEde2},
+    des::TdesEde2,
     sha1::{Digest, Sha1},
+    std::fmt,
 };
 
 const BLOCK_SIZE: usize = 8;
@@ -18,11 +23,38 @@ pub struct TDesCipher {
     kmac: [u8; 16],
 }
 
+impl TDesCipher {
+    /// Construct from session keys, e.g. as given in test vectors, instead of a key seed.
+    pub fn new(kenc: [u8; 16], kmac: [u8; 16]) -> Self {
+        Self { kenc, kmac }
+    }
+
+    pub fn kenc(&self) -> &[u8; 16] {
+        &self.kenc
+    }
+
+    pub fn kmac(&self) -> &[u8; 16] {
+        &self.kmac
+    }
+}
+
+/// The keys are redacted, see [`Redacted`].
+impl fmt::Debug for TDesCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TDesCipher")
+            .field("kenc", &Redacted(&self.kenc))
+            .field("kmac", &Redacted(&self.kmac))
+            .finish()
+    }
+}
+
 impl Cipher for TDesCipher {
+    const CIPHER: SymmetricCipher = SymmetricCipher::Tdes;
+
     fn from_seed(seed: &[u8]) -> Self {
         Self {
-            kenc: kdf(seed, KDF_ENC),
-            kmac: kdf(seed, KDF_MAC),
+            kenc: kdf_tdes(seed, KDF_ENC),
+            kmac: kdf_tdes(seed, KDF_MAC),
         }
     }
 
@@ -49,26 +81,23 @@ impl Cipher for TDesCipher {
         block_mode.decrypt_padded_mut::<NoPadding>(data).unwrap();
     }
 
-    /// Retail MAC (ISO 9797-1 mode 3) using DES.
-    // See <https://crypto.stackexchange.com/questions/18951/what-are-options-to-compute-des-retail-mac-aka-iso-9797-1-mode-3-under-pkcs11>
     fn mac(&self, _ssc: u64, data: &[u8]) -> [u8; 8] {
-        assert_eq!(data.len() % BLOCK_SIZE, 0);
-        let des1 = Des::new_from_slice(&self.kmac[..8]).unwrap();
-        let des2 = Des::new_from_slice(&self.kmac[8..]).unwrap();
-        let mut state = [0_u8; 8];
-        for block in data.chunks_exact(8) {
-            for i in 0..8 {
-                state[i] ^= block[i];
-            }
-            des1.encrypt_block((&mut state).into());
-        }
-        des2.decrypt_block((&mut state).into());
-        des1.encrypt_block((&mut state).into());
-        state
+        Retail3Des::new(&self.kmac).mac(data)
+    }
+
+    #[cfg(feature = "dangerous")]
+    fn export_keys(&self) -> (super::SymmetricCipher, Vec<u8>, Vec<u8>) {
+        (
+            super::SymmetricCipher::Tdes,
+            self.kenc.to_vec(),
+            self.kmac.to_vec(),
+        )
     }
 }
 
-fn kdf(seed: &[u8], counter: u32) -> [u8; 16] {
+/// Key Derivation Function (KDF) for two-key 3DES keys, with parity bits set.
+/// ICAO 9303-11 section 9.7.1.1
+pub fn kdf_tdes(seed: &[u8], counter: u32) -> [u8; 16] {
     let mut hasher = Sha1::new();
     hasher.update(seed);
     hasher.update(counter.to_be_bytes());
@@ -90,7 +119,7 @@ fn set_parity_bits(key: &mut [u8]) {
 mod tests {
     use {
         super::{super::SecureMessaging, *},
-        crate::emrtd::{pad, secure_messaging::Encrypted, seed_from_mrz},
+        crate::emrtd::{pad, secure_messaging::Encrypted, seed_from_mrz, Error},
         hex_literal::hex,
     };
 
@@ -101,7 +130,7 @@ mod tests {
         let seed = seed_from_mrz(mrz);
         assert_eq!(seed, hex!("239AB9CB282DAF66231DC5A4DF6BFBAE"));
 
-        let (kenc, kmac) = (kdf(&seed, KDF_ENC), kdf(&seed, KDF_MAC));
+        let (kenc, kmac) = (kdf_tdes(&seed, KDF_ENC), kdf_tdes(&seed, KDF_MAC));
         assert_eq!(kenc, hex!("AB94FDECF2674FDFB9B391F85D7F76F2"));
         assert_eq!(kmac, hex!("7962D9ECE03D1ACD4C76089DCE131543"));
     }
@@ -110,7 +139,7 @@ mod tests {
     #[test]
     fn test_derive_keys() {
         let k_seed = hex!("0036D272F5C350ACAC50C3F572D23600");
-        let (kenc, kmac) = (kdf(&k_seed, KDF_ENC), kdf(&k_seed, KDF_MAC));
+        let (kenc, kmac) = (kdf_tdes(&k_seed, KDF_ENC), kdf_tdes(&k_seed, KDF_MAC));
         assert_eq!(kenc, hex!("979EC13B1CBFE9DCD01AB0FED307EAE5"));
         assert_eq!(kmac, hex!("F1CB1F1FB5ADF208806B89DC579DC1F8"));
     }
@@ -175,6 +204,27 @@ mod tests {
         assert_eq!(res, enc);
     }
 
+    #[test]
+    fn test_tdes_sm_le() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let mut tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), 0);
+
+        // No Le, so no DO'97'. DO'87' is directly followed by DO'8E'.
+        let papdu = tdes.enc_apdu(&hex!("00 A4 02 0C 02 01 1E")).unwrap();
+        assert_eq!(papdu[5], 0x87);
+        assert_eq!(papdu[16], 0x8E);
+
+        // Short Le of zero means 256.
+        let papdu = tdes.enc_apdu(&hex!("00 B0 00 00 00")).unwrap();
+        assert_eq!(papdu[5..8], hex!("97 01 00"));
+        assert_eq!(papdu[8], 0x8E);
+
+        // Extended Le of zero means 65536.
+        let papdu = tdes.enc_apdu(&hex!("00 B0 00 00 00 00 00")).unwrap();
+        assert_eq!(papdu[7..11], hex!("97 02 0000"));
+        assert_eq!(papdu[11], 0x8E);
+    }
+
     // Example from ICAO 9303-11 section D.4
     #[test]
     fn test_tdes_sm() {
@@ -189,6 +239,15 @@ mod tests {
             papdu,
             hex!("0CA4020C158709016375432908C044F68E08BF8B92D635FF24F800")
         );
+
+        // The same from the session keys in the example.
+        let cipher = TDesCipher::new(
+            hex!("979EC13B1CBFE9DCD01AB0FED307EAE5"),
+            hex!("F1CB1F1FB5ADF208806B89DC579DC1F8"),
+        );
+        let mut from_keys = Encrypted::new(cipher, ssc);
+        assert_eq!(from_keys.enc_apdu(&apdu).unwrap(), papdu);
+
         let rapdu = hex!("990290008E08FA855A5D4C50A8ED");
         let dec = tdes.dec_response(0x9000.into(), &rapdu).unwrap();
         assert_eq!(dec, hex!(""));
@@ -211,4 +270,133 @@ mod tests {
         let data = tdes.dec_response(0x9000.into(), &rapdu).unwrap();
         assert_eq!(data, hex!("04303130365F36063034303030305C026175"));
     }
+
+    // READ BINARY from ICAO 9303-11 section D.4, DO'97' is the only data object.
+    #[test]
+    fn test_tdes_command_mac_without_data() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C229;
+        let tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+        let n = tdes.command_mac_input(ssc, &hex!("0CB00000 00 970104"), false);
+        assert_eq!(
+            n,
+            hex!("887022120C06C229 0CB0000080000000 9701048000000000")
+        );
+        assert_eq!(tdes.cipher.mac(ssc, &n), hex!("ED6705417E96BA55"));
+
+        // Without Le nothing follows the header, the padding adds a full block.
+        let n = tdes.command_mac_input(ssc, &hex!("0C820000 00"), false);
+        assert_eq!(n[16..], hex!("8000000000000000"));
+    }
+
+    // SELECT requesting the FCP has both command data and Le.
+    #[test]
+    fn test_tdes_command_with_data_and_le() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+        let mut tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+        let papdu = tdes.enc_apdu(&hex!("00 A4 02 04 02 011E 00")).unwrap();
+        // DO'87' as in ICAO 9303-11 section D.4, then DO'97', both covered by the MAC.
+        assert_eq!(
+            papdu,
+            hex!("0CA40204 18 8709016375432908C044F6 970100 8E08D8E90BE5359A09DD 00")
+        );
+
+        // Data objects longer than 127 bytes use a long form length.
+        let mut apdu = hex!("00 D6 0000 80").to_vec();
+        apdu.extend_from_slice(&[0x42; 0x80]);
+        let papdu = tdes.enc_apdu(&apdu).unwrap();
+        assert_eq!(papdu[4..8], hex!("96 87 8189"));
+    }
+
+    #[test]
+    fn test_tdes_sm_response_errors() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+        let rapdu = hex!("990290008E08FA855A5D4C50A8ED");
+
+        // A corrupted MAC.
+        let mut tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+        tdes.enc_apdu(&hex!("00 A4 02 0C 02 01 1E")).unwrap();
+        let mut bad_mac = rapdu;
+        bad_mac[13] ^= 1;
+        let err = tdes.dec_response(0x9000.into(), &bad_mac).unwrap_err();
+        assert!(matches!(err, Error::SMResponseMacFailed));
+
+        // A valid MAC, but DO'99' differs from the transported status word.
+        let mut tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+        tdes.enc_apdu(&hex!("00 A4 02 0C 02 01 1E")).unwrap();
+        let err = tdes.dec_response(0x6A82.into(), &rapdu).unwrap_err();
+        assert!(matches!(err, Error::SMResponseInvalid));
+    }
+
+    #[test]
+    fn test_tdes_sm_replayed_response() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+        let mut tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+
+        tdes.enc_apdu(&hex!("00 A4 02 0C 02 01 1E")).unwrap();
+        let rapdu = hex!("990290008E08FA855A5D4C50A8ED");
+        tdes.dec_response(0x9000.into(), &rapdu).unwrap();
+
+        // Replay the SELECT response to the next command.
+        tdes.enc_apdu(&hex!("00 B0 00 00 04")).unwrap();
+        let err = tdes.dec_response(0x9000.into(), &rapdu).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::SscMismatch { expected, actual } if expected == ssc + 4 && actual == ssc + 2
+        ));
+    }
+
+    #[test]
+    fn test_tdes_sm_resynchronize() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+        let mut tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+        let mut chip = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+
+        // The chip processed one exchange more than the terminal saw.
+        chip.enc_apdu(&hex!("00 A4 02 0C 02 01 1E")).unwrap();
+        chip.protect_response(0x9000.into(), &[]);
+        tdes.enc_apdu(&hex!("00 B0 00 00 04")).unwrap();
+        chip.enc_apdu(&hex!("00 B0 00 00 04")).unwrap();
+        let rapdu = chip.protect_response(0x9000.into(), &hex!("60145F01"));
+        let err = tdes.dec_response(0x9000.into(), &rapdu).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::SscMismatch { expected, actual } if expected == ssc + 2 && actual == ssc + 4
+        ));
+
+        // The next exchange uses the chip's counter.
+        let papdu = tdes.enc_apdu(&hex!("00 B0 00 04 12")).unwrap();
+        assert_eq!(papdu, chip.enc_apdu(&hex!("00 B0 00 04 12")).unwrap());
+        let rapdu = chip.protect_response(0x9000.into(), &[0x42; 0x12]);
+        assert_eq!(
+            tdes.dec_response(0x9000.into(), &rapdu).unwrap(),
+            [0x42; 0x12]
+        );
+    }
+
+    #[test]
+    fn test_tdes_sm_without_do99() {
+        let seed = hex!("0036D272F5C350ACAC50C3F572D23600");
+        let ssc = 0x887022120C06C226;
+        let mut tdes = Encrypted::new(TDesCipher::from_seed(&seed[..]), ssc);
+
+        // Select EF.COM, response carries only DO'8E'.
+        tdes.enc_apdu(&hex!("00 A4 02 0C 02 01 1E")).unwrap();
+        let mac = tdes.response_mac(ssc + 2, &[]);
+        let rapdu = [&hex!("8E08")[..], &mac].concat();
+        let dec = tdes.dec_response(0x9000.into(), &rapdu).unwrap();
+        assert_eq!(dec, hex!(""));
+
+        // Read Binary of first four bytes, MAC only over DO'87'.
+        tdes.enc_apdu(&hex!("00 B0 00 00 04")).unwrap();
+        let do87 = hex!("8709019FF0EC34F9922651");
+        let mac = tdes.response_mac(ssc + 4, &do87);
+        let rapdu = [&do87[..], &hex!("8E08"), &mac].concat();
+        let data = tdes.dec_response(0x9000.into(), &rapdu).unwrap();
+        assert_eq!(data, hex!("60145F01"));
+    }
 }
@@ -0,0 +1,107 @@
+//! Travel records in the LDS2 application.
+//!
+//! Entry and exit stamps are stored as records of linear files, each signed by the
+//! issuing state. Access requires PACE, Chip Authentication and the matching Terminal
+//! Authentication authorization.
+//!
+//! See ICAO 9303-10 section 5.
+
+use {
+    super::{DedicatedId, Emrtd, Error, Result, State},
+    crate::{
+        ensure_err,
+        iso7816::{CommandApdu, Le, StatusWord},
+    },
+};
+
+/// Short EF identifier of EF.EntryRecords.
+pub const ENTRY_RECORDS_SFI: u8 = 0x01;
+
+/// Short EF identifier of EF.ExitRecords.
+pub const EXIT_RECORDS_SFI: u8 = 0x02;
+
+/// The raw travel records, as the TLV encoded, signed records stored on the chip.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct TravelRecords {
+    pub entry: Vec<Vec<u8>>,
+    pub exit: Vec<Vec<u8>>,
+}
+
+impl Emrtd {
+    /// Select the LDS2 travel records application and read all entry and exit records.
+    ///
+    /// Fails with [`Error::TerminalAuthenticationRequired`] if the chip denies access, which
+    /// is expected until Terminal Authentication is implemented.
+    pub fn read_travel_records(&mut self) -> Result<TravelRecords> {
+        self.require_state(State::Session)?;
+        self.select_dedicated_file(DedicatedId::TravelRecords.aid().unwrap())?;
+        Ok(TravelRecords {
+            entry: self.read_records(ENTRY_RECORDS_SFI)?,
+            exit: self.read_records(EXIT_RECORDS_SFI)?,
+        })
+    }
+
+    /// Read all records of a linear file by short EF identifier, until one is not found.
+    pub fn read_records(&mut self, file: u8) -> Result<Vec<Vec<u8>>> {
+        let mut records = Vec::new();
+        for number in 1..=u8::MAX {
+            match self.read_record(file, number) {
+                Ok(record) => records.push(record),
+                Err(Error::ErrorResponse(StatusWord::RECORD_NOT_FOUND)) => break,
+                Err(Error::ErrorResponse(StatusWord::ACCESS_DENIED)) => {
+                    return Err(Error::TerminalAuthenticationRequired)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Read record `number` of a file by short EF identifier.
+    ///
+    /// See ISO 7816-4 section 11.4.3.
+    pub fn read_record(&mut self, file: u8, number: u8) -> Result<Vec<u8>> {
+        ensure_err!(file > 0 && file <= 0x1F, Error::InvalidShortFileId);
+        // P2 b3..b1 = 100 reads the record numbered P1.
+        let apdu = CommandApdu::new(0x00, 0xB2, number, (file << 3) | 0x04).with_le(Le::max(false));
+        let response = self.send_apdu(&apdu.to_bytes())?;
+        ensure_err!(response.is_success(), response.status.into());
+        Ok(response.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::nfc::mock::MockReader, hex_literal::hex};
+
+    #[test]
+    fn test_read_travel_records() {
+        let nfc = MockReader::default()
+            .expect(
+                &hex!("00 A4 0400 07 A0000002472001 00"),
+                StatusWord::SUCCESS,
+                &[],
+            )
+            .expect(
+                &hex!("00 B2 010C 00"),
+                StatusWord::SUCCESS,
+                &hex!("61 01 00"),
+            )
+            .expect(&hex!("00 B2 020C 00"), StatusWord::RECORD_NOT_FOUND, &[])
+            .expect(&hex!("00 B2 0114 00"), StatusWord::RECORD_NOT_FOUND, &[]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        card.state = State::Session;
+        let records = card.read_travel_records().unwrap();
+        assert_eq!(records.entry, vec![hex!("61 01 00").to_vec()]);
+        assert!(records.exit.is_empty());
+        assert_eq!(card.parent, DedicatedId::TravelRecords);
+
+        let nfc =
+            MockReader::default().expect(&hex!("00 B2 010C 00"), StatusWord::ACCESS_DENIED, &[]);
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert!(matches!(
+            card.read_records(ENTRY_RECORDS_SFI),
+            Err(Error::TerminalAuthenticationRequired)
+        ));
+    }
+}
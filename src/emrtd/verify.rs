@@ -0,0 +1,504 @@
+//! Document verification with a serializable report.
+
+use {
+    super::{files::FileCache, Emrtd, Error, FileId, Result, State},
+    crate::{
+        asn1::{EfSod, LdsSecurityObject},
+        iso7816::StatusWord,
+        lds::data_group::parse_dg1,
+        passive_authentication::{
+            check_chain, check_document_signer, check_security_object, check_signed_attributes,
+            security_object, verify_signature, TrustStore,
+        },
+    },
+    anyhow::Context,
+    der::Decode,
+    rand::Rng,
+    std::time::SystemTime,
+};
+
+/// Outcome of a single verification step.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "status", content = "reason", rename_all = "snake_case")
+)]
+pub enum Check {
+    Passed,
+    Failed(String),
+
+    /// Skipped, e.g. because it is not implemented, not configured or access was denied.
+    NotPerformed(String),
+}
+
+/// Result of verifying a document, suitable to persist as an audit record.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerificationReport {
+    /// Passive Authentication of EF.SOD.
+    ///
+    /// Only [`Check::Passed`] if all parts, including the signature, were verified.
+    pub passive: Check,
+
+    /// Active Authentication or Chip Authentication.
+    pub active: Check,
+
+    /// Certificate chain from the Document Signer to a CSCA in the trust store.
+    pub chain: Check,
+
+    /// Data group hashes compared to EF.SOD.
+    pub dg_hashes: Vec<DataGroupCheck>,
+
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataGroupCheck {
+    pub number: u8,
+    pub check: Check,
+}
+
+/// Integrity of a data group compared to its hash in EF.SOD.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DgIntegrity {
+    /// The hash matches EF.SOD.
+    Valid,
+
+    /// The hash differs from EF.SOD.
+    HashMismatch,
+
+    /// The data group was read, but is not listed in EF.SOD.
+    NotInSod,
+
+    /// Listed in EF.SOD, but the chip has no such file.
+    NotFound,
+
+    /// Listed in EF.SOD, but not read (yet).
+    NotRead,
+}
+
+impl Check {
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed(_))
+    }
+}
+
+impl VerificationReport {
+    /// True if Passive Authentication passed, the Document Signer chains to a trusted CSCA
+    /// and no other check failed.
+    ///
+    /// Active and Chip Authentication and data groups that require Extended Access Control
+    /// may be skipped.
+    pub fn is_valid(&self) -> bool {
+        self.chain == Check::Passed && self.is_consistent()
+    }
+
+    /// True if Passive Authentication passed and no other check failed.
+    ///
+    /// Unlike [`VerificationReport::is_valid`] the certificate chain may be skipped, so
+    /// this only shows that the document is consistent with a Document Signer, which may
+    /// be self-signed.
+    pub fn is_consistent(&self) -> bool {
+        self.passive == Check::Passed
+            && !self.active.is_failed()
+            && !self.chain.is_failed()
+            && !self.dg_hashes.iter().any(|dg| dg.check.is_failed())
+    }
+}
+
+impl Emrtd {
+    /// Read the document and verify it as far as supported.
+    ///
+    /// With `access`, an MRZ, the Chip Access Procedure is run first, see
+    /// [`Emrtd::access_procedure`]. Without, access control must have been performed
+    /// already. The Document Signer is checked against the CSCAs in `trust_store`.
+    ///
+    /// Failed checks are reported, only errors communicating with the chip or parsing
+    /// EF.SOD are returned as `Err`. Active and Chip Authentication are not implemented
+    /// and reported as not performed.
+    ///
+    /// See ICAO 9303-11 section 5.1.
+    pub fn verify(
+        &mut self,
+        rng: &mut impl Rng,
+        access: Option<&str>,
+        trust_store: &TrustStore,
+    ) -> anyhow::Result<VerificationReport> {
+        if let Some(mrz) = access {
+            self.access_procedure(rng, mrz)?;
+        }
+        self.verify_at(trust_store, SystemTime::now())
+            .context("Error verifying the document.")
+    }
+
+    /// Compare all data groups read so far to their hashes in EF.SOD.
+    ///
+    /// Only files already in the cache are used, no APDUs are sent. EF.SOD must have been
+    /// read. Data groups neither read nor listed in EF.SOD are omitted.
+    pub fn integrity_check(&self) -> Result<Vec<(u8, DgIntegrity)>> {
+        let lso = self.cached_security_object()?;
+        Ok(dg_integrity(&lso, &self.file_cache))
+    }
+
+    /// Compare `bytes` of data group `number` to its hash in EF.SOD.
+    ///
+    /// This checks a data group as soon as it is read, instead of all at the end. EF.SOD
+    /// must have been read, no APDUs are sent.
+    pub fn verify_single(&self, number: u8, bytes: &[u8]) -> Result<DgIntegrity> {
+        let lso = self.cached_security_object()?;
+        Ok(data_group_integrity(&lso, number, bytes))
+    }
+
+    /// The security object of a previously read EF.SOD.
+    fn cached_security_object(&self) -> Result<LdsSecurityObject> {
+        let Some(Some(sod)) = self.file_cache.get(&FileId::Sod) else {
+            return Err(Error::FileNotFound);
+        };
+        Ok(security_object(&EfSod::from_der(sod)?)?)
+    }
+
+    /// Like [`Emrtd::verify`] without `access`, but with certificate validity checked as of
+    /// `now`.
+    pub fn verify_at(
+        &mut self,
+        trust_store: &TrustStore,
+        now: SystemTime,
+    ) -> Result<VerificationReport> {
+        self.require_state(State::Session)?;
+        let mut warnings = self.warnings().to_vec();
+        let sod: EfSod = self.read_cached()?;
+
+        // Hashes can not be compared against a malformed security object.
+        let lso = sod.lds_security_object()?;
+        if let Err(e) = check_security_object(&lso) {
+            let reason = "malformed EF.SOD".to_owned();
+            return Ok(VerificationReport {
+                passive: Check::Failed(e.to_string()),
+                active: Check::NotPerformed(reason.clone()),
+                chain: Check::NotPerformed(reason),
+                dg_hashes: Vec::new(),
+                warnings,
+            });
+        }
+
+        // The document code is the first two characters of the MRZ.
+        let document_code = match self.read_file_cached(FileId::Dg1)? {
+            Some(dg1) => parse_dg1(&dg1)?.get(..2).map(str::to_owned),
+            None => None,
+        };
+        let passive = match check_signed_attributes(&sod) {
+            Err(e) => Check::Failed(e.to_string()),
+            Ok(()) => match document_code {
+                None => Check::Failed("EF.DG1 missing".to_owned()),
+                Some(code) => match check_document_signer(&sod, &code, now) {
+                    Err(e) => Check::Failed(e.to_string()),
+                    Ok(()) => match verify_signature(&sod) {
+                        Ok(()) => Check::Passed,
+                        Err(e) if e.is_unsupported() => Check::NotPerformed(e.to_string()),
+                        Err(e) => Check::Failed(e.to_string()),
+                    },
+                },
+            },
+        };
+
+        let mut dg_hashes = Vec::new();
+        for entry in &lso.data_group_hash_values {
+            let Some(file_id) = u8::try_from(entry.data_group_number)
+                .ok()
+                .and_then(FileId::data_group)
+            else {
+                warnings.push(format!(
+                    "Invalid data group number {} in EF.SOD",
+                    entry.data_group_number
+                ));
+                continue;
+            };
+            let check = match self.read_file_cached(file_id) {
+                Ok(Some(data))
+                    if lso.hash_algorithm.hash_bytes(&data) == entry.hash_value.as_bytes() =>
+                {
+                    Check::Passed
+                }
+                Ok(Some(_)) => Check::Failed("hash mismatch".to_owned()),
+                Ok(None) => Check::Failed("listed in EF.SOD but not found".to_owned()),
+                Err(Error::ErrorResponse(StatusWord::ACCESS_DENIED))
+                | Err(Error::TerminalAuthenticationRequired) => {
+                    Check::NotPerformed("access denied".to_owned())
+                }
+                Err(e) => return Err(e),
+            };
+            dg_hashes.push(DataGroupCheck {
+                number: entry.data_group_number as u8,
+                check,
+            });
+        }
+
+        let chain = match sod.document_signer_certificate() {
+            None => Check::Failed("Document Signer certificate missing".to_owned()),
+            Some(_) if trust_store.is_empty() => Check::NotPerformed("no trust store".to_owned()),
+            Some(certificate) => match check_chain(certificate, trust_store, now) {
+                Ok(()) => Check::Passed,
+                Err(e) if e.is_unsupported() => Check::NotPerformed(e.to_string()),
+                Err(e) => Check::Failed(e.to_string()),
+            },
+        };
+
+        // TODO: Active Authentication and Chip Authentication.
+        let active = Check::NotPerformed("not implemented".to_owned());
+
+        Ok(VerificationReport {
+            passive,
+            active,
+            chain,
+            dg_hashes,
+            warnings,
+        })
+    }
+}
+
+/// Compare the data groups in `files` to the hashes in `lso`.
+fn dg_integrity(lso: &LdsSecurityObject, files: &FileCache) -> Vec<(u8, DgIntegrity)> {
+    let mut result = Vec::new();
+    for number in 1..=16 {
+        let file = FileId::data_group(number).unwrap();
+        let integrity = match (lso.hash_for_dg(number as usize), files.get(&file)) {
+            (Some(_), Some(Some(data))) => data_group_integrity(lso, number, data),
+            (Some(_), Some(None)) => DgIntegrity::NotFound,
+            (Some(_), None) => DgIntegrity::NotRead,
+            (None, Some(Some(_))) => DgIntegrity::NotInSod,
+            (None, _) => continue,
+        };
+        result.push((number, integrity));
+    }
+    result
+}
+
+/// Compare `data` of data group `number` to its hash in `lso`.
+fn data_group_integrity(lso: &LdsSecurityObject, number: u8, data: &[u8]) -> DgIntegrity {
+    match lso.hash_for_dg(number as usize) {
+        Some(expected) if lso.hash_algorithm.hash_bytes(data) == expected => DgIntegrity::Valid,
+        Some(_) => DgIntegrity::HashMismatch,
+        None => DgIntegrity::NotInSod,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            asn1::{DataGroupHash, DigestAlgorithmIdentifier},
+            nfc::mock::MockReader,
+        },
+        der::asn1::OctetString,
+        hex_literal::hex,
+        std::time::Duration,
+    };
+
+    const SPECIMEN_DG1: &[u8] =
+        include_bytes!("../../tests/documents/specimen-td3-ecdsa/EF_DG1.bin");
+    const SPECIMEN_DG2: &[u8] =
+        include_bytes!("../../tests/documents/specimen-td3-ecdsa/EF_DG2.bin");
+    const SPECIMEN_SOD: &[u8] =
+        include_bytes!("../../tests/documents/specimen-td3-ecdsa/EF_SOD.bin");
+    const SPECIMEN_CSCA: &[u8] =
+        include_bytes!("../../tests/documents/specimen-td3-ecdsa/CSCA.der");
+    const OTHER_CSCA: &[u8] = include_bytes!("../../tests/documents/specimen-td1-rsa-pss/CSCA.der");
+
+    fn trust_store(csca: &[u8]) -> TrustStore {
+        let mut trust_store = TrustStore::new();
+        trust_store.add_der(csca).unwrap();
+        trust_store
+    }
+
+    /// A session with the specimen files already read, so no APDUs are sent.
+    fn specimen_card() -> Emrtd {
+        let mut card = Emrtd::new(Box::new(MockReader::default()));
+        card.state = State::Session;
+        card.file_cache
+            .insert(FileId::Dg1, Some(SPECIMEN_DG1.to_vec()));
+        card.file_cache
+            .insert(FileId::Dg2, Some(SPECIMEN_DG2.to_vec()));
+        card.file_cache
+            .insert(FileId::Sod, Some(SPECIMEN_SOD.to_vec()));
+        card
+    }
+
+    #[test]
+    fn test_dg_integrity() {
+        // SHA-256 without parameters.
+        let algorithm =
+            DigestAlgorithmIdentifier::from_der(&hex!("300B 0609 608648016503040201")).unwrap();
+        let entry = |number: u64, data: &[u8]| DataGroupHash {
+            data_group_number: number,
+            hash_value: OctetString::new(algorithm.hash_bytes(data)).unwrap(),
+        };
+        let lso = LdsSecurityObject {
+            version: 0,
+            hash_algorithm: algorithm.clone(),
+            data_group_hash_values: vec![
+                entry(1, b"dg1"),
+                entry(2, b"dg2"),
+                entry(3, b"dg3"),
+                entry(4, b"dg4"),
+            ],
+            lds_version_info: None,
+        };
+        let files = FileCache::from([
+            (FileId::Dg1, Some(b"dg1".to_vec())),
+            (FileId::Dg2, Some(b"tampered".to_vec())),
+            (FileId::Dg3, None),
+            (FileId::Dg11, Some(b"dg11".to_vec())),
+            (FileId::Dg12, None),
+        ]);
+        assert_eq!(
+            dg_integrity(&lso, &files),
+            vec![
+                (1, DgIntegrity::Valid),
+                (2, DgIntegrity::HashMismatch),
+                (3, DgIntegrity::NotFound),
+                (4, DgIntegrity::NotRead),
+                (11, DgIntegrity::NotInSod),
+            ]
+        );
+        assert_eq!(data_group_integrity(&lso, 4, b"dg4"), DgIntegrity::Valid);
+        assert_eq!(
+            data_group_integrity(&lso, 4, b"dg1"),
+            DgIntegrity::HashMismatch
+        );
+        assert_eq!(data_group_integrity(&lso, 5, b"dg5"), DgIntegrity::NotInSod);
+    }
+
+    #[test]
+    fn test_report_is_valid() {
+        let skipped = || Check::NotPerformed("not implemented".to_owned());
+        let mut report = VerificationReport {
+            passive: Check::Passed,
+            active: skipped(),
+            chain: skipped(),
+            dg_hashes: vec![
+                DataGroupCheck {
+                    number: 1,
+                    check: Check::Passed,
+                },
+                DataGroupCheck {
+                    number: 3,
+                    check: Check::NotPerformed("access denied".to_owned()),
+                },
+            ],
+            warnings: vec![],
+        };
+        assert!(!report.is_valid());
+        assert!(report.is_consistent());
+        report.chain = Check::Passed;
+        assert!(report.is_valid());
+
+        report.chain = Check::Failed("untrusted".to_owned());
+        assert!(!report.is_valid());
+        assert!(!report.is_consistent());
+        report.chain = Check::Passed;
+        report.passive = skipped();
+        assert!(!report.is_valid());
+        assert!(!report.is_consistent());
+    }
+
+    #[test]
+    fn test_verify_at() {
+        let sod = EfSod::from_der(SPECIMEN_SOD).unwrap();
+        let validity = &sod
+            .document_signer_certificate()
+            .unwrap()
+            .tbs_certificate
+            .validity;
+        let not_before = validity.not_before.to_system_time();
+        let not_after = validity.not_after.to_system_time();
+        let second = Duration::from_secs(1);
+
+        let trusted = trust_store(SPECIMEN_CSCA);
+        let report = specimen_card()
+            .verify_at(&trusted, not_before + second)
+            .unwrap();
+        assert_eq!(report.passive, Check::Passed);
+        assert_eq!(report.chain, Check::Passed);
+        assert!(report.is_valid());
+        assert_eq!(
+            report.dg_hashes,
+            [1, 2].map(|number| DataGroupCheck {
+                number,
+                check: Check::Passed,
+            })
+        );
+
+        // A self-signed or unknown Document Signer is not valid.
+        let report = specimen_card()
+            .verify_at(&TrustStore::new(), not_before + second)
+            .unwrap();
+        assert_eq!(
+            report.chain,
+            Check::NotPerformed("no trust store".to_owned())
+        );
+        assert!(report.is_consistent());
+        assert!(!report.is_valid());
+        let report = specimen_card()
+            .verify_at(&trust_store(OTHER_CSCA), not_before + second)
+            .unwrap();
+        assert_eq!(
+            report.chain,
+            Check::Failed(
+                "Document Signer certificate is not issued by a trusted CSCA.".to_owned()
+            )
+        );
+        assert!(!report.is_valid());
+
+        let report = specimen_card()
+            .verify_at(&trusted, not_before - second)
+            .unwrap();
+        assert_eq!(
+            report.passive,
+            Check::Failed("Certificate is not valid yet.".to_owned())
+        );
+        assert!(!report.is_valid());
+
+        // Data group hashes are checked regardless.
+        let report = specimen_card()
+            .verify_at(&trusted, not_after + second)
+            .unwrap();
+        assert_eq!(
+            report.passive,
+            Check::Failed("Certificate expired.".to_owned())
+        );
+        assert!(report.dg_hashes.iter().all(|dg| dg.check == Check::Passed));
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_report_serialization() {
+        let report = VerificationReport {
+            passive: Check::Passed,
+            active: Check::NotPerformed("not implemented".to_owned()),
+            chain: Check::Passed,
+            dg_hashes: vec![DataGroupCheck {
+                number: 1,
+                check: Check::Failed("hash mismatch".to_owned()),
+            }],
+            warnings: vec![],
+        };
+        assert!(!report.is_valid());
+        assert_eq!(
+            serde_json::to_value(&report.dg_hashes).unwrap(),
+            serde_json::json!([{
+                "number": 1,
+                "check": { "status": "failed", "reason": "hash mismatch" }
+            }])
+        );
+        assert_eq!(
+            serde_json::to_value(Check::Passed).unwrap(),
+            serde_json::json!({ "status": "passed" })
+        );
+    }
+}
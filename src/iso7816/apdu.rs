@@ -0,0 +1,183 @@
+//! Command and response APDUs.
+//!
+//! See ISO 7816-4 section 5.1.
+
+use super::{Error, Le, StatusWord};
+
+/// A command APDU, encoded with [`CommandApdu::to_bytes`].
+///
+/// Short or extended length is chosen from the data length and Le, both fields always use
+/// the same form.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CommandApdu {
+    pub cla: u8,
+    pub ins: u8,
+    pub p1: u8,
+    pub p2: u8,
+    pub data: Vec<u8>,
+    pub le: Option<Le>,
+}
+
+/// A response APDU split into data and the trailing status word.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ResponseApdu {
+    pub data: Vec<u8>,
+    pub status: StatusWord,
+}
+
+impl CommandApdu {
+    /// Maximum command data length with short length.
+    pub const MAX_SHORT_DATA: usize = 0xFF;
+
+    /// Maximum command data length with extended length.
+    pub const MAX_EXTENDED_DATA: usize = 0xFFFF;
+
+    /// A command without data and Le.
+    pub fn new(cla: u8, ins: u8, p1: u8, p2: u8) -> Self {
+        Self {
+            cla,
+            ins,
+            p1,
+            p2,
+            data: Vec::new(),
+            le: None,
+        }
+    }
+
+    /// Set the command data, fails if longer than extended length allows.
+    pub fn with_data(mut self, data: impl Into<Vec<u8>>) -> Result<Self, Error> {
+        self.data = data.into();
+        if self.data.len() > Self::MAX_EXTENDED_DATA {
+            return Err(Error::DataTooLong(self.data.len()));
+        }
+        Ok(self)
+    }
+
+    pub fn with_le(mut self, le: Le) -> Self {
+        self.le = Some(le);
+        self
+    }
+
+    pub fn is_extended_length(&self) -> bool {
+        self.data.len() > Self::MAX_SHORT_DATA || self.le.is_some_and(Le::is_extended)
+    }
+
+    /// Encode the header, Lc, data and Le.
+    ///
+    /// Lc is omitted without data. A short Le is promoted to extended form if the data
+    /// requires extended length.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let extended = self.is_extended_length();
+        let mut apdu = vec![self.cla, self.ins, self.p1, self.p2];
+        if !self.data.is_empty() {
+            if extended {
+                apdu.push(0x00);
+                apdu.extend_from_slice(&(self.data.len() as u16).to_be_bytes());
+            } else {
+                apdu.push(self.data.len() as u8);
+            }
+            apdu.extend_from_slice(&self.data);
+        }
+        if let Some(le) = self.le {
+            Le::new(le.length(), extended).append_to(&mut apdu);
+        }
+        apdu
+    }
+}
+
+impl ResponseApdu {
+    /// Split a response into data and the status word in its last two bytes.
+    pub fn parse(response: &[u8]) -> Result<Self, Error> {
+        let Some(split) = response.len().checked_sub(2) else {
+            return Err(Error::ResponseTooShort);
+        };
+        let (data, status) = response.split_at(split);
+        Ok(Self {
+            data: data.to_vec(),
+            status: u16::from_be_bytes([status[0], status[1]]).into(),
+        })
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// The file or application does not exist, status `6A82`.
+    pub fn is_not_found(&self) -> bool {
+        self.status == StatusWord::FILE_NOT_FOUND
+    }
+
+    /// Bytes left to collect with GET RESPONSE, see [`StatusWord::data_remaining`].
+    pub fn bytes_remaining(&self) -> Option<usize> {
+        self.status.data_remaining()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::iso7816::parse_apdu, hex_literal::hex};
+
+    #[test]
+    fn test_command_apdu_encoding() {
+        for (apdu, bytes) in [
+            (
+                CommandApdu::new(0x00, 0xA4, 0x00, 0x0C),
+                &hex!("00 A4 000C")[..],
+            ),
+            (
+                CommandApdu::new(0x00, 0xA4, 0x02, 0x0C)
+                    .with_data(hex!("011E"))
+                    .unwrap(),
+                &hex!("00 A4 020C 02 011E"),
+            ),
+            (
+                CommandApdu::new(0x00, 0x84, 0x00, 0x00).with_le(Le::new(8, false)),
+                &hex!("00 84 0000 08"),
+            ),
+            (
+                CommandApdu::new(0x00, 0xB0, 0x00, 0x00).with_le(Le::max(true)),
+                &hex!("00 B0 0000 00 0000"),
+            ),
+            (
+                CommandApdu::new(0x00, 0x82, 0x00, 0x00)
+                    .with_data([0xAA])
+                    .unwrap()
+                    .with_le(Le::max(false)),
+                &hex!("00 82 0000 01 AA 00"),
+            ),
+        ] {
+            assert_eq!(apdu.to_bytes(), bytes);
+            parse_apdu(bytes).unwrap();
+        }
+
+        // Long data promotes Lc and Le to extended length.
+        let apdu = CommandApdu::new(0x00, 0xD6, 0x00, 0x00)
+            .with_data([0x42; 0x100])
+            .unwrap()
+            .with_le(Le::new(0x10, false))
+            .to_bytes();
+        assert_eq!(apdu[4..7], hex!("00 0100"));
+        assert_eq!(apdu[apdu.len() - 2..], hex!("0010"));
+        let parsed = parse_apdu(&apdu).unwrap();
+        assert_eq!(parsed.data.len(), 0x100);
+        assert_eq!(parsed.expected_length(), Some(Le::Extended(0x10)));
+
+        assert!(matches!(
+            CommandApdu::new(0x00, 0xD6, 0x00, 0x00).with_data([0x42; 0x10000]),
+            Err(Error::DataTooLong(0x10000))
+        ));
+    }
+
+    #[test]
+    fn test_response_apdu_parse() {
+        let response = ResponseApdu::parse(&hex!("60 03 9000")).unwrap();
+        assert_eq!(response.data, hex!("6003"));
+        assert_eq!(response.status, StatusWord::SUCCESS);
+        assert!(response.is_success() && !response.is_not_found());
+        assert_eq!(response.bytes_remaining(), None);
+        assert!(ResponseApdu::parse(&hex!("6A82")).unwrap().is_not_found());
+        let response = ResponseApdu::parse(&hex!("6003 6110")).unwrap();
+        assert_eq!(response.bytes_remaining(), Some(0x10));
+        assert!(ResponseApdu::parse(&hex!("90")).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! Human readable descriptions of command APDUs.
+
+use super::parse_apdu;
+
+/// Describe a command APDU, e.g. `SELECT EF by FID 011C, no response expected`.
+///
+/// Only the instructions used in ICAO 9303 are decoded in detail.
+pub fn describe_apdu(apdu: &[u8]) -> String {
+    let apdu = match parse_apdu(apdu) {
+        Ok(apdu) => apdu,
+        Err(e) => return format!("{} ({})", e, hex::encode_upper(apdu)),
+    };
+    let data = hex::encode_upper(apdu.data);
+    let (p1, p2) = (apdu.p1(), apdu.p2());
+    let mut result = match apdu.ins() {
+        0xA4 => {
+            let target = match p1 {
+                0x00 if apdu.data.is_empty() => "MF".to_string(),
+                0x00 => format!("by FID {data}"),
+                0x01 => format!("child DF by FID {data}"),
+                0x02 => format!("EF by FID {data}"),
+                0x03 => "parent DF".to_string(),
+                0x04 => format!("by AID {data}"),
+                _ => format!("P1={p1:02X} {data}"),
+            };
+            format!("SELECT {target}")
+        }
+        0xB0 if p1 & 0x80 != 0 => {
+            format!("READ BINARY short EF {:02X} at offset {p2}", p1 & 0x1F)
+        }
+        0xB0 => format!(
+            "READ BINARY at offset {}",
+            u16::from_be_bytes([p1, p2]) as usize
+        ),
+        0xB1 => format!("READ BINARY (odd INS) {data}"),
+        0x84 => "GET CHALLENGE".to_string(),
+        0x82 => "EXTERNAL AUTHENTICATE".to_string(),
+        0x88 => "INTERNAL AUTHENTICATE".to_string(),
+        0x22 => format!("MANAGE SECURITY ENVIRONMENT {p1:02X}{p2:02X} {data}"),
+        0x86 | 0x87 => format!("GENERAL AUTHENTICATE {data}"),
+        0x2A => format!("PERFORM SECURITY OPERATION {p1:02X}{p2:02X}"),
+        0xC0 => "GET RESPONSE".to_string(),
+        ins => format!("INS {ins:02X} P1={p1:02X} P2={p2:02X} {data}"),
+    };
+    if apdu.cla() & 0x0C != 0 {
+        result.push_str(" (secure messaging)");
+    }
+    match apdu.le_value() {
+        Some(le) => result.push_str(&format!(", expecting up to {le} bytes")),
+        None => result.push_str(", no response expected"),
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_describe_apdu() {
+        assert_eq!(
+            describe_apdu(&hex!("00 A4 02 0C 02 01 1C")),
+            "SELECT EF by FID 011C, no response expected"
+        );
+        assert_eq!(
+            describe_apdu(&hex!("00 B0 9E 00 00")),
+            "READ BINARY short EF 1E at offset 0, expecting up to 256 bytes"
+        );
+        assert_eq!(
+            describe_apdu(&hex!("00 84 00 00 08")),
+            "GET CHALLENGE, expecting up to 8 bytes"
+        );
+        assert_eq!(
+            describe_apdu(&hex!(
+                "0C B0 00 04 0D 97 01 12 8E 08 2E A2 8A 70 F3 C7 B5 35 00"
+            )),
+            "READ BINARY at offset 4 (secure messaging), expecting up to 256 bytes"
+        );
+    }
+}
@@ -1,6 +1,12 @@
+mod apdu;
+mod describe;
 mod status_word;
 
-pub use self::status_word::StatusWord;
+pub use self::{
+    apdu::{CommandApdu, ResponseApdu},
+    describe::describe_apdu,
+    status_word::StatusWord,
+};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -19,6 +25,33 @@ pub enum Error {
 
     #[error("Invalid Extended APDU: Trailing bytes.")]
     ExtendedApduTooLong,
+
+    #[error("Invalid response APDU: Less than 2 bytes.")]
+    ResponseTooShort,
+
+    #[error("Invalid Le: {0} is not 1 to 65536 bytes.")]
+    LeOutOfRange(usize),
+
+    #[error("Invalid Le: {0} bytes, expected one or two.")]
+    LeLength(usize),
+
+    #[error("Invalid APDU: {0} bytes of data exceed extended length.")]
+    DataTooLong(usize),
+}
+
+/// Expected response length Le.
+///
+/// The maximum is encoded as zero: 256 for short and 65536 for extended length. Extended
+/// Le has two bytes, and an extra leading zero byte if there is no command data.
+///
+/// See ISO 7816-4 section 5.1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Le {
+    /// Short Le of 1 to 256 bytes, see [`Le::length`] for other values.
+    Short(usize),
+
+    /// Extended Le of 1 to 65536 bytes, see [`Le::length`] for other values.
+    Extended(usize),
 }
 
 #[derive(Debug)]
@@ -49,6 +82,105 @@ impl ApduRef<'_> {
     pub fn is_extended_length(&self) -> bool {
         self.lc.len() > 1 || self.le.len() > 1
     }
+
+    /// Expected response length, if any.
+    pub fn expected_length(&self) -> Option<Le> {
+        // Extended Le without Lc has a leading zero byte.
+        match *self.le {
+            [] => None,
+            [le] => Le::from_bytes(&[le]).ok(),
+            [hi, lo] | [0x00, hi, lo] => Le::from_bytes(&[hi, lo]).ok(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Expected response length in bytes, if any.
+    pub fn le_value(&self) -> Option<usize> {
+        self.expected_length().map(Le::length)
+    }
+}
+
+impl Le {
+    /// Maximum short Le.
+    pub const MAX_SHORT: usize = 0x100;
+
+    /// Maximum extended Le.
+    pub const MAX_EXTENDED: usize = 0x10000;
+
+    /// Le of `length` bytes.
+    ///
+    /// Zero means the maximum, as it is encoded, and larger lengths are clamped to the
+    /// maximum of the form.
+    pub fn new(length: usize, extended: bool) -> Self {
+        let max = Self::max(extended);
+        if length == 0 || length > max.length() {
+            max
+        } else if extended {
+            Self::Extended(length)
+        } else {
+            Self::Short(length)
+        }
+    }
+
+    /// The largest Le of the given form.
+    pub fn max(extended: bool) -> Self {
+        if extended {
+            Self::Extended(Self::MAX_EXTENDED)
+        } else {
+            Self::Short(Self::MAX_SHORT)
+        }
+    }
+
+    /// Number of bytes expected.
+    ///
+    /// A variant holding zero means the maximum and one holding more than the maximum is
+    /// clamped, as in [`Le::new`].
+    pub fn length(self) -> usize {
+        let (length, max) = match self {
+            Self::Short(length) => (length, Self::MAX_SHORT),
+            Self::Extended(length) => (length, Self::MAX_EXTENDED),
+        };
+        if length == 0 || length > max {
+            max
+        } else {
+            length
+        }
+    }
+
+    pub fn is_extended(self) -> bool {
+        matches!(self, Self::Extended(_))
+    }
+
+    /// Decode a one byte short or two byte extended Le, as in Secure Messaging DO'97'.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(match *bytes {
+            [0x00] => Self::Short(Self::MAX_SHORT),
+            [le] => Self::Short(le as usize),
+            [0x00, 0x00] => Self::Extended(Self::MAX_EXTENDED),
+            [hi, lo] => Self::Extended(u16::from_be_bytes([hi, lo]) as usize),
+            _ => return Err(Error::LeLength(bytes.len())),
+        })
+    }
+
+    /// Encode as one byte short or two byte extended Le, as in Secure Messaging DO'97'.
+    pub fn to_bytes(self) -> Vec<u8> {
+        // The maximum wraps to zero.
+        match self {
+            Self::Short(_) => vec![self.length() as u8],
+            Self::Extended(_) => (self.length() as u16).to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Append the Le field to an APDU of header, Lc and command data.
+    ///
+    /// The APDU must use the same form as the Le for its Lc field.
+    pub fn append_to(self, apdu: &mut Vec<u8>) {
+        // Extended Le without command data is preceded by a zero byte.
+        if self.is_extended() && apdu.len() == 4 {
+            apdu.push(0x00);
+        }
+        apdu.extend_from_slice(&self.to_bytes());
+    }
 }
 
 /// Parse APDU into header, Lc, data, and Le.
@@ -81,7 +213,7 @@ pub fn parse_apdu(apdu: &[u8]) -> Result<ApduRef, Error> {
         },
         // Extended length with data and maybe Le
         (_, Some(&0x00)) => {
-            let lc = u16::from_be_bytes([apdu[4], apdu[5]]) as usize;
+            let lc = u16::from_be_bytes([apdu[5], apdu[6]]) as usize;
             if lc == 0 {
                 return Err(Error::ExtendedLcZero);
             }
@@ -122,3 +254,61 @@ pub fn parse_apdu(apdu: &[u8]) -> Result<ApduRef, Error> {
         _ => return Err(Error::ApduTooLong),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_le_encoding() {
+        for (le, apdu) in [
+            (Le::new(8, false), &hex!("00 84 0000 08")[..]),
+            (Le::max(false), &hex!("00 B0 0000 00")),
+            (Le::new(0x100, true), &hex!("00 B0 0000 00 0100")),
+            (Le::max(true), &hex!("00 B0 0000 00 0000")),
+        ] {
+            let mut encoded = apdu[..4].to_vec();
+            le.append_to(&mut encoded);
+            assert_eq!(encoded, apdu);
+            assert_eq!(parse_apdu(apdu).unwrap().expected_length(), Some(le));
+        }
+
+        // With command data, extended Le has no leading zero.
+        let mut apdu = hex!("00 82 0000 000001 AA").to_vec();
+        Le::max(true).append_to(&mut apdu);
+        assert_eq!(apdu, hex!("00 82 0000 000001 AA 0000"));
+        assert_eq!(
+            parse_apdu(&apdu).unwrap().expected_length(),
+            Some(Le::Extended(0x10000))
+        );
+
+        assert_eq!(Le::max(false).to_bytes(), [0x00]);
+        assert_eq!(Le::from_bytes(&[0x01, 0x00]).unwrap(), Le::Extended(0x100));
+        for bytes in [&[][..], &[0x00; 3]] {
+            assert!(matches!(Le::from_bytes(bytes), Err(Error::LeLength(l)) if l == bytes.len()));
+        }
+    }
+
+    #[test]
+    fn test_le_new_clamps() {
+        assert_eq!(Le::new(0, false), Le::max(false));
+        assert_eq!(Le::new(0x101, false), Le::max(false));
+        assert_eq!(Le::new(0, true), Le::max(true));
+        assert_eq!(Le::new(0x10001, true), Le::max(true));
+        assert_eq!(Le::new(0x101, true), Le::Extended(0x101));
+    }
+
+    #[test]
+    fn test_le_out_of_range_variants() {
+        assert_eq!(Le::Short(0).length(), 0x100);
+        assert_eq!(Le::Short(0).to_bytes(), [0x00]);
+        assert_eq!(Le::Short(0x1FF).to_bytes(), [0x00]);
+        assert_eq!(Le::Extended(0).to_bytes(), [0x00, 0x00]);
+        assert_eq!(
+            CommandApdu::new(0x00, 0xB0, 0x00, 0x00)
+                .with_le(Le::Short(0))
+                .to_bytes(),
+            hex!("00 B0 0000 00")
+        );
+    }
+}
@@ -7,8 +7,18 @@ pub struct StatusWord(u16);
 
 impl StatusWord {
     pub const SUCCESS: StatusWord = StatusWord(0x9000);
+    pub const END_OF_FILE: StatusWord = StatusWord(0x6282);
     pub const FILE_NOT_FOUND: StatusWord = StatusWord(0x6A82);
+    pub const RECORD_NOT_FOUND: StatusWord = StatusWord(0x6A83);
+    pub const INCORRECT_P1P2: StatusWord = StatusWord(0x6A86);
     pub const ACCESS_DENIED: StatusWord = StatusWord(0x6982);
+    pub const NO_CURRENT_EF: StatusWord = StatusWord(0x6986);
+    /// Proprietary status of some Java Card chips.
+    pub const APPLET_SELECT_FAILED: StatusWord = StatusWord(0x6999);
+    pub const WRONG_LENGTH: StatusWord = StatusWord(0x6700);
+    pub const WRONG_P1P2: StatusWord = StatusWord(0x6B00);
+    pub const INS_NOT_SUPPORTED: StatusWord = StatusWord(0x6D00);
+    pub const CLA_NOT_SUPPORTED: StatusWord = StatusWord(0x6E00);
 
     pub const SECURE_MESSAGING_INCOMPLETE: StatusWord = StatusWord(0x6987);
     pub const SECURE_MESSAGING_INCORRECT: StatusWord = StatusWord(0x6988);
@@ -32,6 +42,14 @@ impl StatusWord {
         }
     }
 
+    /// Retries left as reported by a counter status `63CX`, e.g. after failed authentication.
+    pub fn retries_remaining(self) -> Option<u8> {
+        match self.0 {
+            0x63C0..=0x63CF => Some(self.sw2() & 0x0F),
+            _ => None,
+        }
+    }
+
     pub fn is_valid(self) -> bool {
         matches!(self.0, 0x6100..=0x6FFF | 0x9000..=0x9FFF)
     }
@@ -132,6 +150,7 @@ impl StatusWord {
             0x6986 => "Command not allowed (no current EF)",
             0x6987 => "Expected secure messaging data objects missing",
             0x6988 => "Incorrect secure messaging data objects",
+            0x6999 => "Applet selection failed", // Java Card, not in ISO 7816-4
             0x6900..=0x69FF => "Unknown command not allowed error", // Catch all
 
             0x6A00 => "Unspecified wrong parameters error",
@@ -0,0 +1,107 @@
+//! Card capabilities from EF.ATR/INFO.
+//!
+//! See ICAO 9303-10 section 3.11.1 and ISO 7816-4 section 12.2.2.
+
+use super::{
+    tlv::{parse_tlv, TlvRef},
+    Error, Result,
+};
+
+/// Card capabilities data object.
+///
+/// This is the compact-TLV `7x` descriptor from the historical bytes, stored as '47'.
+pub const CARD_CAPABILITIES: u32 = 0x47;
+
+/// Extended length information data object.
+pub const EXTENDED_LENGTH_INFO: u32 = 0x7F66;
+
+/// Capabilities advertised in EF.ATR/INFO.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CardCapabilities {
+    /// Command chaining is supported.
+    pub command_chaining: bool,
+
+    /// Extended Lc and Le fields are supported.
+    pub extended_length: bool,
+
+    /// Maximum number of bytes in a command APDU.
+    pub max_command_length: Option<usize>,
+
+    /// Maximum number of bytes in a response APDU.
+    pub max_response_length: Option<usize>,
+}
+
+/// Parse the content of EF.ATR/INFO.
+///
+/// Unknown data objects are ignored.
+pub fn parse_atr_info(bytes: &[u8]) -> Result<CardCapabilities> {
+    let mut capabilities = CardCapabilities::default();
+    let mut rest = bytes;
+    while let Some(&first) = rest.first() {
+        // The file may be padded after the last data object.
+        if first == 0x00 || first == 0xFF {
+            break;
+        }
+        let (object, tail) = parse_tlv(rest)?;
+        rest = tail;
+        match object.tag {
+            CARD_CAPABILITIES => {
+                // Third software function table, see ISO 7816-4 table 118.
+                if let Some(&byte) = object.value.get(2) {
+                    capabilities.command_chaining = byte & 0x80 != 0;
+                    capabilities.extended_length = byte & 0x40 != 0;
+                }
+            }
+            EXTENDED_LENGTH_INFO => {
+                let mut integers = Vec::new();
+                for integer in object.children() {
+                    let integer = integer?;
+                    if integer.tag == 0x02 {
+                        integers.push(parse_integer(integer)?);
+                    }
+                }
+                capabilities.max_command_length = integers.first().copied();
+                capabilities.max_response_length = integers.get(1).copied();
+            }
+            _ => {}
+        }
+    }
+    Ok(capabilities)
+}
+
+fn parse_integer(object: TlvRef) -> Result<usize> {
+    match object.value.len() {
+        1..=4 => Ok(object
+            .value
+            .iter()
+            .fold(0_usize, |acc, &byte| (acc << 8) | byte as usize)),
+        _ => Err(Error::InvalidInteger),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_atr_info() {
+        let bytes = hex!("47 03 00 00 C0 7F66 08 02 02 0800 02 02 0A00 E0 02 0000 FFFF");
+        let capabilities = parse_atr_info(&bytes).unwrap();
+        assert_eq!(
+            capabilities,
+            CardCapabilities {
+                command_chaining: true,
+                extended_length: true,
+                max_command_length: Some(0x800),
+                max_response_length: Some(0xA00),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_atr_info_empty() {
+        let capabilities = parse_atr_info(&[]).unwrap();
+        assert_eq!(capabilities, CardCapabilities::default());
+    }
+}
@@ -0,0 +1,140 @@
+//! Common Biometric Exchange Formats Framework (CBEFF) wrapping of DG2 to DG4.
+//!
+//! See ICAO 9303-10 section 4.7.2.
+
+use {
+    super::{
+        tlv::{parse_tlv, TlvRef},
+        Error, Result,
+    },
+    crate::ensure_err,
+};
+
+pub const BIOMETRIC_INFORMATION_GROUP_TEMPLATE: u32 = 0x7F61;
+pub const BIOMETRIC_INFORMATION_COUNT: u32 = 0x02;
+pub const BIOMETRIC_INFORMATION_TEMPLATE: u32 = 0x7F60;
+pub const BIOMETRIC_HEADER_TEMPLATE: u32 = 0xA1;
+pub const BIOMETRIC_DATA_BLOCK: u32 = 0x5F2E;
+
+/// Provenance metadata from the Biometric Header Template.
+///
+/// All fields are optional, even the format owner and type that ICAO 9303-10 requires,
+/// as not all chips include them.
+///
+/// See ICAO 9303-10 section 4.7.2.1 table 38.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BiometricHeader {
+    /// `80`: ICAO header version, major and minor.
+    pub version: Option<u16>,
+
+    /// `81`: Biometric type, see ISO/IEC 7816-11 table C.2.
+    pub biometric_type: Option<u32>,
+
+    /// `82`: Biometric subtype, e.g. which finger.
+    pub biometric_subtype: Option<u8>,
+
+    /// `83`: Creation date and time as BCD `YYYYMMDDhhmmss`.
+    pub creation_date: Option<[u8; 7]>,
+
+    /// `85`: Validity period from and to as BCD `YYYYMMDD`.
+    pub validity_period: Option<([u8; 4], [u8; 4])>,
+
+    /// `86`: Creator of the biometric reference data.
+    pub creator: Option<u16>,
+
+    /// `87`: Format owner, a CBEFF biometric organization identifier.
+    pub format_owner: Option<u16>,
+
+    /// `88`: Format type, assigned by the format owner.
+    pub format_type: Option<u16>,
+}
+
+impl BiometricHeader {
+    /// Parse the contents of a Biometric Header Template.
+    pub fn from_tlv(template: TlvRef) -> Result<Self> {
+        ensure_err!(
+            template.tag == BIOMETRIC_HEADER_TEMPLATE,
+            Error::UnexpectedTag(template.tag)
+        );
+        let mut header = Self::default();
+        for object in template.children() {
+            let object = object?;
+            let value = object.value;
+            match object.tag {
+                0x80 => header.version = Some(parse_integer(value, 2)? as u16),
+                0x81 => header.biometric_type = Some(parse_integer(value, 3)?),
+                0x82 => header.biometric_subtype = Some(parse_integer(value, 1)? as u8),
+                0x83 => header.creation_date = Some(fixed(value)?),
+                0x85 => {
+                    let period: [u8; 8] = fixed(value)?;
+                    header.validity_period = Some((fixed(&period[..4])?, fixed(&period[4..])?));
+                }
+                0x86 => header.creator = Some(parse_integer(value, 2)? as u16),
+                0x87 => header.format_owner = Some(parse_integer(value, 2)? as u16),
+                0x88 => header.format_type = Some(parse_integer(value, 2)? as u16),
+                _ => {}
+            }
+        }
+        Ok(header)
+    }
+}
+
+/// Returns the biometric data blocks of a DG2, DG3 or DG4 data group.
+///
+/// `tag` is the tag of the data group, e.g. `0x63` for DG3.
+pub fn biometric_data_blocks(data_group: &[u8], tag: u32) -> Result<Vec<&[u8]>> {
+    Ok(biometric_records(data_group, tag)?
+        .into_iter()
+        .map(|(_, block)| block)
+        .collect())
+}
+
+/// Returns the biometric headers and data blocks of a DG2, DG3 or DG4 data group.
+///
+/// `tag` is the tag of the data group, e.g. `0x63` for DG3.
+pub fn biometric_records(data_group: &[u8], tag: u32) -> Result<Vec<(BiometricHeader, &[u8])>> {
+    let (dg, _) = parse_tlv(data_group)?;
+    ensure_err!(dg.tag == tag, Error::UnexpectedTag(dg.tag));
+    let group = dg.get(BIOMETRIC_INFORMATION_GROUP_TEMPLATE)?;
+
+    // Number of instances of this biometric type.
+    let count = group.get(BIOMETRIC_INFORMATION_COUNT)?.value;
+    ensure_err!(
+        count.len() == 1,
+        Error::InvalidBiometricData("invalid instance count")
+    );
+    let count = count[0] as usize;
+
+    let mut records = Vec::with_capacity(count);
+    for template in group.children() {
+        let template: TlvRef = template?;
+        if template.tag != BIOMETRIC_INFORMATION_TEMPLATE {
+            continue;
+        }
+        let header = match template.find(BIOMETRIC_HEADER_TEMPLATE)? {
+            Some(header) => BiometricHeader::from_tlv(header)?,
+            None => BiometricHeader::default(),
+        };
+        records.push((header, template.get(BIOMETRIC_DATA_BLOCK)?.value));
+    }
+    ensure_err!(
+        records.len() == count,
+        Error::InvalidBiometricData("instance count mismatch")
+    );
+    Ok(records)
+}
+
+/// Parse a big-endian unsigned integer of at most `max_len` bytes.
+fn parse_integer(value: &[u8], max_len: usize) -> Result<u32> {
+    ensure_err!((1..=max_len).contains(&value.len()), Error::InvalidInteger);
+    Ok(value
+        .iter()
+        .fold(0_u32, |acc, &byte| (acc << 8) | byte as u32))
+}
+
+fn fixed<const N: usize>(value: &[u8]) -> Result<[u8; N]> {
+    value
+        .try_into()
+        .map_err(|_| Error::InvalidBiometricData("invalid header field length"))
+}
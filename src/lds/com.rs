@@ -0,0 +1,81 @@
+//! Common data from EF.COM.
+//!
+//! See ICAO 9303-10 section 4.6.1.
+
+use super::{tlv::parse_tlv, Error, Result};
+
+/// EF.COM data object.
+pub const COM: u32 = 0x60;
+
+/// LDS version data object, e.g. `0107` for version 1.7.
+pub const LDS_VERSION: u32 = 0x5F01;
+
+/// Unicode version data object, e.g. `040000` for version 4.0.0.
+pub const UNICODE_VERSION: u32 = 0x5F36;
+
+/// Tag list data object, the tags of the data groups present.
+pub const TAG_LIST: u32 = 0x5C;
+
+/// Data group tags in order of data group number, starting with DG1.
+const DATA_GROUP_TAGS: [u8; 16] = [
+    0x61, 0x75, 0x63, 0x76, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6A, 0x6B, 0x6C, 0x6D, 0x6E, 0x6F, 0x70,
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Com {
+    pub lds_version: String,
+    pub unicode_version: String,
+
+    /// Numbers of the data groups present, in the order listed.
+    pub data_groups: Vec<u8>,
+}
+
+/// Parse the content of EF.COM.
+///
+/// Tags in the tag list that are not data group tags are ignored.
+pub fn parse_com(bytes: &[u8]) -> Result<Com> {
+    let (com, _) = parse_tlv(bytes)?;
+    if com.tag != COM {
+        return Err(Error::UnexpectedTag(com.tag));
+    }
+    let version =
+        |tag| -> Result<String> { Ok(String::from_utf8_lossy(com.get(tag)?.value).into_owned()) };
+    let data_groups = com
+        .get(TAG_LIST)?
+        .value
+        .iter()
+        .filter_map(|tag| data_group_number(*tag))
+        .collect();
+    Ok(Com {
+        lds_version: version(LDS_VERSION)?,
+        unicode_version: version(UNICODE_VERSION)?,
+        data_groups,
+    })
+}
+
+/// Data group number for a tag in the EF.COM tag list.
+pub fn data_group_number(tag: u8) -> Option<u8> {
+    DATA_GROUP_TAGS
+        .iter()
+        .position(|&t| t == tag)
+        .map(|index| index as u8 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_com() {
+        // EF.COM from ICAO 9303-11 Appendix D.4.
+        let com = parse_com(&hex!("60145F0104303130365F36063034303030305C026175")).unwrap();
+        assert_eq!(com.lds_version, "0106");
+        assert_eq!(com.unicode_version, "040000");
+        assert_eq!(com.data_groups, vec![1, 2]);
+
+        let com = parse_com(&hex!("60105F0104303130375F3600 5C046E6F7001")).unwrap();
+        assert_eq!(com.data_groups, vec![14, 15, 16]);
+        assert!(parse_com(&hex!("6103 5C0161")).is_err());
+    }
+}
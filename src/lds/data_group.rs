@@ -0,0 +1,143 @@
+//! Parsing of any data group by number.
+
+use {
+    super::{
+        face::parse_dg2, fingerprint::parse_dg3, personal_details::parse_dg11, tlv::parse_tlv,
+        Error, FaceImage, FingerprintRecord, PersonalDetails, Result,
+    },
+    crate::asn1::{security_info::SecurityInfos, EfDg14},
+    der::Decode,
+};
+
+/// Tag of EF.DG1.
+pub const DG1_TAG: u32 = 0x61;
+
+/// Tag of the MRZ data object in EF.DG1.
+pub const MRZ_TAG: u32 = 0x5F1F;
+
+/// A parsed data group.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataGroup {
+    /// DG1: The Machine Readable Zone.
+    Mrz(String),
+
+    /// DG2: Facial images.
+    Face(Vec<FaceImage>),
+
+    /// DG3: Finger images.
+    Fingerprint(Vec<FingerprintRecord>),
+
+    /// DG11: Additional personal details.
+    PersonalDetails(PersonalDetails),
+
+    /// DG14: Security options for secondary biometrics and Chip Authentication.
+    SecurityInfos(
+        #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_der"))] SecurityInfos,
+    ),
+
+    /// A data group without parser, or that failed to parse in lenient mode.
+    Raw {
+        number: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_base64"))]
+        bytes: Vec<u8>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ParseOptions {
+    /// Return parse errors instead of falling back to [`DataGroup::Raw`].
+    pub strict: bool,
+}
+
+impl DataGroup {
+    /// The data group number.
+    pub fn number(&self) -> u8 {
+        match self {
+            Self::Mrz(_) => 1,
+            Self::Face(_) => 2,
+            Self::Fingerprint(_) => 3,
+            Self::PersonalDetails(_) => 11,
+            Self::SecurityInfos(_) => 14,
+            Self::Raw { number, .. } => *number,
+        }
+    }
+}
+
+/// Parse the contents of data group `dg`.
+///
+/// This is the single place that maps data group numbers to parsers.
+pub fn parse_data_group(dg: u8, bytes: &[u8], opts: ParseOptions) -> Result<DataGroup> {
+    let result = match dg {
+        1 => parse_dg1(bytes).map(DataGroup::Mrz),
+        2 => parse_dg2(bytes).map(DataGroup::Face),
+        3 => parse_dg3(bytes).map(DataGroup::Fingerprint),
+        11 => parse_dg11(bytes).map(DataGroup::PersonalDetails),
+        14 => EfDg14::from_der(bytes)
+            .map(|dg14| DataGroup::SecurityInfos(dg14.0))
+            .map_err(Error::from),
+        _ => Ok(raw(dg, bytes)),
+    };
+    match result {
+        Err(_) if !opts.strict => Ok(raw(dg, bytes)),
+        result => result,
+    }
+}
+
+/// Parse the MRZ from EF.DG1.
+///
+/// See ICAO 9303-10 section 4.7.1.
+pub fn parse_dg1(bytes: &[u8]) -> Result<String> {
+    let (dg1, _) = parse_tlv(bytes)?;
+    if dg1.tag != DG1_TAG {
+        return Err(Error::UnexpectedTag(dg1.tag));
+    }
+    let mrz = dg1.get(MRZ_TAG)?;
+    if !mrz.value.is_ascii() {
+        return Err(Error::InvalidMrz);
+    }
+    Ok(String::from_utf8_lossy(mrz.value).into_owned())
+}
+
+fn raw(number: u8, bytes: &[u8]) -> DataGroup {
+    DataGroup::Raw {
+        number,
+        bytes: bytes.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_dg1() {
+        let mrz = b"P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<L898902C36UTO7408122F1204159ZE184226B<<<<<10";
+        let mut dg1 = hex!("61 5B 5F1F 58").to_vec();
+        dg1.extend_from_slice(mrz);
+        let parsed = parse_data_group(1, &dg1, ParseOptions::default()).unwrap();
+        assert_eq!(
+            parsed,
+            DataGroup::Mrz(String::from_utf8(mrz.to_vec()).unwrap())
+        );
+        assert_eq!(parsed.number(), 1);
+    }
+
+    #[test]
+    fn test_lenient_fallback() {
+        let invalid = hex!("61 03 5F1F");
+        let strict = ParseOptions { strict: true };
+        assert!(parse_data_group(1, &invalid, strict).is_err());
+        assert_eq!(
+            parse_data_group(1, &invalid, ParseOptions::default()).unwrap(),
+            DataGroup::Raw {
+                number: 1,
+                bytes: invalid.to_vec()
+            }
+        );
+        assert!(matches!(
+            parse_data_group(7, &invalid, strict).unwrap(),
+            DataGroup::Raw { number: 7, .. }
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+//! Application directory from EF.DIR.
+//!
+//! See ICAO 9303-10 section 3.11.3 and ISO 7816-4 section 12.2.3.
+
+use super::{tlv::parse_tlv, Result};
+
+/// Application template data object.
+pub const APPLICATION_TEMPLATE: u32 = 0x61;
+
+/// Application identifier data object.
+pub const APPLICATION_IDENTIFIER: u32 = 0x4F;
+
+/// Application label data object.
+pub const APPLICATION_LABEL: u32 = 0x50;
+
+/// An application listed in EF.DIR.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApplicationTemplate {
+    /// Application identifier, e.g. `A0000002471001` for the eMRTD LDS1 application.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_base64"))]
+    pub aid: Vec<u8>,
+
+    /// Optional human readable label.
+    pub label: Option<String>,
+}
+
+/// Parse the content of EF.DIR.
+///
+/// Data objects other than application templates are ignored.
+pub fn parse_dir(bytes: &[u8]) -> Result<Vec<ApplicationTemplate>> {
+    let mut applications = Vec::new();
+    let mut rest = bytes;
+    while let Some(&first) = rest.first() {
+        // The file may be padded after the last data object.
+        if first == 0x00 || first == 0xFF {
+            break;
+        }
+        let (object, tail) = parse_tlv(rest)?;
+        rest = tail;
+        if object.tag != APPLICATION_TEMPLATE {
+            continue;
+        }
+        let aid = object.get(APPLICATION_IDENTIFIER)?.value.to_vec();
+        let label = object
+            .find(APPLICATION_LABEL)?
+            .map(|label| String::from_utf8_lossy(label.value).into_owned());
+        applications.push(ApplicationTemplate { aid, label });
+    }
+    Ok(applications)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_dir() {
+        let dir = hex!(
+            "61 09 4F 07 A0000002471001
+             61 0F 4F 07 A0000002472001 50 04 4C445332
+             0000"
+        );
+        let applications = parse_dir(&dir).unwrap();
+        assert_eq!(
+            applications,
+            vec![
+                ApplicationTemplate {
+                    aid: hex!("A0000002471001").to_vec(),
+                    label: None,
+                },
+                ApplicationTemplate {
+                    aid: hex!("A0000002472001").to_vec(),
+                    label: Some("LDS2".to_string()),
+                },
+            ]
+        );
+    }
+}
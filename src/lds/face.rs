@@ -0,0 +1,214 @@
+//! Facial images from DG2.
+//!
+//! See ISO/IEC 19794-5:2005 section 5.
+
+use {
+    super::{
+        biometric::{biometric_records, BiometricHeader},
+        Error, Result,
+    },
+    crate::ensure_err,
+};
+
+/// Tag of EF.DG2.
+pub const DG2_TAG: u32 = 0x75;
+
+const GENERAL_HEADER_LENGTH: usize = 14;
+const FACIAL_INFORMATION_LENGTH: usize = 20;
+const FEATURE_POINT_LENGTH: usize = 8;
+const IMAGE_INFORMATION_LENGTH: usize = 12;
+
+/// Start of a JPEG file, the SOI marker.
+const JPEG_MAGIC: &[u8] = &[0xFF, 0xD8];
+
+/// Start of a JPEG 2000 file, the JP2 signature box.
+const JP2_MAGIC: &[u8] = &[0x00, 0x00, 0x00, 0x0C, 0x6A, 0x50, 0x20];
+
+/// Start of a raw JPEG 2000 codestream, the SOC and SIZ markers.
+const J2K_MAGIC: &[u8] = &[0xFF, 0x4F, 0xFF, 0x51];
+
+/// A single facial image from DG2.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FaceImage {
+    /// Face image type, see ISO/IEC 19794-5 table 8.
+    pub face_image_type: u8,
+
+    pub image_format: FaceImageFormat,
+
+    pub width: u16,
+
+    pub height: u16,
+
+    /// Encoded image data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_base64"))]
+    pub image: Vec<u8>,
+
+    /// Metadata from the biometric header of the record containing this image.
+    pub header: BiometricHeader,
+}
+
+/// Image encoding, see ISO/IEC 19794-5 table 9.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FaceImageFormat {
+    Jpeg,
+    Jpeg2000,
+    Unknown(u8),
+}
+
+impl From<u8> for FaceImageFormat {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Jpeg,
+            1 => Self::Jpeg2000,
+            n => Self::Unknown(n),
+        }
+    }
+}
+
+impl FaceImageFormat {
+    /// Detect the format from the image data, falling back to the declared image data type.
+    ///
+    /// Some documents declare JPEG 2000 for baseline JPEG images or vice versa, the magic
+    /// bytes are authoritative.
+    pub fn detect(image: &[u8], image_data_type: u8) -> Self {
+        if image.starts_with(JPEG_MAGIC) {
+            Self::Jpeg
+        } else if image.starts_with(JP2_MAGIC) || image.starts_with(J2K_MAGIC) {
+            Self::Jpeg2000
+        } else {
+            Self::from(image_data_type)
+        }
+    }
+}
+
+/// Parse all facial images in an EF.DG2 file.
+pub fn parse_dg2(data_group: &[u8]) -> Result<Vec<FaceImage>> {
+    let mut faces = Vec::new();
+    for (header, block) in biometric_records(data_group, DG2_TAG)? {
+        faces.extend(
+            parse_facial_record(block)?
+                .into_iter()
+                .map(|face| FaceImage {
+                    header: header.clone(),
+                    ..face
+                }),
+        );
+    }
+    Ok(faces)
+}
+
+/// Parse an ISO/IEC 19794-5 facial record.
+///
+/// A record holds a general header followed by one or more facial images.
+pub fn parse_facial_record(record: &[u8]) -> Result<Vec<FaceImage>> {
+    ensure_err!(
+        record.len() >= GENERAL_HEADER_LENGTH,
+        Error::InvalidBiometricData("truncated general header")
+    );
+    ensure_err!(
+        &record[0..4] == b"FAC\0",
+        Error::InvalidBiometricData("not a facial record")
+    );
+    let count = u16::from_be_bytes([record[12], record[13]]) as usize;
+
+    let mut rest = &record[GENERAL_HEADER_LENGTH..];
+    let mut faces = Vec::with_capacity(count);
+    for _ in 0..count {
+        ensure_err!(
+            rest.len() >= FACIAL_INFORMATION_LENGTH,
+            Error::InvalidBiometricData("truncated facial information")
+        );
+        let length = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        let feature_points = u16::from_be_bytes([rest[4], rest[5]]) as usize;
+        let image_offset = FACIAL_INFORMATION_LENGTH
+            + feature_points * FEATURE_POINT_LENGTH
+            + IMAGE_INFORMATION_LENGTH;
+        ensure_err!(
+            (image_offset..=rest.len()).contains(&length),
+            Error::InvalidBiometricData("invalid facial record length")
+        );
+        let (face, tail) = rest.split_at(length);
+        let info = &face[image_offset - IMAGE_INFORMATION_LENGTH..image_offset];
+        let image = &face[image_offset..];
+        faces.push(FaceImage {
+            face_image_type: info[0],
+            image_format: FaceImageFormat::detect(image, info[1]),
+            width: u16::from_be_bytes([info[2], info[3]]),
+            height: u16::from_be_bytes([info[4], info[5]]),
+            image: image.to_vec(),
+            header: BiometricHeader::default(),
+        });
+        rest = tail;
+    }
+    Ok(faces)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    /// DG2 with a single facial image of the given image data type.
+    fn dg2(image_data_type: u8, image: &[u8]) -> Vec<u8> {
+        // Facial information without feature points, frontal full image information.
+        let mut face = hex!("00000000 0000 01 01 01 000000 0000 000000 000000").to_vec();
+        face.extend_from_slice(&[0x01, image_data_type]);
+        face.extend_from_slice(&hex!("01E0 0280 01 02 0000 0000"));
+        face.extend_from_slice(image);
+        let face_length = (face.len() as u32).to_be_bytes();
+        face[0..4].copy_from_slice(&face_length);
+
+        let mut record = hex!("46414300 30313000").to_vec();
+        record.extend_from_slice(&((GENERAL_HEADER_LENGTH + face.len()) as u32).to_be_bytes());
+        record.extend_from_slice(&hex!("0001"));
+        record.extend_from_slice(&face);
+
+        let mut bdb = hex!("5F2E").to_vec();
+        bdb.push(record.len() as u8);
+        bdb.extend_from_slice(&record);
+        let mut bit = hex!("7F60").to_vec();
+        let bht = hex!("A1 0E 800101 810102 87020101 88020008");
+        bit.push((bdb.len() + bht.len()) as u8);
+        bit.extend_from_slice(&bht);
+        bit.extend_from_slice(&bdb);
+        let mut group = hex!("7F61").to_vec();
+        group.push((bit.len() + 3) as u8);
+        group.extend_from_slice(&hex!("02 01 01"));
+        group.extend_from_slice(&bit);
+        let mut dg2 = vec![0x75, group.len() as u8];
+        dg2.extend_from_slice(&group);
+        dg2
+    }
+
+    #[test]
+    fn test_parse_dg2_jpeg() {
+        let jpeg = hex!("FFD8FFE0 00104A46 49460001");
+        let faces = parse_dg2(&dg2(0x00, &jpeg)).unwrap();
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].image_format, FaceImageFormat::Jpeg);
+        assert_eq!(faces[0].face_image_type, 0x01);
+        assert_eq!(faces[0].width, 480);
+        assert_eq!(faces[0].height, 640);
+        assert_eq!(faces[0].image, jpeg);
+        assert_eq!(faces[0].header.biometric_type, Some(0x02));
+        assert_eq!(faces[0].header.format_type, Some(0x0008));
+    }
+
+    #[test]
+    fn test_parse_dg2_jpeg2000() {
+        let jp2 = hex!("0000000C 6A502020 0D0A870A");
+        let faces = parse_dg2(&dg2(0x01, &jp2)).unwrap();
+        assert_eq!(faces[0].image_format, FaceImageFormat::Jpeg2000);
+        assert_eq!(faces[0].image, jp2);
+
+        // The magic bytes take precedence over a wrong image data type.
+        let faces = parse_dg2(&dg2(0x00, &jp2)).unwrap();
+        assert_eq!(faces[0].image_format, FaceImageFormat::Jpeg2000);
+    }
+
+    #[test]
+    fn test_parse_truncated_record() {
+        assert!(parse_facial_record(b"FAC\0").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! File control information returned when selecting a file.
+//!
+//! See ISO 7816-4 section 7.4 and, for control reference templates, section 10.3.
+
+use {
+    super::{
+        tlv::{parse_tlv, TlvRef},
+        Error, Result,
+    },
+    crate::{
+        asn1::security_info::{ChipAuthenticationProtocol, PaceProtocol, SymmetricCipher},
+        ensure_err,
+    },
+    der::asn1::ObjectIdentifier as Oid,
+};
+
+/// File control information template.
+pub const FCI_TEMPLATE: u32 = 0x6F;
+
+/// File control parameters template.
+pub const FCP_TEMPLATE: u32 = 0x62;
+
+/// Number of data bytes in the file, excluding structural information.
+pub const FILE_SIZE: u32 = 0x80;
+
+/// Number of bytes allocated to the file, including structural information.
+pub const TOTAL_FILE_SIZE: u32 = 0x81;
+
+/// File descriptor byte, optionally followed by data coding and record information.
+pub const FILE_DESCRIPTOR: u32 = 0x82;
+
+/// File identifier.
+pub const FILE_IDENTIFIER: u32 = 0x83;
+
+/// Short EF identifier, in bits 8 to 4.
+pub const SHORT_EF_IDENTIFIER: u32 = 0x88;
+
+/// Security attributes in proprietary format.
+pub const SECURITY_ATTRIBUTE_PROPRIETARY: u32 = 0x86;
+
+/// Security attributes referencing an expanded format in EF.ARR.
+pub const SECURITY_ATTRIBUTE_REFERENCE: u32 = 0x8C;
+
+/// Security attributes in compact format.
+pub const SECURITY_ATTRIBUTE_COMPACT: u32 = 0x8D;
+
+/// Proprietary information data object.
+pub const PROPRIETARY_INFORMATION: u32 = 0xA5;
+
+/// Control reference template for cryptographic checksum.
+pub const CRT_CRYPTOGRAPHIC_CHECKSUM: u32 = 0xB4;
+
+/// Control reference template for confidentiality.
+pub const CRT_CONFIDENTIALITY: u32 = 0xB8;
+
+/// Cryptographic mechanism reference data object.
+pub const CRYPTOGRAPHIC_MECHANISM_REFERENCE: u32 = 0x80;
+
+/// Information from the FCI or FCP returned by SELECT.
+///
+/// Fields are `None` (or empty) if the chip did not include the data object.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct FileControlInfo {
+    /// Number of data bytes in the file.
+    pub file_size: Option<usize>,
+
+    /// Number of bytes allocated to the file.
+    pub total_file_size: Option<usize>,
+
+    /// File descriptor byte, see ISO 7816-4 table 12.
+    pub file_descriptor: Option<u8>,
+
+    pub file_id: Option<u16>,
+
+    pub short_file_id: Option<u8>,
+
+    /// Security attributes as `(tag, value)`, in the format given by the tag.
+    pub security_attributes: Vec<(u32, Vec<u8>)>,
+
+    /// Cipher the chip expects for Secure Messaging, if it indicates one.
+    pub sm_cipher: Option<SymmetricCipher>,
+
+    /// `(file identifier, short EF identifier)` of files described by nested FCP
+    /// templates, e.g. the elementary files of an application.
+    pub short_ef_ids: Vec<(u16, u8)>,
+}
+
+/// Parse the response data of SELECT, an FCI or FCP template.
+///
+/// The Secure Messaging cipher is taken from the cryptographic mechanism reference in a
+/// control reference template, either directly in the template or in its proprietary
+/// information. The reference is the content of a PACE or Chip Authentication protocol
+/// OID, as in MSE:Set AT. Unknown data objects and mechanisms are ignored.
+pub fn parse_fci(bytes: &[u8]) -> Result<FileControlInfo> {
+    let mut fci = FileControlInfo::default();
+    if bytes.is_empty() {
+        return Ok(fci);
+    }
+    let (template, _) = parse_tlv(bytes)?;
+    if !matches!(template.tag, FCI_TEMPLATE | FCP_TEMPLATE) {
+        return Err(Error::UnexpectedTag(template.tag));
+    }
+    parse_template(&mut fci, template)?;
+    Ok(fci)
+}
+
+/// Parse the response data of SELECT, keeping whatever was recognised.
+///
+/// Unlike [`parse_fci`] this does not fail: an unknown template gives an empty
+/// [`FileControlInfo`], and the fields before a malformed data object are kept.
+pub fn parse_fci_lenient(bytes: &[u8]) -> FileControlInfo {
+    let mut fci = FileControlInfo::default();
+    if let Ok((template, _)) = parse_tlv(bytes) {
+        if matches!(template.tag, FCI_TEMPLATE | FCP_TEMPLATE) {
+            // Fields parsed before an error remain set.
+            let _ = parse_template(&mut fci, template);
+        }
+    }
+    fci
+}
+
+fn parse_template(fci: &mut FileControlInfo, template: TlvRef) -> Result<()> {
+    for object in template.children() {
+        let object = object?;
+        match object.tag {
+            // An FCI may wrap the FCP, see ISO 7816-4 section 7.4.1.
+            FCP_TEMPLATE => {
+                parse_template(fci, object)?;
+                let file_id = object.find(FILE_IDENTIFIER)?.map(|object| object.value);
+                let short_id = object.find(SHORT_EF_IDENTIFIER)?.map(|object| object.value);
+                if let (Some(&[high, low]), Some(&[short_id, ..])) = (file_id, short_id) {
+                    fci.short_ef_ids
+                        .push((u16::from_be_bytes([high, low]), short_id >> 3));
+                }
+            }
+            FILE_SIZE => fci.file_size = Some(parse_size(object.value)?),
+            TOTAL_FILE_SIZE => fci.total_file_size = Some(parse_size(object.value)?),
+            FILE_DESCRIPTOR => fci.file_descriptor = object.value.first().copied(),
+            FILE_IDENTIFIER => match object.value {
+                &[high, low] => fci.file_id = Some(u16::from_be_bytes([high, low])),
+                _ => return Err(Error::InvalidInteger),
+            },
+            // An empty value means the file has no short EF identifier.
+            SHORT_EF_IDENTIFIER => fci.short_file_id = object.value.first().map(|b| b >> 3),
+            SECURITY_ATTRIBUTE_PROPRIETARY
+            | SECURITY_ATTRIBUTE_REFERENCE
+            | SECURITY_ATTRIBUTE_COMPACT => fci
+                .security_attributes
+                .push((object.tag, object.value.to_vec())),
+            PROPRIETARY_INFORMATION => {
+                for object in object.children() {
+                    fci.sm_cipher = fci.sm_cipher.or(crt_cipher(object?)?);
+                }
+            }
+            _ => fci.sm_cipher = fci.sm_cipher.or(crt_cipher(object)?),
+        }
+    }
+    Ok(())
+}
+
+fn parse_size(value: &[u8]) -> Result<usize> {
+    ensure_err!((1..=4).contains(&value.len()), Error::InvalidInteger);
+    Ok(value.iter().fold(0, |acc, &b| (acc << 8) | b as usize))
+}
+
+fn crt_cipher(object: TlvRef) -> Result<Option<SymmetricCipher>> {
+    if !matches!(object.tag, CRT_CRYPTOGRAPHIC_CHECKSUM | CRT_CONFIDENTIALITY) {
+        return Ok(None);
+    }
+    let Some(reference) = object.find(CRYPTOGRAPHIC_MECHANISM_REFERENCE)? else {
+        return Ok(None);
+    };
+    let Ok(oid) = Oid::from_bytes(reference.value) else {
+        return Ok(None);
+    };
+    if let Ok(protocol) = PaceProtocol::try_from(oid) {
+        return Ok(protocol.cipher);
+    }
+    if let Ok(protocol) = ChipAuthenticationProtocol::try_from(oid) {
+        return Ok(protocol.cipher);
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_fci() {
+        assert_eq!(parse_fci(&[]).unwrap(), FileControlInfo::default());
+
+        // id-PACE-ECDH-GM-AES-CBC-CMAC-128 in proprietary information.
+        let fci = hex!("6F 10 A5 0E B4 0C 80 0A 04007F00070202040202");
+        assert_eq!(
+            parse_fci(&fci).unwrap().sm_cipher,
+            Some(SymmetricCipher::Aes128)
+        );
+
+        // id-CA-ECDH-3DES-CBC-CBC directly in the FCI, after an unrelated AID.
+        let fci = hex!("6F 17 84 07 A0000002471001 B8 0C 80 0A 04007F00070202030201");
+        assert_eq!(
+            parse_fci(&fci).unwrap().sm_cipher,
+            Some(SymmetricCipher::Tdes)
+        );
+
+        // FCP of EF.COM with proprietary and compact security attributes.
+        let fcp = hex!("62 15 80 02 001A 82 01 01 83 02 011E 88 01 F0 86 01 00 8D 02 4003");
+        let fcp = parse_fci(&fcp).unwrap();
+        assert_eq!(fcp.file_size, Some(26));
+        assert_eq!(fcp.total_file_size, None);
+        assert_eq!(fcp.file_descriptor, Some(0x01));
+        assert_eq!(fcp.file_id, Some(0x011E));
+        assert_eq!(fcp.short_file_id, Some(0x1E));
+        assert_eq!(
+            fcp.security_attributes,
+            vec![(0x86, vec![0x00]), (0x8D, vec![0x40, 0x03])]
+        );
+
+        // FCP wrapped in an FCI.
+        let fci = hex!("6F 08 62 06 81 04 00010000");
+        assert_eq!(parse_fci(&fci).unwrap().total_file_size, Some(0x10000));
+
+        // Non-standard short EF identifiers of an application.
+        let fci = hex!(
+            "6F 18 84 07 A0000002471001
+             62 07 83 02 0101 88 01 28
+             62 04 83 02 011E"
+        );
+        assert_eq!(parse_fci(&fci).unwrap().short_ef_ids, vec![(0x0101, 0x05)]);
+
+        // Unknown mechanisms are ignored.
+        let fci = hex!("6F 07 B4 05 80 03 2A0304");
+        assert_eq!(parse_fci(&fci).unwrap().sm_cipher, None);
+    }
+
+    #[test]
+    fn test_parse_fci_lenient() {
+        // Unknown template.
+        let data = hex!("A5 03 80 01 00");
+        assert!(parse_fci(&data).is_err());
+        assert_eq!(parse_fci_lenient(&data), FileControlInfo::default());
+
+        // Not TLV at all.
+        assert_eq!(parse_fci_lenient(&hex!("9F")), FileControlInfo::default());
+
+        // Fields before a malformed file identifier are kept.
+        let fcp = hex!("62 07 80 02 001A 83 01 01");
+        assert!(parse_fci(&fcp).is_err());
+        let fcp = parse_fci_lenient(&fcp);
+        assert_eq!(fcp.file_size, Some(26));
+        assert_eq!(fcp.file_id, None);
+    }
+}
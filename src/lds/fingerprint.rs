@@ -0,0 +1,172 @@
+//! Finger image records from DG3.
+//!
+//! See ISO/IEC 19794-4:2005 section 8.
+
+use {
+    super::{
+        biometric::{biometric_records, BiometricHeader},
+        Error, Result,
+    },
+    crate::ensure_err,
+};
+
+/// Tag of EF.DG3.
+pub const DG3_TAG: u32 = 0x63;
+
+const GENERAL_HEADER_LENGTH: usize = 32;
+const FINGER_HEADER_LENGTH: usize = 14;
+
+/// A single finger image from DG3.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FingerprintRecord {
+    /// Finger position code, see ISO/IEC 19794-4 table 2.
+    pub finger_position: u8,
+
+    /// Impression type code, see ISO/IEC 19794-4 table 4.
+    pub impression_type: u8,
+
+    pub image_format: FingerImageFormat,
+
+    /// Encoded image data.
+    #[cfg_attr(feature = "serde", serde(with = "crate::utils::serde_base64"))]
+    pub image: Vec<u8>,
+
+    /// Metadata from the biometric header of the record containing this image.
+    pub header: BiometricHeader,
+}
+
+/// Image compression algorithm, see ISO/IEC 19794-4 table 3.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FingerImageFormat {
+    Uncompressed,
+    UncompressedBitPacked,
+    Wsq,
+    Jpeg,
+    Jpeg2000,
+    Png,
+    Unknown(u8),
+}
+
+impl From<u8> for FingerImageFormat {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Uncompressed,
+            1 => Self::UncompressedBitPacked,
+            2 => Self::Wsq,
+            3 => Self::Jpeg,
+            4 => Self::Jpeg2000,
+            5 => Self::Png,
+            n => Self::Unknown(n),
+        }
+    }
+}
+
+/// Parse all finger image records in an EF.DG3 file.
+pub fn parse_dg3(data_group: &[u8]) -> Result<Vec<FingerprintRecord>> {
+    let mut records = Vec::new();
+    for (header, block) in biometric_records(data_group, DG3_TAG)? {
+        records.extend(parse_finger_image_record(block)?.into_iter().map(|record| {
+            FingerprintRecord {
+                header: header.clone(),
+                ..record
+            }
+        }));
+    }
+    Ok(records)
+}
+
+/// Parse an ISO/IEC 19794-4 finger image record.
+///
+/// A record holds a general header followed by one or more finger images.
+pub fn parse_finger_image_record(record: &[u8]) -> Result<Vec<FingerprintRecord>> {
+    ensure_err!(
+        record.len() >= GENERAL_HEADER_LENGTH,
+        Error::InvalidBiometricData("truncated general header")
+    );
+    ensure_err!(
+        &record[0..4] == b"FIR\0",
+        Error::InvalidBiometricData("not a finger image record")
+    );
+    let count = record[18] as usize;
+    let image_format = FingerImageFormat::from(record[29]);
+
+    let mut rest = &record[GENERAL_HEADER_LENGTH..];
+    let mut fingers = Vec::with_capacity(count);
+    for _ in 0..count {
+        ensure_err!(
+            rest.len() >= FINGER_HEADER_LENGTH,
+            Error::InvalidBiometricData("truncated finger header")
+        );
+        let length = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        ensure_err!(
+            (FINGER_HEADER_LENGTH..=rest.len()).contains(&length),
+            Error::InvalidBiometricData("invalid finger data length")
+        );
+        let (finger, tail) = rest.split_at(length);
+        fingers.push(FingerprintRecord {
+            finger_position: finger[4],
+            impression_type: finger[8],
+            image_format,
+            image: finger[FINGER_HEADER_LENGTH..].to_vec(),
+            header: BiometricHeader::default(),
+        });
+        rest = tail;
+    }
+    Ok(fingers)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_dg3() {
+        // General header with two finger images in WSQ format.
+        let mut record =
+            hex!("46495200 30313000 000000000041 0000 001F 02 01 01F4 01F4 01F4 01F4 08 02 0000")
+                .to_vec();
+        // Right index finger, live-scan plain, two image bytes.
+        record.extend_from_slice(&hex!("00000010 02 01 01 50 00 0190 01F4 00 AABB"));
+        // Left index finger, live-scan plain, three image bytes.
+        record.extend_from_slice(&hex!("00000011 07 01 01 50 00 0190 01F4 00 CCDDEE"));
+
+        let mut bdb = hex!("5F2E").to_vec();
+        bdb.push(record.len() as u8);
+        bdb.extend_from_slice(&record);
+        let mut bit = hex!("7F60").to_vec();
+        let bht = hex!("A1 17 800101 810108 87020101 88020007 8307 20240131120000");
+        bit.push((bdb.len() + bht.len()) as u8);
+        bit.extend_from_slice(&bht);
+        bit.extend_from_slice(&bdb);
+        let mut group = hex!("7F61").to_vec();
+        group.push((bit.len() + 3) as u8);
+        group.extend_from_slice(&hex!("02 01 01"));
+        group.extend_from_slice(&bit);
+        let mut dg3 = vec![0x63, group.len() as u8];
+        dg3.extend_from_slice(&group);
+
+        let fingers = parse_dg3(&dg3).unwrap();
+        assert_eq!(fingers.len(), 2);
+        assert_eq!(fingers[0].finger_position, 2);
+        assert_eq!(fingers[0].impression_type, 0);
+        assert_eq!(fingers[0].image_format, FingerImageFormat::Wsq);
+        assert_eq!(fingers[0].image, hex!("AABB"));
+        assert_eq!(fingers[1].finger_position, 7);
+        assert_eq!(fingers[1].image, hex!("CCDDEE"));
+        assert_eq!(fingers[1].header.version, Some(0x01));
+        assert_eq!(fingers[1].header.biometric_type, Some(0x08));
+        assert_eq!(fingers[1].header.format_owner, Some(0x0101));
+        assert_eq!(fingers[1].header.format_type, Some(0x0007));
+        assert_eq!(
+            fingers[1].header.creation_date,
+            Some(hex!("20240131120000"))
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated_record() {
+        assert!(parse_finger_image_record(b"FIR\0").is_err());
+    }
+}
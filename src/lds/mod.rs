@@ -0,0 +1,78 @@
+//! Logical Data Structure parsing.
+//!
+//! Data groups are BER-TLV encoded with multi-byte application tags (e.g. `7F61`), which
+//! [`der`] can not represent. They are parsed with the small [`tlv`] reader instead.
+//!
+//! See ICAO 9303-10 section 4.
+
+pub mod atr_info;
+pub mod biometric;
+pub mod com;
+pub mod data_group;
+pub mod dir;
+pub mod face;
+pub mod fci;
+pub mod fingerprint;
+pub mod mrz;
+pub mod personal_details;
+pub mod public_key;
+pub mod tlv;
+
+pub use self::{
+    atr_info::CardCapabilities,
+    biometric::BiometricHeader,
+    com::{parse_com, Com},
+    data_group::{parse_data_group, DataGroup, ParseOptions},
+    dir::ApplicationTemplate,
+    face::{FaceImage, FaceImageFormat},
+    fci::FileControlInfo,
+    fingerprint::{FingerImageFormat, FingerprintRecord},
+    mrz::{check_digit, validate_mrz_info, verify_check_digit, MrzInput, MrzName, OptionalData},
+    personal_details::{parse_dg11, PersonalDetails},
+    public_key::{KeyAlgorithm, PublicKeyInfo},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Truncated TLV data object.")]
+    TlvTruncated,
+
+    #[error("Unsupported TLV length encoding.")]
+    TlvInvalidLength,
+
+    #[error("TLV data objects of indefinite length nested too deeply.")]
+    TlvTooDeep,
+
+    #[error("Missing data object with tag {0:X}.")]
+    MissingTag(u32),
+
+    #[error("Unexpected data object with tag {0:X}.")]
+    UnexpectedTag(u32),
+
+    #[error("Invalid integer data object.")]
+    InvalidInteger,
+
+    #[error("Invalid biometric data block: {0}")]
+    InvalidBiometricData(&'static str),
+
+    #[error("Expected {expected} data objects in list, found {actual}.")]
+    CountMismatch { expected: usize, actual: usize },
+
+    #[error("Invalid MRZ data object.")]
+    InvalidMrz,
+
+    #[error("Invalid MRZ information length {0}, expected at least 24 characters.")]
+    InvalidMrzLength(usize),
+
+    #[error("Invalid MRZ character {0:?}.")]
+    InvalidMrzCharacter(char),
+
+    #[error("Invalid MRZ check digit for {0}.")]
+    InvalidCheckDigit(&'static str),
+
+    #[error("Invalid DER: {0}")]
+    InvalidDer(#[from] der::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
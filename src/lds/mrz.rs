@@ -0,0 +1,537 @@
+//! MRZ fields used as the access control password, and the holder name.
+//!
+//! See ICAO 9303-3 section 4.9 for check digits, section 4.6 for names and ICAO 9303-11
+//! section 9.7.2 for the MRZ information used to derive BAC and PACE keys.
+
+use {
+    super::{Error, Result},
+    crate::{ensure_err, utils::Redacted},
+    std::fmt,
+};
+
+/// Length of the document number field in the MRZ, shorter numbers are padded with `<`.
+pub const DOCUMENT_NUMBER_LENGTH: usize = 9;
+
+/// Length of each of the three lines of a TD1 (ID card) MRZ.
+pub const TD1_LINE_LENGTH: usize = 30;
+
+/// Length of each of the two lines of a TD2 MRZ.
+pub const TD2_LINE_LENGTH: usize = 36;
+
+/// Length of each of the two lines of a TD3 (passport) MRZ.
+pub const TD3_LINE_LENGTH: usize = 44;
+
+/// The MRZ fields needed for Basic Access Control and PACE.
+///
+/// Dates are `YYMMDD`. Check digits are computed, not supplied. The document number is
+/// redacted from the [`Debug`](fmt::Debug) output, see [`Redacted`]. As the fields are the
+/// Basic Access Control key material it can be deserialized, but not serialized.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct MrzInput {
+    pub document_number: String,
+    pub date_of_birth: String,
+    pub date_of_expiry: String,
+}
+
+impl fmt::Debug for MrzInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MrzInput")
+            .field("document_number", &Redacted(&self.document_number))
+            .field("date_of_birth", &self.date_of_birth)
+            .field("date_of_expiry", &self.date_of_expiry)
+            .finish()
+    }
+}
+
+impl MrzInput {
+    pub fn new(document_number: &str, date_of_birth: &str, date_of_expiry: &str) -> Result<Self> {
+        let document_number = document_number.trim_end_matches('<');
+        ensure_err!(
+            !document_number.is_empty() && document_number.bytes().all(is_mrz_character),
+            Error::InvalidMrz
+        );
+        for date in [date_of_birth, date_of_expiry] {
+            ensure_err!(
+                date.len() == 6 && date.bytes().all(|c| c.is_ascii_digit()),
+                Error::InvalidMrz
+            );
+        }
+        Ok(Self {
+            document_number: document_number.to_owned(),
+            date_of_birth: date_of_birth.to_owned(),
+            date_of_expiry: date_of_expiry.to_owned(),
+        })
+    }
+
+    /// Extract the fields from the first two lines of a TD1 (ID card) MRZ.
+    ///
+    /// Document numbers longer than nine characters continue in the optional data. The
+    /// check digit position then holds a filler, and the continuation is terminated by the
+    /// check digit over the full document number.
+    ///
+    /// See ICAO 9303-5 section 4.2.2 note j.
+    pub fn from_td1(line1: &str, line2: &str) -> Result<Self> {
+        ensure_err!(
+            line1.len() == TD1_LINE_LENGTH && line2.len() == TD1_LINE_LENGTH,
+            Error::InvalidMrz
+        );
+        ensure_err!(
+            line1.bytes().chain(line2.bytes()).all(is_mrz_character),
+            Error::InvalidMrz
+        );
+        let mut document_number = line1[5..14].to_owned();
+        let mut document_check = &line1[14..15];
+        if document_check == "<" {
+            // Continuation in the optional data, up to the first filler.
+            let optional = &line1[15..];
+            let end = optional.find('<').unwrap_or(optional.len());
+            ensure_err!(end >= 2, Error::InvalidMrz);
+            document_number.push_str(&optional[..end - 1]);
+            document_check = &optional[end - 1..end];
+        }
+        let (date_of_birth, birth_check) = (&line2[0..6], &line2[6..7]);
+        let (date_of_expiry, expiry_check) = (&line2[8..14], &line2[14..15]);
+        for (name, field, check) in [
+            ("document number", document_number.as_str(), document_check),
+            ("date of birth", date_of_birth, birth_check),
+            ("date of expiry", date_of_expiry, expiry_check),
+        ] {
+            ensure_err!(
+                check.starts_with(check_digit(field)),
+                Error::InvalidCheckDigit(name)
+            );
+        }
+        Self::new(&document_number, date_of_birth, date_of_expiry)
+    }
+
+    /// Extract the fields from a complete MRZ without line breaks, as stored in EF.DG1.
+    ///
+    /// The format follows from the length: TD1 has three lines of 30 characters, TD2 and
+    /// TD3 have two lines of 36 and 44 characters.
+    pub fn from_mrz(mrz: &str) -> Result<Self> {
+        ensure_err!(mrz.is_ascii(), Error::InvalidMrz);
+        match mrz.len() {
+            len if len == 3 * TD1_LINE_LENGTH => Self::from_td1(
+                &mrz[..TD1_LINE_LENGTH],
+                &mrz[TD1_LINE_LENGTH..2 * TD1_LINE_LENGTH],
+            ),
+            len if len == 2 * TD2_LINE_LENGTH => Self::from_second_line(&mrz[TD2_LINE_LENGTH..]),
+            len if len == 2 * TD3_LINE_LENGTH => Self::from_second_line(&mrz[TD3_LINE_LENGTH..]),
+            _ => Err(Error::InvalidMrz),
+        }
+    }
+
+    /// Extract the fields from the second line of a TD2 or TD3 MRZ.
+    fn from_second_line(line: &str) -> Result<Self> {
+        ensure_err!(line.bytes().all(is_mrz_character), Error::InvalidMrz);
+        for (name, field, check) in [
+            ("document number", &line[0..9], &line[9..10]),
+            ("date of birth", &line[13..19], &line[19..20]),
+            ("date of expiry", &line[21..27], &line[27..28]),
+        ] {
+            ensure_err!(
+                check.starts_with(check_digit(field)),
+                Error::InvalidCheckDigit(name)
+            );
+        }
+        Self::new(&line[0..9], &line[13..19], &line[21..27])
+    }
+
+    /// The MRZ information, each field followed by its check digit.
+    ///
+    /// This is the string accepted by [`Emrtd::basic_access_control`](crate::emrtd::Emrtd::basic_access_control).
+    /// Document numbers longer than nine characters are used in full.
+    pub fn mrz_information(&self) -> String {
+        let mut document_number = self.document_number.clone();
+        while document_number.len() < DOCUMENT_NUMBER_LENGTH {
+            document_number.push('<');
+        }
+        let mut result = String::new();
+        for field in [&document_number, &self.date_of_birth, &self.date_of_expiry] {
+            result.push_str(field);
+            result.push(check_digit(field));
+        }
+        result
+    }
+}
+
+/// The name of the holder from the MRZ.
+///
+/// Names are transliterated to `A-Z`: apostrophes are omitted and hyphens, like spaces,
+/// are encoded as a single `<`, so `O'NEIL-SMITH` reads as `ONEIL SMITH`. The original
+/// spelling is only available from DG11.
+///
+/// See ICAO 9303-3 section 4.6.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MrzName {
+    /// Primary identifier, usually the surname, with components separated by spaces.
+    pub primary: String,
+
+    /// Secondary identifier, usually the given names separated by spaces. Empty if the
+    /// holder has a single name.
+    pub secondary: String,
+
+    /// The field ends in a name character, so the name may have been truncated to fit.
+    pub truncated: bool,
+}
+
+impl MrzName {
+    /// Parse a name field, components separated by `<` and identifiers by `<<`.
+    pub fn parse(field: &str) -> Result<Self> {
+        if let Some(c) = field
+            .chars()
+            .find(|&c| !c.is_ascii() || !is_mrz_character(c as u8))
+        {
+            return Err(Error::InvalidMrzCharacter(c));
+        }
+        let name = field.trim_end_matches('<');
+        let (primary, secondary) = name.split_once("<<").unwrap_or((name, ""));
+        let components = |part: &str| {
+            part.split('<')
+                .filter(|component| !component.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        Ok(Self {
+            primary: components(primary),
+            secondary: components(secondary),
+            truncated: !field.is_empty() && name.len() == field.len(),
+        })
+    }
+
+    /// Extract the name from a complete MRZ without line breaks, as stored in EF.DG1.
+    ///
+    /// The name is on the third line of TD1 and follows the issuing state on the first line
+    /// of TD2 and TD3.
+    pub fn from_mrz(mrz: &str) -> Result<Self> {
+        ensure_err!(mrz.is_ascii(), Error::InvalidMrz);
+        match mrz.len() {
+            len if len == 3 * TD1_LINE_LENGTH => Self::parse(&mrz[2 * TD1_LINE_LENGTH..]),
+            len if len == 2 * TD2_LINE_LENGTH => Self::parse(&mrz[5..TD2_LINE_LENGTH]),
+            len if len == 2 * TD3_LINE_LENGTH => Self::parse(&mrz[5..TD3_LINE_LENGTH]),
+            _ => Err(Error::InvalidMrz),
+        }
+    }
+}
+
+/// The optional data fields of the MRZ, often holding a personal number.
+///
+/// See ICAO 9303-4, 9303-5 and 9303-6 section 4.2.2.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OptionalData {
+    /// Optional data with trailing fillers removed. TD1 has two fields, on the first and
+    /// second line, TD2 and TD3 have one on the second line.
+    pub fields: Vec<String>,
+
+    /// The TD3 optional data as a personal number, if not empty. Its check digit is
+    /// validated.
+    pub personal_number: Option<String>,
+}
+
+impl OptionalData {
+    /// Extract the optional data from a complete MRZ without line breaks, as stored in EF.DG1.
+    ///
+    /// The composite check digit, which covers the optional data, is validated.
+    pub fn from_mrz(mrz: &str) -> Result<Self> {
+        ensure_err!(mrz.is_ascii(), Error::InvalidMrz);
+        ensure_err!(mrz.bytes().all(is_mrz_character), Error::InvalidMrz);
+        let field = |field: &str| field.trim_end_matches('<').to_owned();
+        let (composite, check, result) = match mrz.len() {
+            len if len == 3 * TD1_LINE_LENGTH => {
+                let (line1, line2) = (&mrz[..30], &mrz[30..60]);
+                let composite = [&line1[5..30], &line2[0..7], &line2[8..15], &line2[18..29]];
+                let result = Self {
+                    fields: vec![field(&line1[15..30]), field(&line2[18..29])],
+                    personal_number: None,
+                };
+                (composite.concat(), &line2[29..30], result)
+            }
+            len if len == 2 * TD2_LINE_LENGTH => {
+                let line2 = &mrz[TD2_LINE_LENGTH..];
+                let composite = [&line2[0..10], &line2[13..20], &line2[21..35]];
+                let result = Self {
+                    fields: vec![field(&line2[28..35])],
+                    personal_number: None,
+                };
+                (composite.concat(), &line2[35..36], result)
+            }
+            len if len == 2 * TD3_LINE_LENGTH => {
+                let line2 = &mrz[TD3_LINE_LENGTH..];
+                let personal_number = field(&line2[28..42]);
+                // The check digit may be a filler if the personal number is absent.
+                if !personal_number.is_empty() || &line2[42..43] != "<" {
+                    ensure_err!(
+                        line2[42..43].starts_with(check_digit(&line2[28..42])),
+                        Error::InvalidCheckDigit("personal number")
+                    );
+                }
+                let composite = [&line2[0..10], &line2[13..20], &line2[21..43]];
+                let result = Self {
+                    fields: vec![personal_number.clone()],
+                    personal_number: Some(personal_number).filter(|n| !n.is_empty()),
+                };
+                (composite.concat(), &line2[43..44], result)
+            }
+            _ => return Err(Error::InvalidMrz),
+        };
+        ensure_err!(
+            check.starts_with(check_digit(&composite)),
+            Error::InvalidCheckDigit("composite")
+        );
+        Ok(result)
+    }
+}
+
+/// Check the structure of an MRZ information string, see [`MrzInput::mrz_information`].
+///
+/// The string is the document number of at least nine characters, the date of birth and
+/// the date of expiry, each followed by its check digit. A malformed string fails BAC and
+/// decrements the retry counter of chips that have one, so check it before authenticating.
+pub fn validate_mrz_info(info: &str) -> Result<()> {
+    if let Some(c) = info
+        .chars()
+        .find(|&c| !c.is_ascii() || !is_mrz_character(c as u8))
+    {
+        return Err(Error::InvalidMrzCharacter(c));
+    }
+    let length = info.len();
+    ensure_err!(
+        length >= DOCUMENT_NUMBER_LENGTH + 15,
+        Error::InvalidMrzLength(length)
+    );
+    let (document_number, dates) = info.split_at(length - 15);
+    let (date_of_birth, date_of_expiry) = dates[1..].split_at(7);
+    for date in [&date_of_birth[..6], &date_of_expiry[..6]] {
+        if let Some(c) = date.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(Error::InvalidMrzCharacter(c));
+        }
+    }
+    for (name, field, check) in [
+        ("document number", document_number, &dates[..1]),
+        ("date of birth", &date_of_birth[..6], &date_of_birth[6..]),
+        ("date of expiry", &date_of_expiry[..6], &date_of_expiry[6..]),
+    ] {
+        ensure_err!(
+            check.starts_with(check_digit(field)),
+            Error::InvalidCheckDigit(name)
+        );
+    }
+    Ok(())
+}
+
+/// Compute the check digit of an MRZ field.
+///
+/// Characters are valued `0-9` for digits, `10-35` for `A-Z` and `0` for the filler `<`,
+/// then weighted `7, 3, 1` repeating and summed modulo 10.
+///
+/// ICAO 9303-3 section 4.9
+pub fn check_digit(field: &str) -> char {
+    let sum: u32 = field
+        .bytes()
+        .zip([7, 3, 1].into_iter().cycle())
+        .map(|(c, weight)| character_value(c) * weight)
+        .sum();
+    char::from(b'0' + (sum % 10) as u8)
+}
+
+/// True if `expected` is the check digit of `field`, see [`check_digit`].
+pub fn verify_check_digit(field: &str, expected: char) -> bool {
+    check_digit(field) == expected
+}
+
+fn character_value(c: u8) -> u32 {
+    match c {
+        b'0'..=b'9' => (c - b'0') as u32,
+        b'A'..=b'Z' => (c - b'A') as u32 + 10,
+        _ => 0,
+    }
+}
+
+fn is_mrz_character(c: u8) -> bool {
+    matches!(c, b'0'..=b'9' | b'A'..=b'Z' | b'<')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ICAO 9303-11 Appendix D.2
+    #[test]
+    fn test_mrz_information() {
+        let input = MrzInput::new("L898902C", "690806", "940623").unwrap();
+        assert_eq!(input.mrz_information(), "L898902C<369080619406236");
+        assert_eq!(check_digit("L898902C<"), '3');
+        assert!(MrzInput::new("l898902c", "690806", "940623").is_err());
+        assert!(MrzInput::new("L898902C", "6908", "940623").is_err());
+    }
+
+    // ICAO 9303-11 Appendix D.2
+    #[test]
+    fn test_check_digit() {
+        assert_eq!(check_digit("690806"), '1');
+        assert_eq!(check_digit("940623"), '6');
+        assert!(verify_check_digit("L898902C3", '6'));
+        assert!(!verify_check_digit("L898902C3", '7'));
+    }
+
+    #[test]
+    fn test_validate_mrz_info() {
+        validate_mrz_info("L898902C<369080619406236").unwrap();
+        validate_mrz_info("D23145890734974081221204159").unwrap();
+        assert!(matches!(
+            validate_mrz_info("L898902C<36908061940623"),
+            Err(Error::InvalidMrzLength(23))
+        ));
+        assert!(matches!(
+            validate_mrz_info("l898902C<369080619406236"),
+            Err(Error::InvalidMrzCharacter('l'))
+        ));
+        assert!(matches!(
+            validate_mrz_info("L898902C<36908A619406236"),
+            Err(Error::InvalidMrzCharacter('A'))
+        ));
+        assert!(matches!(
+            validate_mrz_info("L898902C<469080619406236"),
+            Err(Error::InvalidCheckDigit("document number"))
+        ));
+        assert!(matches!(
+            validate_mrz_info("L898902C<369080629406236"),
+            Err(Error::InvalidCheckDigit("date of birth"))
+        ));
+        assert!(matches!(
+            validate_mrz_info("L898902C<369080619406237"),
+            Err(Error::InvalidCheckDigit("date of expiry"))
+        ));
+    }
+
+    #[test]
+    fn test_from_mrz() {
+        // ICAO 9303-4 Appendix A, TD3.
+        let input = MrzInput::from_mrz(concat!(
+            "P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<",
+            "L898902C36UTO7408122F1204159ZE184226B<<<<<10"
+        ))
+        .unwrap();
+        assert_eq!(
+            input,
+            MrzInput::new("L898902C3", "740812", "120415").unwrap()
+        );
+
+        let input = MrzInput::from_mrz(concat!(
+            "I<UTOD23145890<7349<<<<<<<<<<<",
+            "7408122F1204159UTO<<<<<<<<<<<6",
+            "ERIKSSON<<ANNA<MARIA<<<<<<<<<<"
+        ))
+        .unwrap();
+        assert_eq!(input.document_number, "D23145890734");
+
+        assert!(MrzInput::from_mrz("L898902C36UTO7408122F1204159ZE184226B<<<<<10").is_err());
+        assert!(matches!(
+            MrzInput::from_mrz(concat!(
+                "P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<",
+                "L898902C36UTO7408123F1204159ZE184226B<<<<<10"
+            )),
+            Err(Error::InvalidCheckDigit("date of birth"))
+        ));
+    }
+
+    // ICAO 9303-5 Appendix A, TD1 with a twelve character document number.
+    #[test]
+    fn test_td1_long_document_number() {
+        let input = MrzInput::from_td1(
+            "I<UTOD23145890<7349<<<<<<<<<<<",
+            "7408122F1204159UTO<<<<<<<<<<<6",
+        )
+        .unwrap();
+        assert_eq!(input.document_number, "D23145890734");
+        assert_eq!(input.mrz_information(), "D23145890734974081221204159");
+
+        // Wrong check digit on the continuation.
+        assert!(matches!(
+            MrzInput::from_td1(
+                "I<UTOD23145890<7348<<<<<<<<<<<",
+                "7408122F1204159UTO<<<<<<<<<<<6",
+            ),
+            Err(Error::InvalidCheckDigit("document number"))
+        ));
+    }
+
+    #[test]
+    fn test_mrz_name() {
+        let name = MrzName::parse("ERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<").unwrap();
+        assert_eq!(name.primary, "ERIKSSON");
+        assert_eq!(name.secondary, "ANNA MARIA");
+        assert!(!name.truncated);
+
+        // Truncated to the 39 characters of a TD3 name field.
+        let name = MrzName::parse("DE<LA<CRUZ<GONZALEZ<FERNANDEZ<<MARIA<JO").unwrap();
+        assert_eq!(name.primary, "DE LA CRUZ GONZALEZ FERNANDEZ");
+        assert_eq!(name.secondary, "MARIA JO");
+        assert!(name.truncated);
+
+        // O'NEIL-SMITH, single name.
+        let name = MrzName::parse("ONEIL<SMITH<<<<<<<<<<<<<<<<<<<").unwrap();
+        assert_eq!(name.primary, "ONEIL SMITH");
+        assert_eq!(name.secondary, "");
+
+        assert!(matches!(
+            MrzName::parse("ERIKSSON<<ANNA-MARIA"),
+            Err(Error::InvalidMrzCharacter('-'))
+        ));
+
+        let name = MrzName::from_mrz(concat!(
+            "I<UTOD231458907<<<<<<<<<<<<<<<",
+            "7408122F1204159UTO<<<<<<<<<<<6",
+            "ERIKSSON<<ANNA<MARIA<<<<<<<<<<"
+        ))
+        .unwrap();
+        assert_eq!(name.primary, "ERIKSSON");
+        assert_eq!(name.secondary, "ANNA MARIA");
+    }
+
+    #[test]
+    fn test_optional_data() {
+        let line1 = "P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<";
+        let data = OptionalData::from_mrz(&format!(
+            "{line1}L898902C36UTO7408122F1204159ZE184226B<<<<<10"
+        ))
+        .unwrap();
+        assert_eq!(data.fields, vec!["ZE184226B"]);
+        assert_eq!(data.personal_number.as_deref(), Some("ZE184226B"));
+
+        assert!(matches!(
+            OptionalData::from_mrz(&format!(
+                "{line1}L898902C36UTO7408122F1204159ZE184226B<<<<<20"
+            )),
+            Err(Error::InvalidCheckDigit("personal number"))
+        ));
+        assert!(matches!(
+            OptionalData::from_mrz(&format!(
+                "{line1}L898902C36UTO7408122F1204159ZE184226B<<<<<11"
+            )),
+            Err(Error::InvalidCheckDigit("composite"))
+        ));
+
+        let data = OptionalData::from_mrz(concat!(
+            "I<UTOD231458907<<<<<<<<<<<<<<<",
+            "7408122F1204159UTO<<<<<<<<<<<6",
+            "ERIKSSON<<ANNA<MARIA<<<<<<<<<<"
+        ))
+        .unwrap();
+        assert_eq!(data.fields, vec!["", ""]);
+        assert_eq!(data.personal_number, None);
+    }
+
+    #[test]
+    #[cfg(not(feature = "dangerous"))]
+    fn test_debug_redacted() {
+        let input = MrzInput::new("L898902C", "690806", "940623").unwrap();
+        assert_eq!(
+            format!("{input:?}"),
+            r#"MrzInput { document_number: <redacted>, date_of_birth: "690806", date_of_expiry: "940623" }"#
+        );
+    }
+}
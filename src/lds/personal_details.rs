@@ -0,0 +1,118 @@
+//! Additional personal details from DG11.
+//!
+//! See ICAO 9303-10 section 4.7.11.
+
+use super::{
+    tlv::{counted_list, parse_tlv, TlvRef},
+    Error, Result,
+};
+
+/// Tag of EF.DG11.
+pub const DG11_TAG: u32 = 0x6B;
+
+const FULL_NAME_TAG: u32 = 0x5F0E;
+const OTHER_NAMES_TAG: u32 = 0xA0;
+const OTHER_NAME_TAG: u32 = 0x5F0F;
+const PERSONAL_NUMBER_TAG: u32 = 0x5F10;
+const FULL_DATE_OF_BIRTH_TAG: u32 = 0x5F2B;
+const PLACE_OF_BIRTH_TAG: u32 = 0x5F11;
+const ADDRESS_TAG: u32 = 0x5F42;
+
+/// Personal details from DG11, in national characters.
+///
+/// Names use `<<` between the primary and secondary identifiers and `<` as separator, like
+/// the MRZ. Other data objects of DG11 are ignored.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PersonalDetails {
+    /// Name of the holder in full, not truncated as in the MRZ.
+    pub full_name: Option<String>,
+
+    /// Other names of the holder, e.g. a birth name.
+    pub other_names: Vec<String>,
+
+    pub personal_number: Option<String>,
+
+    /// Date of birth as `YYYYMMDD`.
+    pub full_date_of_birth: Option<String>,
+
+    pub place_of_birth: Option<String>,
+
+    pub address: Option<String>,
+}
+
+/// Parse the personal details in EF.DG11.
+///
+/// Other names are a count-prefixed list in template `A0`, each in its own `5F0F` data
+/// object. Chips that split the names over several templates are supported as well, the
+/// count then covers all of them.
+pub fn parse_dg11(bytes: &[u8]) -> Result<PersonalDetails> {
+    let (dg11, _) = parse_tlv(bytes)?;
+    if dg11.tag != DG11_TAG {
+        return Err(Error::UnexpectedTag(dg11.tag));
+    }
+    let mut details = PersonalDetails::default();
+    let mut other_names = Vec::new();
+    for object in dg11.children() {
+        let object = object?;
+        match object.tag {
+            FULL_NAME_TAG => details.full_name = Some(text(object)),
+            OTHER_NAMES_TAG => other_names.push(object),
+            PERSONAL_NUMBER_TAG => details.personal_number = Some(text(object)),
+            FULL_DATE_OF_BIRTH_TAG => details.full_date_of_birth = Some(text(object)),
+            PLACE_OF_BIRTH_TAG => details.place_of_birth = Some(text(object)),
+            ADDRESS_TAG => details.address = Some(text(object)),
+            _ => {}
+        }
+    }
+    if !other_names.is_empty() {
+        for name in counted_list(&other_names, OTHER_NAME_TAG)? {
+            details.other_names.push(text(name));
+        }
+    }
+    Ok(details)
+}
+
+fn text(object: TlvRef) -> String {
+    String::from_utf8_lossy(object.value).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_dg11_other_names() {
+        let mut dg11 = hex!("6B 41 5C 05 5F0E A0 5F2B").to_vec();
+        dg11.extend_from_slice(&hex!("5F0E 14"));
+        dg11.extend_from_slice(b"ERIKSSON<<ANNA<MARIA");
+        dg11.extend_from_slice(&hex!("A0 16 02 01 02 5F0F 06"));
+        dg11.extend_from_slice(b"SVENSK");
+        dg11.extend_from_slice(&hex!("5F0F 07"));
+        dg11.extend_from_slice(b"SMITH<<");
+        dg11.extend_from_slice(&hex!("5F2B 08"));
+        dg11.extend_from_slice(b"19740812");
+        let details = parse_dg11(&dg11).unwrap();
+        assert_eq!(details.full_name.as_deref(), Some("ERIKSSON<<ANNA<MARIA"));
+        assert_eq!(details.other_names, ["SVENSK", "SMITH<<"]);
+        assert_eq!(details.full_date_of_birth.as_deref(), Some("19740812"));
+        assert_eq!(details.place_of_birth, None);
+
+        // Names split over two templates, counted in the first.
+        let mut dg11 = hex!("6B 18 A0 0C 02 01 02 5F0F 06").to_vec();
+        dg11.extend_from_slice(b"SVENSK");
+        dg11.extend_from_slice(&hex!("A0 08 5F0F 05"));
+        dg11.extend_from_slice(b"SMITH");
+        assert_eq!(parse_dg11(&dg11).unwrap().other_names, ["SVENSK", "SMITH"]);
+
+        // The count does not match the number of names.
+        let dg11 = hex!("6B 0C A0 0A 02 01 02 5F0F 04 4A4F4853");
+        assert!(matches!(
+            parse_dg11(&dg11),
+            Err(Error::CountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+//! Public keys from DG14 and DG15.
+//!
+//! See ICAO 9303-11 section 6.1 for Active Authentication and RFC 5480 for the encoding.
+
+use {
+    super::Result,
+    crate::{
+        asn1::{
+            public_key::{
+                ECAlgoParameters, FieldId, PubkeyAlgorithmIdentifier, RsaPublicKey,
+                SubjectPublicKeyInfo, ID_RSA_ENCRYPTION,
+            },
+            EfDg15,
+        },
+        crypto::named_curve_bits,
+    },
+    der::{
+        asn1::{Int, ObjectIdentifier as Oid},
+        Decode, Encode,
+    },
+    sha2::{Digest, Sha256},
+};
+
+/// A public key with the properties needed to display or pin it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PublicKeyInfo {
+    pub algorithm: KeyAlgorithm,
+
+    /// RSA modulus or EC field size in bits, `None` if unknown.
+    pub key_size: Option<usize>,
+
+    /// SHA-256 of the DER encoded `SubjectPublicKeyInfo`.
+    pub fingerprint: [u8; 32],
+
+    pub public_key: SubjectPublicKeyInfo,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum KeyAlgorithm {
+    Rsa {
+        modulus: Int,
+        public_exponent: Int,
+    },
+
+    /// Elliptic curve, `curve` is `None` for explicit or implicit domain parameters.
+    Ec {
+        curve: Option<Oid>,
+    },
+
+    Unknown(Oid),
+}
+
+impl PublicKeyInfo {
+    pub fn from_spki(public_key: SubjectPublicKeyInfo) -> Result<Self> {
+        let (algorithm, key_size) = match &public_key.algorithm {
+            PubkeyAlgorithmIdentifier::Ec(ECAlgoParameters::NamedCurve(curve)) => (
+                KeyAlgorithm::Ec {
+                    curve: Some(*curve),
+                },
+                named_curve_bits(curve),
+            ),
+            PubkeyAlgorithmIdentifier::Ec(ECAlgoParameters::EcParameters(params)) => {
+                let key_size = match &params.field_id {
+                    FieldId::PrimeField { modulus } => Some(int_bits(modulus)),
+                    FieldId::Unknown(_) => None,
+                };
+                (KeyAlgorithm::Ec { curve: None }, key_size)
+            }
+            PubkeyAlgorithmIdentifier::Ec(ECAlgoParameters::ImplicitlyCA(_)) => {
+                (KeyAlgorithm::Ec { curve: None }, None)
+            }
+            PubkeyAlgorithmIdentifier::Unknown(any) if any.algorithm == ID_RSA_ENCRYPTION => {
+                let key = RsaPublicKey::from_der(public_key.subject_public_key.raw_bytes())?;
+                let key_size = int_bits(&key.modulus);
+                (
+                    KeyAlgorithm::Rsa {
+                        modulus: key.modulus,
+                        public_exponent: key.public_exponent,
+                    },
+                    Some(key_size),
+                )
+            }
+            PubkeyAlgorithmIdentifier::Unknown(any) => (KeyAlgorithm::Unknown(any.algorithm), None),
+        };
+        let fingerprint = Sha256::digest(public_key.to_der()?).into();
+        Ok(Self {
+            algorithm,
+            key_size,
+            fingerprint,
+            public_key,
+        })
+    }
+}
+
+/// Parse EF.DG15, the Active Authentication public key.
+pub fn parse_dg15(data_group: &[u8]) -> Result<PublicKeyInfo> {
+    PublicKeyInfo::from_spki(EfDg15::from_der(data_group)?.0)
+}
+
+/// Number of significant bits of a non-negative integer.
+fn int_bits(int: &Int) -> usize {
+    let bytes = int.as_bytes();
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    match bytes[start..].first() {
+        Some(first) => (bytes.len() - start) * 8 - first.leading_zeros() as usize,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::asn1::{AnyAlgorithmIdentifier, ApplicationTagged},
+        der::asn1::{Any, BitString},
+        hex_literal::hex,
+    };
+
+    #[test]
+    fn test_parse_dg15_ec() {
+        let mut dg15 =
+            hex!("6F 5B 30 59 30 13 06 07 2A8648CE3D0201 06 08 2A8648CE3D030107 03 42 00 04")
+                .to_vec();
+        dg15.extend_from_slice(&[0x42; 64]);
+        let key = parse_dg15(&dg15).unwrap();
+        assert_eq!(
+            key.algorithm,
+            KeyAlgorithm::Ec {
+                curve: Some(Oid::new_unwrap("1.2.840.10045.3.1.7"))
+            }
+        );
+        assert_eq!(key.key_size, Some(256));
+        assert_eq!(
+            key.fingerprint,
+            <[u8; 32]>::from(Sha256::digest(&dg15[2..]))
+        );
+        assert!(matches!(
+            key.public_key.algorithm,
+            PubkeyAlgorithmIdentifier::Ec(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_dg15_rsa() {
+        let mut modulus = vec![0x00, 0xC0];
+        modulus.extend_from_slice(&[0x42; 127]);
+        let rsa = RsaPublicKey {
+            modulus: Int::new(&modulus).unwrap(),
+            public_exponent: Int::new(&hex!("010001")).unwrap(),
+        };
+        let spki = SubjectPublicKeyInfo {
+            algorithm: PubkeyAlgorithmIdentifier::Unknown(AnyAlgorithmIdentifier {
+                algorithm: ID_RSA_ENCRYPTION,
+                parameters: Some(Any::null()),
+            }),
+            subject_public_key: BitString::from_bytes(&rsa.to_der().unwrap()).unwrap(),
+        };
+        let dg15 = ApplicationTagged::<15, _>(spki).to_der().unwrap();
+        let key = parse_dg15(&dg15).unwrap();
+        assert_eq!(key.key_size, Some(1024));
+        let KeyAlgorithm::Rsa {
+            public_exponent, ..
+        } = key.algorithm
+        else {
+            panic!("expected RSA key");
+        };
+        assert_eq!(public_exponent.as_bytes(), hex!("010001"));
+    }
+}
@@ -0,0 +1,427 @@
+//! BER-TLV data objects.
+//!
+//! Tags are represented as the big-endian integer of their encoded bytes, so `7F61` is
+//! `0x7F61`, matching the notation used in ICAO 9303-10.
+//!
+//! See ISO 7816-4 section 5.2.2 and ICAO 9303-10 section 4.2.
+
+use {
+    super::{Error, Result},
+    crate::ensure_err,
+};
+
+/// A BER-TLV data object borrowing its value.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TlvRef<'a> {
+    pub tag: u32,
+    pub value: &'a [u8],
+}
+
+/// A BER-TLV data object owning its value.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Tlv {
+    pub tag: u32,
+    pub value: Vec<u8>,
+}
+
+/// Iterator over consecutive BER-TLV data objects.
+#[derive(Clone, Debug)]
+pub struct TlvIter<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> TlvRef<'a> {
+    /// Constructed data objects contain further data objects.
+    pub fn is_constructed(&self) -> bool {
+        first_tag_byte(self.tag) & 0x20 != 0
+    }
+
+    /// Iterate over the data objects contained in a constructed object.
+    pub fn children(&self) -> TlvIter<'a> {
+        TlvIter::new(self.value)
+    }
+
+    /// Find the first child data object with the given tag.
+    pub fn find(&self, tag: u32) -> Result<Option<TlvRef<'a>>> {
+        for child in self.children() {
+            let child = child?;
+            if child.tag == tag {
+                return Ok(Some(child));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find the first child data object with the given tag, or fail.
+    pub fn get(&self, tag: u32) -> Result<TlvRef<'a>> {
+        self.find(tag)?.ok_or(Error::MissingTag(tag))
+    }
+}
+
+impl From<TlvRef<'_>> for Tlv {
+    fn from(tlv: TlvRef<'_>) -> Self {
+        Self {
+            tag: tlv.tag,
+            value: tlv.value.to_vec(),
+        }
+    }
+}
+
+impl<'a> TlvIter<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = Result<TlvRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        match parse_tlv(self.bytes) {
+            Ok((tlv, rest)) => {
+                self.bytes = rest;
+                Some(Ok(tlv))
+            }
+            Err(e) => {
+                // Stop iterating after an error.
+                self.bytes = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Length byte of the indefinite length form.
+const INDEFINITE_LENGTH: u8 = 0x80;
+
+/// End-of-contents marker closing a value of indefinite length.
+const END_OF_CONTENTS: [u8; 2] = [0x00, 0x00];
+
+/// Maximum nesting of objects with indefinite length, deeper input is rejected.
+const MAX_INDEFINITE_DEPTH: usize = 32;
+
+/// Tag of the data object holding the number of entries in a count-prefixed list.
+const COUNT_TAG: u32 = 0x02;
+
+/// The entries of a count-prefixed list in `templates`.
+///
+/// The first template starts with a `02` data object holding the number of entries,
+/// followed by data objects with tag `tag`. The entries may be split over several
+/// templates, the count is then the total over all of them and only the first template
+/// needs to hold it. This is used for the other names in DG11, the other persons in DG12
+/// and the persons to notify in DG16.
+///
+/// See ICAO 9303-10 section 4.7.11.
+pub fn counted_list<'a>(templates: &[TlvRef<'a>], tag: u32) -> Result<Vec<TlvRef<'a>>> {
+    let first = templates.first().ok_or(Error::MissingTag(COUNT_TAG))?;
+    let count = first.get(COUNT_TAG)?.value;
+    ensure_err!((1..=4).contains(&count.len()), Error::InvalidInteger);
+    let expected = count
+        .iter()
+        .fold(0_usize, |acc, &byte| (acc << 8) | byte as usize);
+    let mut entries = Vec::new();
+    for template in templates {
+        for child in template.children() {
+            let child = child?;
+            if child.tag == tag {
+                entries.push(child);
+            }
+        }
+    }
+    ensure_err!(
+        entries.len() == expected,
+        Error::CountMismatch {
+            expected,
+            actual: entries.len(),
+        }
+    );
+    Ok(entries)
+}
+
+/// Length of a data object as determined from its first bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObjectLength {
+    /// More bytes are needed to decode the tag and length.
+    Incomplete,
+
+    /// Total encoded length, including tag and length bytes.
+    Definite(usize),
+
+    /// Indefinite length form `80`, the end is only known by reading on.
+    Indefinite,
+}
+
+/// Determine the total length of a data object from its leading bytes.
+pub fn object_length(bytes: &[u8]) -> Result<ObjectLength> {
+    let (_, rest) = match parse_tag(bytes) {
+        Err(Error::TlvTruncated) => return Ok(ObjectLength::Incomplete),
+        result => result?,
+    };
+    if rest.first() == Some(&INDEFINITE_LENGTH) {
+        return Ok(ObjectLength::Indefinite);
+    }
+    let (length, value) = match parse_length(rest) {
+        Err(Error::TlvTruncated) => return Ok(ObjectLength::Incomplete),
+        result => result?,
+    };
+    let header_len = bytes.len() - value.len();
+    Ok(ObjectLength::Definite(header_len + length))
+}
+
+/// Parse a single data object, returning it and the remaining bytes.
+///
+/// Constructed objects may use the indefinite length form, their value then runs up to the
+/// matching end-of-contents marker `0000`. Such objects may be nested at most 32 deep.
+pub fn parse_tlv(bytes: &[u8]) -> Result<(TlvRef<'_>, &[u8])> {
+    parse_tlv_at_depth(bytes, 0)
+}
+
+/// Parse a single data object nested in `depth` objects of indefinite length.
+fn parse_tlv_at_depth(bytes: &[u8], depth: usize) -> Result<(TlvRef<'_>, &[u8])> {
+    let (tag, bytes) = parse_tag(bytes)?;
+    if let Some((&INDEFINITE_LENGTH, value)) = bytes.split_first() {
+        ensure_err!(first_tag_byte(tag) & 0x20 != 0, Error::TlvInvalidLength);
+        ensure_err!(depth < MAX_INDEFINITE_DEPTH, Error::TlvTooDeep);
+        return parse_indefinite(tag, value, depth + 1);
+    }
+    let (length, bytes) = parse_length(bytes)?;
+    ensure_err!(bytes.len() >= length, Error::TlvTruncated);
+    let (value, rest) = bytes.split_at(length);
+    Ok((TlvRef { tag, value }, rest))
+}
+
+/// Parse the value of indefinite length following the header of a constructed object.
+fn parse_indefinite(tag: u32, bytes: &[u8], depth: usize) -> Result<(TlvRef<'_>, &[u8])> {
+    let mut rest = bytes;
+    loop {
+        if let Some(tail) = rest.strip_prefix(&END_OF_CONTENTS) {
+            let value = &bytes[..bytes.len() - rest.len()];
+            return Ok((TlvRef { tag, value }, tail));
+        }
+        // Nested objects may themselves have indefinite length.
+        (_, rest) = parse_tlv_at_depth(rest, depth)?;
+    }
+}
+
+/// Parse a BER-TLV tag of up to four bytes.
+fn parse_tag(bytes: &[u8]) -> Result<(u32, &[u8])> {
+    let (&first, mut rest) = bytes.split_first().ok_or(Error::TlvTruncated)?;
+    let mut tag = first as u32;
+    if first & 0x1F == 0x1F {
+        // Subsequent bytes follow while b8 is set.
+        loop {
+            let (&byte, tail) = rest.split_first().ok_or(Error::TlvTruncated)?;
+            ensure_err!(tag <= 0xFF_FFFF, Error::TlvInvalidLength);
+            tag = (tag << 8) | byte as u32;
+            rest = tail;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+    Ok((tag, rest))
+}
+
+/// Parse a definite BER-TLV length of up to four length bytes.
+fn parse_length(bytes: &[u8]) -> Result<(usize, &[u8])> {
+    let (&first, rest) = bytes.split_first().ok_or(Error::TlvTruncated)?;
+    match first {
+        0x00..=0x7F => Ok((first as usize, rest)),
+        0x81..=0x84 => {
+            let n = (first & 0x7F) as usize;
+            ensure_err!(rest.len() >= n, Error::TlvTruncated);
+            let (length, rest) = rest.split_at(n);
+            let length = length
+                .iter()
+                .fold(0_usize, |acc, &byte| (acc << 8) | byte as usize);
+            Ok((length, rest))
+        }
+        _ => Err(Error::TlvInvalidLength),
+    }
+}
+
+fn first_tag_byte(tag: u32) -> u8 {
+    let bytes = tag.to_be_bytes();
+    bytes.iter().copied().find(|&byte| byte != 0).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_object_length() {
+        for (bytes, expected) in [
+            (&hex!("60 16 5F01")[..], ObjectLength::Definite(0x18)),
+            (&hex!("77 81 90 30"), ObjectLength::Definite(0x93)),
+            (&hex!("75 82 0102 5F"), ObjectLength::Definite(0x106)),
+            (&hex!("7F61 82 01"), ObjectLength::Incomplete),
+            (&hex!("60"), ObjectLength::Incomplete),
+            (&hex!("60 80 5F01"), ObjectLength::Indefinite),
+        ] {
+            assert_eq!(object_length(bytes).unwrap(), expected);
+        }
+        assert!(object_length(&hex!("60 85 0000000000")).is_err());
+    }
+
+    #[test]
+    fn test_parse_nested() {
+        let bytes = hex!("7F61 08 02 01 01 5F2E 02 AB CD FF");
+        let (tlv, rest) = parse_tlv(&bytes).unwrap();
+        assert_eq!(rest, &[0xFF]);
+        assert_eq!(tlv.tag, 0x7F61);
+        assert!(tlv.is_constructed());
+        assert_eq!(tlv.get(0x02).unwrap().value, &[0x01]);
+        assert_eq!(tlv.get(0x5F2E).unwrap().value, &[0xAB, 0xCD]);
+        assert!(tlv.find(0x5F2F).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_nested_templates() {
+        // DG2 style biometric information group template with two nested templates.
+        let bytes = hex!("7F61 13 02 01 02 7F60 06 A1 04 80 02 0101 7F60 04 A1 02 81 00");
+        let (group, _) = parse_tlv(&bytes).unwrap();
+        let templates: Vec<_> = group.children().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(templates.len(), 3);
+        assert_eq!(templates[1].tag, 0x7F60);
+        assert_eq!(templates[2].tag, 0x7F60);
+        let header = templates[1].get(0xA1).unwrap();
+        assert!(header.is_constructed());
+        assert_eq!(header.get(0x80).unwrap().value, &[0x01, 0x01]);
+        assert!(templates[2]
+            .get(0xA1)
+            .unwrap()
+            .get(0x81)
+            .unwrap()
+            .value
+            .is_empty());
+        assert_eq!(
+            Tlv::from(templates[2]),
+            Tlv {
+                tag: 0x7F60,
+                value: hex!("A1 02 81 00").to_vec()
+            }
+        );
+
+        // A truncated child is reported while iterating, after which iteration stops.
+        let (template, _) = parse_tlv(&hex!("A1 05 80 02 0101 81")).unwrap();
+        let mut children = template.children();
+        assert_eq!(children.next().unwrap().unwrap().value, &[0x01, 0x01]);
+        assert!(matches!(children.next(), Some(Err(Error::TlvTruncated))));
+        assert!(children.next().is_none());
+        assert!(matches!(template.find(0x82), Err(Error::TlvTruncated)));
+    }
+
+    #[test]
+    fn test_parse_indefinite_length() {
+        let bytes = hex!("75 80 7F61 80 02 01 01 0000 5F2E 01 AB 0000 FF");
+        let (dg2, rest) = parse_tlv(&bytes).unwrap();
+        assert_eq!(rest, &[0xFF]);
+        assert_eq!(dg2.tag, 0x75);
+        assert_eq!(dg2.value, &hex!("7F61 80 02 01 01 0000 5F2E 01 AB"));
+        let group = dg2.get(0x7F61).unwrap();
+        assert_eq!(group.value, &hex!("02 01 01"));
+        assert_eq!(dg2.get(0x5F2E).unwrap().value, &[0xAB]);
+
+        // Primitive objects have definite length.
+        assert!(matches!(
+            parse_tlv(&hex!("5F2E 80 AB 0000")),
+            Err(Error::TlvInvalidLength)
+        ));
+        // Missing end-of-contents.
+        assert!(matches!(
+            parse_tlv(&hex!("7F61 80 02 01 01")),
+            Err(Error::TlvTruncated)
+        ));
+    }
+
+    #[test]
+    fn test_parse_indefinite_depth() {
+        let nested = |depth: usize| {
+            let mut bytes = [0x7F, 0x61, 0x80].repeat(depth);
+            bytes.extend([0x00, 0x00].repeat(depth));
+            bytes
+        };
+        let bytes = nested(MAX_INDEFINITE_DEPTH);
+        let (outer, rest) = parse_tlv(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(outer.tag, 0x7F61);
+        assert!(matches!(
+            parse_tlv(&nested(MAX_INDEFINITE_DEPTH + 1)),
+            Err(Error::TlvTooDeep)
+        ));
+        // Without end-of-contents markers, as long as the input allows.
+        assert!(matches!(
+            parse_tlv(&[0x7F, 0x61, 0x80].repeat(10_000)),
+            Err(Error::TlvTooDeep)
+        ));
+    }
+
+    #[test]
+    fn test_counted_list() {
+        let bytes = hex!("A0 0C 02 01 02 5F1A 02 4142 5F1A 01 43");
+        let (template, _) = parse_tlv(&bytes).unwrap();
+        let entries = counted_list(&[template], 0x5F1A).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].value, b"AB");
+        assert_eq!(entries[1].value, b"C");
+        assert!(matches!(
+            counted_list(&[template], 0x5F0F),
+            Err(Error::CountMismatch {
+                expected: 2,
+                actual: 0
+            })
+        ));
+        assert!(matches!(
+            counted_list(&[], 0x5F1A),
+            Err(Error::MissingTag(0x02))
+        ));
+    }
+
+    #[test]
+    fn test_counted_list_split() {
+        // The count in the first template is the total, later ones may repeat it.
+        let bytes = hex!("A0 08 02 01 03 5F1A 02 4142 A0 04 5F1A 01 43 A0 07 02 01 03 5F1A 01 44");
+        let templates: Vec<_> = TlvIter::new(&bytes).map(Result::unwrap).collect();
+        let entries = counted_list(&templates, 0x5F1A).unwrap();
+        let values: Vec<_> = entries.iter().map(|entry| entry.value).collect();
+        assert_eq!(values, [&b"AB"[..], b"C", b"D"]);
+        assert!(matches!(
+            counted_list(&templates[..2], 0x5F1A),
+            Err(Error::CountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            counted_list(&templates[1..], 0x5F1A),
+            Err(Error::MissingTag(0x02))
+        ));
+    }
+
+    #[test]
+    fn test_parse_long_length() {
+        let mut bytes = hex!("5F2E 82 0100").to_vec();
+        bytes.extend_from_slice(&[0x42; 0x100]);
+        let (tlv, rest) = parse_tlv(&bytes).unwrap();
+        assert!(rest.is_empty());
+        assert!(!tlv.is_constructed());
+        assert_eq!(tlv.value.len(), 0x100);
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        assert!(matches!(parse_tlv(&hex!("5F")), Err(Error::TlvTruncated)));
+        assert!(matches!(
+            parse_tlv(&hex!("04 03 0102")),
+            Err(Error::TlvTruncated)
+        ));
+        assert!(matches!(
+            parse_tlv(&hex!("04 82 01")),
+            Err(Error::TlvTruncated)
+        ));
+    }
+}
@@ -1,6 +1,16 @@
+//! Reading ICAO 9303 compliant eMRTDs over NFC.
+//!
+//! The API is synchronous: [`nfc::NfcReader`] implementations block on the transport and
+//! [`emrtd::Emrtd`] methods block until the chip has responded. Async callers should run
+//! a session on a blocking thread.
+
 pub mod asn1;
 pub mod crypto;
+pub mod cvc;
 pub mod emrtd;
 pub mod iso7816;
+pub mod lds;
 pub mod nfc;
+#[cfg(feature = "passive-auth")]
+pub mod passive_authentication;
 pub mod utils;
@@ -0,0 +1,99 @@
+//! ISO 14443-4 Answer To Select.
+//!
+//! See ISO/IEC 14443-4 section 5.2.
+
+/// Frame size for each frame size integer (FSCI / FSDI).
+///
+/// Values 9 to C were added in ISO/IEC 14443-4:2016, higher values are RFU.
+const FRAME_SIZES: [usize; 13] = [16, 24, 32, 40, 48, 64, 96, 128, 256, 512, 1024, 2048, 4096];
+
+/// Parsed Answer To Select of a type A card.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Ats {
+    /// Card frame size integer.
+    pub fsci: u8,
+
+    /// Interface byte TA(1) with the supported bit rates.
+    pub ta: Option<u8>,
+
+    /// Interface byte TB(1) with the frame waiting time (FWI) and start-up frame guard
+    /// time (SFGI) integers.
+    pub tb: Option<u8>,
+
+    /// Interface byte TC(1) with NAD and CID support.
+    pub tc: Option<u8>,
+
+    pub historical_bytes: Vec<u8>,
+}
+
+impl Ats {
+    /// Parse an ATS starting with the length byte TL.
+    ///
+    /// Any trailing bytes beyond TL, such as a CRC, are ignored. Returns `None` if the
+    /// ATS is malformed.
+    pub fn parse(ats: &[u8]) -> Option<Self> {
+        let (&length, _) = ats.split_first()?;
+        let ats = ats.get(1..length as usize)?;
+
+        // A missing format byte T0 means default values.
+        let Some((&t0, mut rest)) = ats.split_first() else {
+            return Some(Self {
+                fsci: 2,
+                ..Self::default()
+            });
+        };
+        let mut interface_byte = |present: bool| -> Option<Option<u8>> {
+            if !present {
+                return Some(None);
+            }
+            let (&byte, tail) = rest.split_first()?;
+            rest = tail;
+            Some(Some(byte))
+        };
+        let ta = interface_byte(t0 & 0x10 != 0)?;
+        let tb = interface_byte(t0 & 0x20 != 0)?;
+        let tc = interface_byte(t0 & 0x40 != 0)?;
+        Some(Self {
+            fsci: t0 & 0x0F,
+            ta,
+            tb,
+            tc,
+            historical_bytes: rest.to_vec(),
+        })
+    }
+
+    /// Maximum frame size the card can receive, including PCB and CRC.
+    pub fn fsc(&self) -> usize {
+        frame_size(self.fsci)
+    }
+}
+
+/// Frame size in bytes for a frame size integer, RFU values are treated as 256 bytes.
+pub fn frame_size(integer: u8) -> usize {
+    FRAME_SIZES.get(integer as usize).copied().unwrap_or(256)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_ats() {
+        // Typical eMRTD ATS with FSCI 8, TA, TB and TC present.
+        let ats = Ats::parse(&hex!("0A 78 80 81 02 4B 4F 4E 41 14 11")).unwrap();
+        assert_eq!(ats.fsci, 8);
+        assert_eq!(ats.fsc(), 256);
+        assert_eq!(ats.ta, Some(0x80));
+        assert_eq!(ats.tb, Some(0x81));
+        assert_eq!(ats.tc, Some(0x02));
+        assert_eq!(ats.historical_bytes, hex!("4B 4F 4E 41 14"));
+    }
+
+    #[test]
+    fn test_parse_ats_minimal() {
+        assert_eq!(Ats::parse(&hex!("01")).unwrap().fsc(), 32);
+        assert_eq!(Ats::parse(&hex!("02 05")).unwrap().fsc(), 64);
+        assert_eq!(Ats::parse(&hex!("03 75 80")), None);
+        assert_eq!(frame_size(0x0F), 256);
+    }
+}
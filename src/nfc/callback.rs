@@ -0,0 +1,61 @@
+//! Reader backed by a platform APDU transport.
+//!
+//! On mobile platforms the operating system discovers and connects the card, e.g.
+//! Android's `IsoDep`, and only offers a function exchanging raw APDUs. See
+//! `examples/android_jni.rs` for wiring one up through a C ABI.
+
+use {
+    super::{CardType, NfcReader},
+    crate::iso7816::ResponseApdu,
+    anyhow::Result,
+};
+
+/// Exchanges a command APDU for the full response, including the status word.
+pub type Transceive = Box<dyn FnMut(&[u8]) -> Result<Vec<u8>> + Send>;
+
+/// An [`NfcReader`] sending APDUs through a callback.
+///
+/// The card is connected by the platform before the reader is created, so
+/// [`NfcReader::connect`] does nothing and returns `None` for the unknown card type.
+pub struct CallbackReader {
+    transceive: Transceive,
+}
+
+impl CallbackReader {
+    pub fn new(transceive: Transceive) -> Self {
+        Self { transceive }
+    }
+}
+
+impl NfcReader for CallbackReader {
+    fn connect(&mut self) -> Result<Option<CardType>> {
+        Ok(None)
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
+        Ok(ResponseApdu::parse(&(self.transceive)(apdu)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::iso7816::StatusWord, hex_literal::hex};
+
+    #[test]
+    fn test_callback_reader() {
+        let mut reader = CallbackReader::new(Box::new(|apdu| {
+            assert_eq!(apdu, hex!("00 84 0000 08"));
+            Ok(hex!("4608F91988702212 9000").to_vec())
+        }));
+        let response = reader.send_apdu(&hex!("00 84 0000 08")).unwrap();
+        assert_eq!(response.status, StatusWord::SUCCESS);
+        assert_eq!(response.data, hex!("4608F91988702212"));
+
+        let mut reader = CallbackReader::new(Box::new(|_| Ok(vec![0x90])));
+        assert!(reader.send_apdu(&hex!("00 84 0000 08")).is_err());
+    }
+}
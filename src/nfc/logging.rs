@@ -0,0 +1,85 @@
+//! Permissive [`NfcReader`] for dry runs without a card.
+
+use {
+    super::{CardType, NfcReader},
+    crate::iso7816::{ResponseApdu, StatusWord},
+    anyhow::Result,
+    std::sync::{Arc, Mutex},
+};
+
+/// Command APDUs sent so far, shared with the reader.
+pub type CommandLog = Arc<Mutex<Vec<Vec<u8>>>>;
+
+/// Accepts any APDU and answers with a fixed response, recording every command.
+///
+/// Unlike a scripted reader nothing is checked, so a flow runs as far as the canned
+/// response allows. Obtain the log with [`LoggingReader::commands`] before boxing the
+/// reader for [`Emrtd`].
+///
+/// [`Emrtd`]: crate::emrtd::Emrtd
+#[derive(Debug)]
+pub struct LoggingReader {
+    status: StatusWord,
+    data: Vec<u8>,
+    commands: CommandLog,
+}
+
+impl Default for LoggingReader {
+    fn default() -> Self {
+        Self::new(StatusWord::SUCCESS, &[])
+    }
+}
+
+impl LoggingReader {
+    /// Answer every command with `status` and `data`.
+    pub fn new(status: StatusWord, data: &[u8]) -> Self {
+        Self {
+            status,
+            data: data.to_vec(),
+            commands: CommandLog::default(),
+        }
+    }
+
+    pub fn commands(&self) -> CommandLog {
+        self.commands.clone()
+    }
+}
+
+impl NfcReader for LoggingReader {
+    fn connect(&mut self) -> Result<Option<CardType>> {
+        Ok(None)
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
+        self.commands.lock().unwrap().push(apdu.to_vec());
+        Ok(ResponseApdu {
+            data: self.data.clone(),
+            status: self.status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::emrtd::Emrtd, hex_literal::hex};
+
+    #[test]
+    fn test_logging_reader() {
+        let nfc = LoggingReader::new(StatusWord::SUCCESS, &hex!("4608F91988702212"));
+        let commands = nfc.commands();
+        let mut card = Emrtd::new(Box::new(nfc));
+        assert_eq!(card.get_challenge().unwrap(), hex!("4608F91988702212"));
+        card.send_apdu(&hex!("00 B0 9E00 04")).unwrap();
+        assert_eq!(
+            *commands.lock().unwrap(),
+            vec![
+                hex!("00 84 0000 08").to_vec(),
+                hex!("00 B0 9E00 04").to_vec()
+            ]
+        );
+    }
+}
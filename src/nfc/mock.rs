@@ -0,0 +1,47 @@
+//! Scripted [`NfcReader`] for unit tests.
+
+use {
+    super::{CardType, NfcReader},
+    crate::iso7816::{ResponseApdu, StatusWord},
+    anyhow::{anyhow, ensure, Result},
+    std::collections::VecDeque,
+};
+
+/// Replays a fixed sequence of APDU exchanges.
+#[derive(Debug, Default)]
+pub struct MockReader {
+    exchanges: VecDeque<(Vec<u8>, StatusWord, Vec<u8>)>,
+}
+
+impl MockReader {
+    /// Expect `apdu` next and reply with `status` and `data`.
+    pub fn expect(mut self, apdu: &[u8], status: StatusWord, data: &[u8]) -> Self {
+        self.exchanges
+            .push_back((apdu.to_vec(), status, data.to_vec()));
+        self
+    }
+}
+
+impl NfcReader for MockReader {
+    fn connect(&mut self) -> Result<Option<CardType>> {
+        Ok(None)
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
+        let (expected, status, data) = self
+            .exchanges
+            .pop_front()
+            .ok_or_else(|| anyhow!("Unexpected APDU {}", hex::encode(apdu)))?;
+        ensure!(
+            apdu == expected,
+            "Expected APDU {}, got {}",
+            hex::encode(expected),
+            hex::encode(apdu)
+        );
+        Ok(ResponseApdu { data, status })
+    }
+}
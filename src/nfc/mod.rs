@@ -1,6 +1,39 @@
+mod ats;
+mod callback;
+mod logging;
+#[cfg(test)]
+pub mod mock;
 mod proxmark3;
+pub mod transcript;
 
-use {crate::iso7816::StatusWord, anyhow::Result};
+#[cfg(feature = "proxmark3")]
+pub use self::proxmark3::Proxmark3;
+pub use self::{
+    ats::{frame_size, Ats},
+    callback::{CallbackReader, Transceive},
+    logging::{CommandLog, LoggingReader},
+};
+use {
+    crate::iso7816::ResponseApdu,
+    anyhow::{bail, Result},
+    std::{
+        fmt::{self, Display, Formatter},
+        str::FromStr,
+    },
+};
+
+/// Transmission protocol between reader and card, see ISO 7816-3.
+///
+/// Contactless ISO 14443-4 transmission is block oriented like T=1. Whether a chip answers
+/// `61XX` and expects GET RESPONSE depends on the protocol it believes is in use.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Protocol {
+    /// Character oriented, response data may require GET RESPONSE.
+    T0,
+
+    /// Block oriented, response data is returned directly.
+    T1,
+}
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
 pub enum CardType {
@@ -37,13 +70,73 @@ pub struct CardTypeB {
     cid: u8,
 }
 
-pub trait NfcReader {
+impl CardTypeA {
+    /// The parsed Answer To Select, if the card sent a valid one.
+    pub fn ats(&self) -> Option<Ats> {
+        Ats::parse(&self.ats)
+    }
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::T0 => write!(f, "T=0"),
+            Self::T1 => write!(f, "T=1"),
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "T0" | "T=0" => Ok(Self::T0),
+            "T1" | "T=1" => Ok(Self::T1),
+            _ => Err(format!("Unknown protocol {s}")),
+        }
+    }
+}
+
+/// A reader driving a single card.
+///
+/// Readers are [`Send`] but not [`Sync`]: each reader belongs to one [`Emrtd`] at a time,
+/// which may run on any thread. Independent readers can be driven concurrently from
+/// separate threads, see [`list_readers`] and [`connect_reader_by_name`].
+///
+/// [`Emrtd`]: crate::emrtd::Emrtd
+pub trait NfcReader: Send {
     // TODO: Should return card info, and reader/card capabilities like extended length.
     fn connect(&mut self) -> Result<Option<CardType>>;
+
+    /// Connect using `protocol`, or the reader's default if `None`.
+    ///
+    /// Fails if the reader can not use the requested protocol.
+    fn connect_with(&mut self, protocol: Option<Protocol>) -> Result<Option<CardType>> {
+        match protocol {
+            None => self.connect(),
+            Some(protocol) => bail!("Protocol {protocol} not supported by this reader"),
+        }
+    }
+
     fn disconnect(&mut self) -> Result<()>;
-    fn send_apdu(&mut self, apdu: &[u8]) -> Result<(StatusWord, Vec<u8>)>;
+    fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu>;
 }
 
+/// Connect to the first available reader.
+#[cfg(feature = "proxmark3")]
 pub fn connect_reader() -> Result<Box<dyn NfcReader>> {
     Ok(Box::new(proxmark3::Proxmark3::new()?))
 }
+
+/// Names of all connected readers, for use with [`connect_reader_by_name`].
+#[cfg(feature = "proxmark3")]
+pub fn list_readers() -> Result<Vec<String>> {
+    proxmark3::Proxmark3::list()
+}
+
+/// Connect to the reader with the given name, as returned by [`list_readers`].
+#[cfg(feature = "proxmark3")]
+pub fn connect_reader_by_name(name: &str) -> Result<Box<dyn NfcReader>> {
+    Ok(Box::new(proxmark3::Proxmark3::open(name)?))
+}
@@ -7,8 +7,8 @@ mod usb; // TODO: BLE
 
 use {
     self::usb::UsbConnection,
-    super::{CardType, CardTypeA, CardTypeB, NfcReader},
-    crate::iso7816::StatusWord,
+    super::{frame_size, CardType, CardTypeA, CardTypeB, NfcReader, Protocol},
+    crate::iso7816::ResponseApdu,
     anyhow::{bail, ensure, Result},
     bytes::{Buf, BufMut, BytesMut},
     crc::{Crc, CRC_16_ISO_IEC_14443_3_A},
@@ -31,6 +31,19 @@ pub enum Command {
     Hf14bReader = 0x0305,
 }
 
+// Flags for `Hf14aReader`.
+// See https://github.com/RfidResearchGroup/proxmark3/blob/55ef252a5d0d590026a4959a4c1b7a6028d1ad13/include/mifare.h#L88
+const ISO14A_CONNECT: u64 = 1 << 0;
+const ISO14A_NO_DISCONNECT: u64 = 1 << 1;
+const ISO14A_APDU: u64 = 1 << 2;
+const ISO14A_RAW: u64 = 1 << 3;
+const ISO14A_APPEND_CRC: u64 = 1 << 5;
+const ISO14A_NO_RATS: u64 = 1 << 9;
+const ISO14A_SEND_CHAINING: u64 = 1 << 10;
+
+/// Frame size integer the firmware sends in RATS, FSD = 256 bytes.
+const DEFAULT_FSDI: u8 = 8;
+
 #[repr(i16)]
 pub enum Status {
     Success = 0,
@@ -44,10 +57,16 @@ pub struct Proxmark3 {
     crc: bool,
     trace: bool,
     current_card: Option<CardType>,
+
+    /// FSDI to send in RATS instead of the firmware default.
+    rats_fsdi: Option<u8>,
+
+    /// Maximum frame size for the connected card, `min(FSC, FSD)`.
+    max_frame_size: usize,
 }
 
 /// Connection to a Proxmark3 UART interface.
-trait Connection {
+trait Connection: Send {
     fn read(&mut self, buffer: &mut [u8]) -> Result<()>;
     fn write(&mut self, data: &[u8]) -> Result<()>;
     fn close(self) -> Result<()>;
@@ -57,9 +76,23 @@ impl Proxmark3 {
     pub fn new() -> Result<Self> {
         // Connect to Proxmark3
         let connection = UsbConnection::new()?;
-        let mut proxmark3 = Proxmark3::from_connection(Box::new(connection));
-        proxmark3.test_connection()?;
-        Ok(proxmark3)
+        Self::from_connection(Box::new(connection)).tested()
+    }
+
+    /// Names of all connected Proxmark3 devices, by USB bus and address.
+    pub fn list() -> Result<Vec<String>> {
+        UsbConnection::list()
+    }
+
+    /// Connect to the Proxmark3 with the given name, as returned by [`Self::list`].
+    pub fn open(name: &str) -> Result<Self> {
+        let connection = UsbConnection::open(name)?;
+        Self::from_connection(Box::new(connection)).tested()
+    }
+
+    fn tested(mut self) -> Result<Self> {
+        self.test_connection()?;
+        Ok(self)
     }
 
     pub fn close(mut self) -> Result<()> {
@@ -74,9 +107,19 @@ impl Proxmark3 {
             crc: true,
             trace: false,
             current_card: None,
+            rats_fsdi: None,
+            max_frame_size: frame_size(DEFAULT_FSDI),
         }
     }
 
+    /// Send RATS with the given frame size integer instead of the firmware default.
+    ///
+    /// Some cards reject the default 256 byte reader frame size. Takes effect on the next
+    /// connect.
+    pub fn set_rats_fsdi(&mut self, fsdi: Option<u8>) {
+        self.rats_fsdi = fsdi;
+    }
+
     fn test_connection(&mut self) -> Result<()> {
         // TODO: Flush device read buffer.
 
@@ -121,8 +164,11 @@ impl Proxmark3 {
     fn connect_type_a(&mut self) -> Result<Option<CardTypeA>> {
         // Connect to ISO 14443-A card as reader, keeping the field on.
         // hf 14a reader -k
-        // https://github.com/RfidResearchGroup/proxmark3/blob/55ef252a5d0d590026a4959a4c1b7a6028d1ad13/include/mifare.h#L88
-        self.send_command_mix(Command::Hf14aReader, 3, 0, 0, &[])?; // 3 = CONNECT | NO_DISCONNECT
+        let mut flags = ISO14A_CONNECT | ISO14A_NO_DISCONNECT;
+        if self.rats_fsdi.is_some() {
+            flags |= ISO14A_NO_RATS;
+        }
+        self.send_command_mix(Command::Hf14aReader, flags, 0, 0, &[])?;
         let (status, cmd, response) = self.receive_response()?;
         ensure!(status == Status::Success as i16);
         ensure!(cmd == Command::Ack as u16);
@@ -136,8 +182,8 @@ impl Proxmark3 {
             return Ok(None);
         }
         ensure!(response.len() == 271);
-        ensure!(arg0 == 1);
-        // TODO: arg0 == 2 means no ATS included and will have to be requested separately.
+        // arg0 == 2 means no ATS included and will have to be requested separately.
+        ensure!(arg0 == 1 || arg0 == 2);
         let (uid, mut response) = response.split_at(10);
         let uid_len = response.get_u8();
         let uid = &uid[..uid_len as usize];
@@ -145,17 +191,60 @@ impl Proxmark3 {
         let sak = response.get_u8();
         let ats_len = response.get_u8();
         let (ats, mut _response) = response.split_at(ats_len as usize);
+        let fsdi = self.rats_fsdi.unwrap_or(DEFAULT_FSDI);
+        let ats = if arg0 == 2 || self.rats_fsdi.is_some() {
+            self.rats(fsdi)?
+        } else {
+            ats.to_vec()
+        };
 
         let card = CardTypeA {
             uid: uid.to_vec(),
             atqa,
             sak,
-            ats: ats.to_vec(),
+            ats,
         };
+
+        // Honor the card's maximum frame size, see ISO 14443-4 section 5.2.3.
+        let fsc = card.ats().map_or(frame_size(2), |ats| ats.fsc());
+        self.max_frame_size = fsc.min(frame_size(fsdi));
+        if self.trace {
+            eprintln!("Maximum frame size: {} bytes", self.max_frame_size);
+        }
+
         self.current_card = Some(CardType::A(card.clone()));
         Ok(Some(card))
     }
 
+    /// Send Request for Answer To Select and return the ATS.
+    ///
+    /// See ISO 14443-4 section 5.1.
+    fn rats(&mut self, fsdi: u8) -> Result<Vec<u8>> {
+        ensure!(fsdi <= 0x0F);
+        // Parameter byte: FSDI and CID 0.
+        let rats = [0xE0, fsdi << 4];
+        self.send_command_mix(
+            Command::Hf14aReader,
+            ISO14A_RAW | ISO14A_APPEND_CRC | ISO14A_NO_DISCONNECT,
+            rats.len() as u64,
+            0,
+            &rats,
+        )?;
+        let (status, cmd, response) = self.receive_response()?;
+        ensure!(status == Status::Success as i16);
+        ensure!(cmd == Command::Ack as u16);
+        let mut response = &response[..];
+        ensure!(response.len() >= 24);
+        let length = response.get_u64_le();
+        let _arg1 = response.get_u64_le();
+        let _arg2 = response.get_u64_le();
+        ensure!(
+            length > 0 && length as usize <= response.len(),
+            "No ATS received"
+        );
+        Ok(response[..length as usize].to_vec())
+    }
+
     fn connect_type_b(&mut self) -> Result<Option<CardTypeB>> {
         // Switch off field.
         self.hf14b(0x0002, &[])?;
@@ -191,8 +280,12 @@ impl Proxmark3 {
         // TODO: Support extended length
 
         // hf 14a apdu -k -d <apdu>
-        // 6 = SEND_APDU | NO_DISCONNECT
-        self.send_command_mix(Command::Hf14aReader, 6, apdu.len() as u64, 0, apdu)?;
+        // Chain I-blocks if the APDU plus PCB and CRC does not fit in a frame.
+        let mut flags = ISO14A_APDU | ISO14A_NO_DISCONNECT;
+        if apdu.len() + 3 > self.max_frame_size {
+            flags |= ISO14A_SEND_CHAINING;
+        }
+        self.send_command_mix(Command::Hf14aReader, flags, apdu.len() as u64, 0, apdu)?;
         let (status, cmd, response) = self.receive_response()?;
         ensure!(status == Status::Success as i16);
         ensure!(cmd == Command::Ack as u16);
@@ -340,25 +433,30 @@ impl NfcReader for Proxmark3 {
         Ok(None)
     }
 
+    fn connect_with(&mut self, protocol: Option<Protocol>) -> Result<Option<CardType>> {
+        // ISO 14443-4 block transmission is the only option, and behaves as T=1.
+        match protocol {
+            None | Some(Protocol::T1) => self.connect(),
+            Some(Protocol::T0) => bail!("Protocol T=0 not supported by Proxmark3"),
+        }
+    }
+
     fn disconnect(&mut self) -> Result<()> {
         // Switch field off
         if self.trace {
             eprintln!("Switching field off:");
         }
-        self.send_command_mix(Command::Hf14aReader, 1, 0, 0, &[])?;
+        self.send_command_mix(Command::Hf14aReader, ISO14A_CONNECT, 0, 0, &[])?;
         let _response = self.receive_response()?;
         Ok(())
     }
 
-    fn send_apdu(&mut self, apdu: &[u8]) -> Result<(StatusWord, Vec<u8>)> {
+    fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
         let data = match self.current_card {
             Some(CardType::A(_)) => self.hf14a_send(apdu)?,
             Some(CardType::B(_)) => self.hf14b_send(apdu)?,
             None => bail!("No card connected"),
         };
-        ensure!(data.len() >= 2);
-        let (data, status) = data.split_at(data.len() - 2);
-        let status = u16::from_be_bytes([status[0], status[1]]).into();
-        Ok((status, data.to_vec()))
+        Ok(ResponseApdu::parse(&data)?)
     }
 }
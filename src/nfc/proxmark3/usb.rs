@@ -1,7 +1,7 @@
 use {
     super::Connection,
     anyhow::{anyhow, Result},
-    rusb::{DeviceHandle, GlobalContext, UsbContext},
+    rusb::{Device, DeviceHandle, GlobalContext, UsbContext},
     std::time::Duration,
 };
 
@@ -19,16 +19,25 @@ pub struct UsbConnection {
 impl UsbConnection {
     pub fn new() -> Result<Self> {
         // Get device handle
-        let devices = rusb::devices()?;
-        for device in devices.iter() {
-            let device_desc = device.device_descriptor()?;
-            if device_desc.vendor_id() == PROXMARK3_VENDOR_ID
-                && device_desc.product_id() == PROXMARK3_PRODUCT_ID
-            {
-                return Self::from_device(device);
-            }
-        }
-        panic!("Proxmark3 device not found");
+        let device = devices()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Proxmark3 device not found"))?;
+        Self::from_device(device)
+    }
+
+    /// Names of all connected Proxmark3 devices.
+    pub fn list() -> Result<Vec<String>> {
+        Ok(devices()?.iter().map(device_name).collect())
+    }
+
+    /// Open the Proxmark3 with the given name, see [`Self::list`].
+    pub fn open(name: &str) -> Result<Self> {
+        let device = devices()?
+            .into_iter()
+            .find(|device| device_name(device) == name)
+            .ok_or_else(|| anyhow!("Proxmark3 device {name} not found"))?;
+        Self::from_device(device)
     }
 
     pub fn from_device(device: rusb::Device<GlobalContext>) -> Result<Self> {
@@ -108,6 +117,25 @@ impl Connection for UsbConnection {
     }
 }
 
+/// All connected Proxmark3 devices, in USB enumeration order.
+fn devices() -> Result<Vec<Device<GlobalContext>>> {
+    let mut result = Vec::new();
+    for device in rusb::devices()?.iter() {
+        let device_desc = device.device_descriptor()?;
+        if device_desc.vendor_id() == PROXMARK3_VENDOR_ID
+            && device_desc.product_id() == PROXMARK3_PRODUCT_ID
+        {
+            result.push(device);
+        }
+    }
+    Ok(result)
+}
+
+/// Stable name of a device while it stays plugged in, e.g. `usb:001:004`.
+fn device_name(device: &Device<GlobalContext>) -> String {
+    format!("usb:{:03}:{:03}", device.bus_number(), device.address())
+}
+
 fn get_endpoints<T: UsbContext>(device: &rusb::Device<T>) -> Result<(u8, u8)> {
     let config_desc = device.active_config_descriptor()?;
     let mut bulk_in_endpoint = None;
@@ -0,0 +1,149 @@
+//! Recording and replaying APDU transcripts.
+//!
+//! A transcript is a text file with one exchange per pair of lines: the command APDU
+//! prefixed with `>` and the response data and status word prefixed with `<`, both in hex.
+//! Empty lines and lines starting with `#` are ignored.
+//!
+//! ```text
+//! # GET CHALLENGE
+//! > 0084000008
+//! < 4608F919887022129000
+//! ```
+
+use {
+    super::{CardType, NfcReader, Protocol},
+    crate::iso7816::{ResponseApdu, StatusWord},
+    anyhow::{anyhow, bail, ensure, Context, Result},
+    std::{collections::VecDeque, io::Write},
+};
+
+/// Wraps a reader and writes all exchanges to a transcript.
+pub struct Recorder {
+    inner: Box<dyn NfcReader>,
+    output: Box<dyn Write + Send>,
+}
+
+/// Replays a transcript, failing on any command that deviates from it.
+#[derive(Clone, Debug, Default)]
+pub struct Replay {
+    exchanges: VecDeque<(Vec<u8>, StatusWord, Vec<u8>)>,
+}
+
+impl Recorder {
+    pub fn new(inner: Box<dyn NfcReader>, output: Box<dyn Write + Send>) -> Self {
+        Self { inner, output }
+    }
+}
+
+impl NfcReader for Recorder {
+    fn connect(&mut self) -> Result<Option<CardType>> {
+        let card = self.inner.connect()?;
+        writeln!(self.output, "# Connected: {:?}", card)?;
+        Ok(card)
+    }
+
+    fn connect_with(&mut self, protocol: Option<Protocol>) -> Result<Option<CardType>> {
+        let card = self.inner.connect_with(protocol)?;
+        writeln!(self.output, "# Connected: {:?}", card)?;
+        Ok(card)
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        writeln!(self.output, "# Disconnected")?;
+        self.inner.disconnect()
+    }
+
+    fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
+        let response = self.inner.send_apdu(apdu)?;
+        writeln!(self.output, "> {}", hex::encode_upper(apdu))?;
+        writeln!(
+            self.output,
+            "< {}{:04X}",
+            hex::encode_upper(&response.data),
+            u16::from(response.status)
+        )?;
+        self.output.flush()?;
+        Ok(response)
+    }
+}
+
+impl Replay {
+    pub fn parse(transcript: &str) -> Result<Self> {
+        let mut exchanges = VecDeque::new();
+        let mut command = None;
+        for (number, line) in transcript.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let context = || format!("Transcript line {}", number + 1);
+            if let Some(apdu) = line.strip_prefix('>') {
+                ensure!(command.is_none(), "{}: Missing response", context());
+                command = Some(hex::decode(apdu.trim()).with_context(context)?);
+            } else if let Some(response) = line.strip_prefix('<') {
+                let apdu = command
+                    .take()
+                    .ok_or_else(|| anyhow!("{}: Response without command", context()))?;
+                let mut data = hex::decode(response.trim()).with_context(context)?;
+                ensure!(data.len() >= 2, "{}: Missing status word", context());
+                let status = data.split_off(data.len() - 2);
+                let status = u16::from_be_bytes([status[0], status[1]]).into();
+                exchanges.push_back((apdu, status, data));
+            } else {
+                bail!("{}: Expected '>' or '<'", context());
+            }
+        }
+        ensure!(command.is_none(), "Transcript ends without response");
+        Ok(Self { exchanges })
+    }
+
+    /// True if all exchanges have been replayed.
+    pub fn is_done(&self) -> bool {
+        self.exchanges.is_empty()
+    }
+}
+
+impl NfcReader for Replay {
+    fn connect(&mut self) -> Result<Option<CardType>> {
+        Ok(None)
+    }
+
+    fn disconnect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn send_apdu(&mut self, apdu: &[u8]) -> Result<ResponseApdu> {
+        let (expected, status, data) = self
+            .exchanges
+            .pop_front()
+            .ok_or_else(|| anyhow!("Transcript ended, got {}", hex::encode_upper(apdu)))?;
+        ensure!(
+            apdu == expected,
+            "Transcript expected {}, got {}",
+            hex::encode_upper(expected),
+            hex::encode_upper(apdu)
+        );
+        Ok(ResponseApdu { data, status })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, hex_literal::hex};
+
+    #[test]
+    fn test_parse_replay() {
+        let mut replay = Replay::parse(
+            "# GET CHALLENGE
+            > 0084000008
+            < 4608F919887022129000",
+        )
+        .unwrap();
+        let response = replay.send_apdu(&hex!("0084000008")).unwrap();
+        assert_eq!(response.status, StatusWord::SUCCESS);
+        assert_eq!(response.data, hex!("4608F91988702212"));
+        assert!(replay.is_done());
+        assert!(Replay::parse("> 00").is_err());
+        assert!(Replay::parse("< 9000").is_err());
+    }
+}
@@ -0,0 +1,878 @@
+//! Passive Authentication of the document data.
+//!
+//! See ICAO 9303-11 section 5.1 and ICAO 9303-12 for the certificate profiles.
+
+use {
+    crate::{
+        asn1::{
+            public_key::{
+                PubkeyAlgorithmIdentifier, SubjectPublicKeyInfo, ID_EC_PUBLIC_KEY,
+                ID_RSA_ENCRYPTION,
+            },
+            AnyAlgorithmIdentifier, DigestAlgorithmIdentifier, DocumentTypeListSyntax, EfSod,
+            LdsSecurityObject, ID_SHA1, ID_SHA224, ID_SHA256, ID_SHA384, ID_SHA512,
+        },
+        crypto::{ecdsa_verify, parse_uint, EllipticCurvePoint},
+    },
+    cms::signed_data::SignerInfo,
+    der::{
+        asn1::{BitString, Int, ObjectIdentifier as Oid, OctetString},
+        Decode, Encode, Sequence,
+    },
+    rsa::{pkcs1::DecodeRsaPublicKey, Pkcs1v15Sign, Pss, RsaPublicKey},
+    sha1::Sha1,
+    sha2::{Sha224, Sha256, Sha384, Sha512, Sha512_224, Sha512_256},
+    spki::AlgorithmIdentifierOwned,
+    std::{mem, time::SystemTime},
+    thiserror::Error,
+    x509_cert::Certificate,
+};
+
+pub const ID_CE_KEY_USAGE: Oid = Oid::new_unwrap("2.5.29.15");
+pub const ID_CE_EXT_KEY_USAGE: Oid = Oid::new_unwrap("2.5.29.37");
+
+// CMS signed attributes, see RFC 5652 section 11.
+pub const ID_CONTENT_TYPE: Oid = Oid::new_unwrap("1.2.840.113549.1.9.3");
+pub const ID_MESSAGE_DIGEST: Oid = Oid::new_unwrap("1.2.840.113549.1.9.4");
+
+// RSA signatures, see RFC 8017 appendix A.2.
+pub const ID_RSASSA_PSS: Oid = Oid::new_unwrap("1.2.840.113549.1.1.10");
+pub const ID_MGF1: Oid = Oid::new_unwrap("1.2.840.113549.1.1.8");
+pub const ID_SHA1_WITH_RSA: Oid = Oid::new_unwrap("1.2.840.113549.1.1.5");
+pub const ID_SHA224_WITH_RSA: Oid = Oid::new_unwrap("1.2.840.113549.1.1.14");
+pub const ID_SHA256_WITH_RSA: Oid = Oid::new_unwrap("1.2.840.113549.1.1.11");
+pub const ID_SHA384_WITH_RSA: Oid = Oid::new_unwrap("1.2.840.113549.1.1.12");
+pub const ID_SHA512_WITH_RSA: Oid = Oid::new_unwrap("1.2.840.113549.1.1.13");
+
+// ECDSA signatures, see RFC 5758 section 3.2 and RFC 3279 section 2.2.3.
+pub const ID_ECDSA_WITH_SHA1: Oid = Oid::new_unwrap("1.2.840.10045.4.1");
+pub const ID_ECDSA_WITH_SHA224: Oid = Oid::new_unwrap("1.2.840.10045.4.3.1");
+pub const ID_ECDSA_WITH_SHA256: Oid = Oid::new_unwrap("1.2.840.10045.4.3.2");
+pub const ID_ECDSA_WITH_SHA384: Oid = Oid::new_unwrap("1.2.840.10045.4.3.3");
+pub const ID_ECDSA_WITH_SHA512: Oid = Oid::new_unwrap("1.2.840.10045.4.3.4");
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Document Signer certificate missing from EF.SOD.")]
+    MissingDocumentSigner,
+
+    #[error("Signer info missing from EF.SOD.")]
+    MissingSignerInfo,
+
+    #[error("Certificate is not valid yet.")]
+    CertificateNotYetValid,
+
+    #[error("Certificate expired.")]
+    CertificateExpired,
+
+    #[error("Certificate constraint violated: {0}")]
+    ConstraintViolation(&'static str),
+
+    #[error("Signed attribute missing: {0}")]
+    MissingSignedAttribute(Oid),
+
+    #[error("Signed content type does not match the encapsulated content.")]
+    ContentTypeMismatch,
+
+    #[error("Signed message digest does not match the encapsulated content.")]
+    MessageDigestMismatch,
+
+    #[error("Malformed LDS Security Object: {0}")]
+    MalformedSecurityObject(String),
+
+    #[error("Unsupported digest algorithm {0}.")]
+    UnsupportedDigestAlgorithm(Oid),
+
+    #[error("Unsupported signature algorithm {0}.")]
+    UnsupportedSignatureAlgorithm(Oid),
+
+    #[error("Unsupported Document Signer public key: {0}")]
+    UnsupportedPublicKey(String),
+
+    #[error("Document Signer signature is invalid.")]
+    InvalidSignature,
+
+    #[error("Document Signer certificate is not issued by a trusted CSCA.")]
+    UntrustedDocumentSigner,
+
+    #[error("Invalid DER: {0}")]
+    InvalidDer(#[from] der::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Trusted Country Signing CA certificates, e.g. from a CSCA Master List.
+///
+/// See ICAO 9303-12 section 5.1.
+#[derive(Clone, Debug, Default)]
+pub struct TrustStore {
+    certificates: Vec<Certificate>,
+}
+
+/// ECDSA signature value, see RFC 3279 section 2.2.3.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct EcdsaSigValue {
+    r: Int,
+    s: Int,
+}
+
+impl TrustStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, certificate: Certificate) {
+        self.certificates.push(certificate);
+    }
+
+    /// Add a DER encoded CSCA certificate.
+    pub fn add_der(&mut self, der: &[u8]) -> Result<()> {
+        self.add(Certificate::from_der(der)?);
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.certificates.is_empty()
+    }
+
+    pub fn certificates(&self) -> &[Certificate] {
+        &self.certificates
+    }
+}
+
+impl Error {
+    /// True if the signature could not be checked, as opposed to being invalid.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            Self::UnsupportedDigestAlgorithm(_)
+                | Self::UnsupportedSignatureAlgorithm(_)
+                | Self::UnsupportedPublicKey(_)
+        )
+    }
+}
+
+/// The bytes the Document Signer signature is computed over.
+///
+/// If signed attributes are present, the signature is over their DER encoding with
+/// the `SET OF` tag, not over the encapsulated content. See RFC 5652 section 5.4.
+pub fn signature_input(sod: &EfSod) -> Result<Vec<u8>> {
+    let signer_info = sod.signer_info().ok_or(Error::MissingSignerInfo)?;
+    signer_info_signature_input(signer_info, sod.econtent()?.as_bytes())
+}
+
+/// Check that the signed attributes commit to the encapsulated content.
+///
+/// The `contentType` attribute must match the eContentType and the `messageDigest`
+/// attribute must equal the hash of the eContent. Without signed attributes the signature
+/// is directly over the eContent and there is nothing to check.
+///
+/// The hash is the SignerInfo `digestAlgorithm`, which may differ from the LDS Security
+/// Object hash algorithm used for the data groups, e.g. SHA-256 here and SHA-384 there.
+///
+/// See RFC 5652 section 5.3 and 11.
+pub fn check_signed_attributes(sod: &EfSod) -> Result<()> {
+    check_signer_info_attributes(
+        sod.signer_info().ok_or(Error::MissingSignerInfo)?,
+        &sod.encapsulated_content().econtent_type,
+        sod.econtent()?.as_bytes(),
+    )
+}
+
+/// Verify the Document Signer signature with the public key of the certificate in EF.SOD.
+///
+/// Supports RSA PKCS #1 v1.5, RSASSA-PSS and ECDSA with explicit domain parameters. ECDSA
+/// keys on named curves are reported as [`Error::UnsupportedPublicKey`]. This does not
+/// check the signed attributes, see [`check_signed_attributes`], nor the certificate.
+///
+/// See ICAO 9303-11 section 5.1 and RFC 5652 section 5.6.
+pub fn verify_signature(sod: &EfSod) -> Result<()> {
+    let certificate = sod
+        .document_signer_certificate()
+        .ok_or(Error::MissingDocumentSigner)?;
+    let signer_info = sod.signer_info().ok_or(Error::MissingSignerInfo)?;
+    let public_key = SubjectPublicKeyInfo::from_der(
+        &certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()?,
+    )?;
+    verify_signer_info_signature(signer_info, &public_key, &signature_input(sod)?)
+}
+
+/// Decode the LDS Security Object from EF.SOD and check it is well-formed.
+///
+/// See [`check_security_object`].
+pub fn security_object(sod: &EfSod) -> Result<LdsSecurityObject> {
+    let lso = sod.lds_security_object()?;
+    check_security_object(&lso)?;
+    Ok(lso)
+}
+
+/// Check the structure of an LDS Security Object before comparing hashes against it.
+///
+/// Version 1 must have LDS version info and version 0 must not. The hash algorithm must be
+/// supported, and there must be at least one hash, at most one per data group 1 to 16, each
+/// of the length the algorithm produces.
+///
+/// See ICAO 9303-10 section 4.6.2.3.
+pub fn check_security_object(lso: &LdsSecurityObject) -> Result<()> {
+    let malformed = |reason: String| Err(Error::MalformedSecurityObject(reason));
+    match (lso.version, &lso.lds_version_info) {
+        (0, None) | (1, Some(_)) => {}
+        (0, Some(_)) => return malformed("version 0 with LDS version info".to_owned()),
+        (1, None) => return malformed("version 1 without LDS version info".to_owned()),
+        (version, _) => return malformed(format!("unsupported version {version}")),
+    }
+    let Some(hash_length) = lso.hash_algorithm.output_size() else {
+        return Err(Error::UnsupportedDigestAlgorithm(lso.hash_algorithm.oid()));
+    };
+    if lso.data_group_hash_values.is_empty() {
+        return malformed("no data group hashes".to_owned());
+    }
+    let mut seen = [false; 17];
+    for entry in &lso.data_group_hash_values {
+        let number = entry.data_group_number;
+        if !(1..=16).contains(&number) {
+            return malformed(format!("invalid data group number {number}"));
+        }
+        if mem::replace(&mut seen[number as usize], true) {
+            return malformed(format!("duplicate hash for DG{number}"));
+        }
+        let length = entry.hash_value.as_bytes().len();
+        if length != hash_length {
+            return malformed(format!(
+                "DG{number} hash has {length} bytes, {} produces {hash_length}",
+                lso.hash_algorithm.name()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn signer_info_signature_input(signer_info: &SignerInfo, econtent: &[u8]) -> Result<Vec<u8>> {
+    match &signer_info.signed_attrs {
+        // Encoding the `SetOfVec` uses the universal `SET OF` tag instead of `[0] IMPLICIT`.
+        Some(signed_attrs) => Ok(signed_attrs.to_der()?),
+        None => Ok(econtent.to_vec()),
+    }
+}
+
+fn signer_info_digest_algorithm(signer_info: &SignerInfo) -> Result<DigestAlgorithmIdentifier> {
+    let digest_algorithm = DigestAlgorithmIdentifier::try_from(AnyAlgorithmIdentifier {
+        algorithm: signer_info.digest_alg.oid,
+        parameters: signer_info.digest_alg.parameters.clone(),
+    })?;
+    if let DigestAlgorithmIdentifier::Unknown(algorithm) = digest_algorithm {
+        return Err(Error::UnsupportedDigestAlgorithm(algorithm.algorithm));
+    }
+    Ok(digest_algorithm)
+}
+
+fn verify_signer_info_signature(
+    signer_info: &SignerInfo,
+    public_key: &SubjectPublicKeyInfo,
+    input: &[u8],
+) -> Result<()> {
+    verify_signature_value(
+        &signer_info.signature_algorithm,
+        &signer_info_digest_algorithm(signer_info)?,
+        public_key,
+        input,
+        signer_info.signature.as_bytes(),
+    )
+}
+
+/// The digest of a certificate signature, implied by the signature algorithm.
+fn certificate_digest_algorithm(
+    algorithm: &AlgorithmIdentifierOwned,
+) -> Result<DigestAlgorithmIdentifier> {
+    let digest = match algorithm.oid {
+        ID_SHA1_WITH_RSA | ID_ECDSA_WITH_SHA1 => ID_SHA1,
+        ID_SHA224_WITH_RSA | ID_ECDSA_WITH_SHA224 => ID_SHA224,
+        ID_SHA256_WITH_RSA | ID_ECDSA_WITH_SHA256 => ID_SHA256,
+        ID_SHA384_WITH_RSA | ID_ECDSA_WITH_SHA384 => ID_SHA384,
+        ID_SHA512_WITH_RSA | ID_ECDSA_WITH_SHA512 => ID_SHA512,
+        ID_RSASSA_PSS => pss_parameters(algorithm)?.0,
+        algorithm => return Err(Error::UnsupportedSignatureAlgorithm(algorithm)),
+    };
+    Ok(DigestAlgorithmIdentifier::try_from(
+        AnyAlgorithmIdentifier {
+            algorithm: digest,
+            parameters: None,
+        },
+    )?)
+}
+
+/// The hash and salt length of RSASSA-PSS parameters.
+///
+/// The same hash must be used for the message and MGF1.
+fn pss_parameters(algorithm: &AlgorithmIdentifierOwned) -> Result<(Oid, usize)> {
+    let params = algorithm
+        .parameters
+        .as_ref()
+        .ok_or(Error::UnsupportedSignatureAlgorithm(ID_RSASSA_PSS))?;
+    let params = params.to_der()?;
+    let params = rsa::pkcs1::RsaPssParams::from_der(&params)?;
+    let mgf1_hash = params
+        .mask_gen
+        .parameters
+        .filter(|_| params.mask_gen.oid == ID_MGF1)
+        .map(|hash| hash.oid);
+    if mgf1_hash != Some(params.hash.oid) {
+        return Err(Error::UnsupportedSignatureAlgorithm(ID_RSASSA_PSS));
+    }
+    Ok((params.hash.oid, params.salt_len.into()))
+}
+
+/// Verify `signature` over `input` hashed with `digest_algorithm`.
+fn verify_signature_value(
+    algorithm: &AlgorithmIdentifierOwned,
+    digest_algorithm: &DigestAlgorithmIdentifier,
+    public_key: &SubjectPublicKeyInfo,
+    input: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    let hash = digest_algorithm.hash_bytes(input);
+    match algorithm.oid {
+        ID_RSA_ENCRYPTION | ID_SHA1_WITH_RSA | ID_SHA224_WITH_RSA | ID_SHA256_WITH_RSA
+        | ID_SHA384_WITH_RSA | ID_SHA512_WITH_RSA => {
+            verify_rsa(public_key, digest_algorithm, None, &hash, signature)
+        }
+        ID_RSASSA_PSS => {
+            // The PSS hash must also be the one used for the signed attributes.
+            let (pss_hash, salt_length) = pss_parameters(algorithm)?;
+            if pss_hash != digest_algorithm.oid() {
+                return Err(Error::UnsupportedSignatureAlgorithm(ID_RSASSA_PSS));
+            }
+            verify_rsa(
+                public_key,
+                digest_algorithm,
+                Some(salt_length),
+                &hash,
+                signature,
+            )
+        }
+        ID_EC_PUBLIC_KEY | ID_ECDSA_WITH_SHA1 | ID_ECDSA_WITH_SHA224 | ID_ECDSA_WITH_SHA256
+        | ID_ECDSA_WITH_SHA384 | ID_ECDSA_WITH_SHA512 => verify_ecdsa(public_key, &hash, signature),
+        algorithm => Err(Error::UnsupportedSignatureAlgorithm(algorithm)),
+    }
+}
+
+/// RSA PKCS #1 v1.5 or, with a salt length, RSASSA-PSS signature verification.
+fn verify_rsa(
+    public_key: &SubjectPublicKeyInfo,
+    digest_algorithm: &DigestAlgorithmIdentifier,
+    pss_salt_length: Option<usize>,
+    hash: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    fn verify<D>(
+        key: &RsaPublicKey,
+        pss_salt_length: Option<usize>,
+        hash: &[u8],
+        signature: &[u8],
+    ) -> rsa::Result<()>
+    where
+        D: 'static + rsa::sha2::Digest + rsa::signature::digest::DynDigest,
+        D: rsa::pkcs8::AssociatedOid + Send + Sync,
+    {
+        match pss_salt_length {
+            None => key.verify(Pkcs1v15Sign::new::<D>(), hash, signature),
+            Some(length) => key.verify(Pss::new_with_salt::<D>(length), hash, signature),
+        }
+    }
+
+    if !matches!(
+        &public_key.algorithm,
+        PubkeyAlgorithmIdentifier::Unknown(AnyAlgorithmIdentifier {
+            algorithm: ID_RSA_ENCRYPTION,
+            ..
+        })
+    ) {
+        return Err(Error::UnsupportedPublicKey("not an RSA key".to_owned()));
+    }
+    let key = RsaPublicKey::from_pkcs1_der(public_key.subject_public_key.raw_bytes())
+        .map_err(|e| Error::UnsupportedPublicKey(e.to_string()))?;
+    let result = match digest_algorithm {
+        DigestAlgorithmIdentifier::Sha1(_) => {
+            verify::<Sha1>(&key, pss_salt_length, hash, signature)
+        }
+        DigestAlgorithmIdentifier::Sha224(_) => {
+            verify::<Sha224>(&key, pss_salt_length, hash, signature)
+        }
+        DigestAlgorithmIdentifier::Sha256(_) => {
+            verify::<Sha256>(&key, pss_salt_length, hash, signature)
+        }
+        DigestAlgorithmIdentifier::Sha384(_) => {
+            verify::<Sha384>(&key, pss_salt_length, hash, signature)
+        }
+        DigestAlgorithmIdentifier::Sha512(_) => {
+            verify::<Sha512>(&key, pss_salt_length, hash, signature)
+        }
+        DigestAlgorithmIdentifier::Sha512_224(_) => {
+            verify::<Sha512_224>(&key, pss_salt_length, hash, signature)
+        }
+        DigestAlgorithmIdentifier::Sha512_256(_) => {
+            verify::<Sha512_256>(&key, pss_salt_length, hash, signature)
+        }
+        DigestAlgorithmIdentifier::Unknown(algorithm) => {
+            return Err(Error::UnsupportedDigestAlgorithm(algorithm.algorithm))
+        }
+    };
+    result.map_err(|_| Error::InvalidSignature)
+}
+
+/// ECDSA signature verification, the signature is an `Ecdsa-Sig-Value`.
+fn verify_ecdsa(public_key: &SubjectPublicKeyInfo, hash: &[u8], signature: &[u8]) -> Result<()> {
+    let unsupported = |e: anyhow::Error| Error::UnsupportedPublicKey(e.to_string());
+    let (curve, point) = EllipticCurvePoint::from_pubkey(public_key).map_err(unsupported)?;
+    let point = curve.pt_from_monty(point).map_err(unsupported)?;
+    let signature = EcdsaSigValue::from_der(signature).map_err(|_| Error::InvalidSignature)?;
+    let (Ok(r), Ok(s)) = (parse_uint(&signature.r), parse_uint(&signature.s)) else {
+        return Err(Error::InvalidSignature);
+    };
+    if !ecdsa_verify(point, hash, (r, s)) {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(())
+}
+
+fn check_signer_info_attributes(
+    signer_info: &SignerInfo,
+    econtent_type: &Oid,
+    econtent: &[u8],
+) -> Result<()> {
+    let Some(signed_attrs) = &signer_info.signed_attrs else {
+        return Ok(());
+    };
+    let attribute_value = |oid: Oid| {
+        signed_attrs
+            .iter()
+            .find(|attr| attr.oid == oid)
+            .and_then(|attr| attr.values.iter().next())
+            .ok_or(Error::MissingSignedAttribute(oid))
+    };
+
+    let content_type: Oid = attribute_value(ID_CONTENT_TYPE)?.decode_as()?;
+    if content_type != *econtent_type {
+        return Err(Error::ContentTypeMismatch);
+    }
+
+    let message_digest: OctetString = attribute_value(ID_MESSAGE_DIGEST)?.decode_as()?;
+    let digest_algorithm = signer_info_digest_algorithm(signer_info)?;
+    if message_digest.as_bytes() != digest_algorithm.hash_bytes(econtent) {
+        return Err(Error::MessageDigestMismatch);
+    }
+    Ok(())
+}
+
+/// Check that the Document Signer in the SOD is authorized to sign this document.
+///
+/// `document_code` is the document code from the MRZ, e.g. `P<`. The certificate must be
+/// valid at `now`, usually `SystemTime::now()`.
+pub fn check_document_signer(sod: &EfSod, document_code: &str, now: SystemTime) -> Result<()> {
+    let certificate = sod
+        .document_signer_certificate()
+        .ok_or(Error::MissingDocumentSigner)?;
+    check_certificate_validity(certificate, now)?;
+    check_document_signer_certificate(certificate, document_code)
+}
+
+/// Check that the Document Signer certificate is issued by a CSCA in `trust_store`.
+///
+/// Candidate CSCAs are those with the certificate issuer as subject, to allow for key
+/// rollover. One of them must be valid at `now` and its key must verify the certificate
+/// signature. Revocation is not checked.
+///
+/// See ICAO 9303-11 section 5.1.1 and ICAO 9303-12 section 5.
+pub fn check_chain(
+    certificate: &Certificate,
+    trust_store: &TrustStore,
+    now: SystemTime,
+) -> Result<()> {
+    let algorithm = &certificate.signature_algorithm;
+    let digest_algorithm = certificate_digest_algorithm(algorithm)?;
+    let input = certificate.tbs_certificate.to_der()?;
+    let signature = certificate
+        .signature
+        .as_bytes()
+        .ok_or(Error::InvalidSignature)?;
+    let mut result = Err(Error::UntrustedDocumentSigner);
+    for csca in trust_store
+        .certificates()
+        .iter()
+        .filter(|csca| csca.tbs_certificate.subject == certificate.tbs_certificate.issuer)
+    {
+        result = check_certificate_validity(csca, now).and_then(|()| {
+            let public_key = SubjectPublicKeyInfo::from_der(
+                &csca.tbs_certificate.subject_public_key_info.to_der()?,
+            )?;
+            verify_signature_value(algorithm, &digest_algorithm, &public_key, &input, signature)
+        });
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// Check that `now` is within the certificate validity period.
+///
+/// Pass a fixed time to validate as of a historical date, e.g. when the document was read.
+pub fn check_certificate_validity(certificate: &Certificate, now: SystemTime) -> Result<()> {
+    let validity = &certificate.tbs_certificate.validity;
+    if now < validity.not_before.to_system_time() {
+        return Err(Error::CertificateNotYetValid);
+    }
+    if now > validity.not_after.to_system_time() {
+        return Err(Error::CertificateExpired);
+    }
+    Ok(())
+}
+
+/// Check the Document Signer certificate profile constraints.
+///
+/// * Key usage must be present and allow digital signatures.
+/// * Extended key usage must be absent, it is reserved for e.g. Master List Signers.
+/// * If a document type list is present, it must include the document code.
+///
+/// See ICAO 9303-12 section 7.1.1 table 6.
+pub fn check_document_signer_certificate(
+    certificate: &Certificate,
+    document_code: &str,
+) -> Result<()> {
+    let extensions = certificate
+        .tbs_certificate
+        .extensions
+        .as_deref()
+        .unwrap_or_default();
+
+    // Key usage digitalSignature is bit 0.
+    let key_usage = extensions
+        .iter()
+        .find(|ext| ext.extn_id == ID_CE_KEY_USAGE)
+        .ok_or(Error::ConstraintViolation("key usage missing"))?;
+    let key_usage = BitString::from_der(key_usage.extn_value.as_bytes())?;
+    if !matches!(key_usage.raw_bytes().first(), Some(b) if b & 0x80 != 0) {
+        return Err(Error::ConstraintViolation(
+            "key usage excludes digital signature",
+        ));
+    }
+
+    if extensions
+        .iter()
+        .any(|ext| ext.extn_id == ID_CE_EXT_KEY_USAGE)
+    {
+        return Err(Error::ConstraintViolation(
+            "extended key usage not allowed for document signer",
+        ));
+    }
+
+    // Document types are the MRZ document code without fillers.
+    if let Some(ext) = extensions
+        .iter()
+        .find(|ext| ext.extn_id == DocumentTypeListSyntax::EXTENSION_ID)
+    {
+        let list = DocumentTypeListSyntax::from_der(ext.extn_value.as_bytes())?;
+        let document_code = document_code.trim_end_matches('<');
+        if !list
+            .doc_type_list
+            .iter()
+            .any(|doc_type| doc_type.as_str() == document_code)
+        {
+            return Err(Error::ConstraintViolation(
+                "document type not in document signer's list",
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::asn1::DataGroupHash,
+        hex_literal::hex,
+        std::time::Duration,
+        x509_cert::{ext::Extension, serial_number::SerialNumber},
+    };
+
+    const ECONTENT: &[u8] = b"LDS security object";
+
+    /// SignerInfo with `contentType` and `messageDigest` (SHA-256 of [`ECONTENT`]) attributes.
+    const SIGNER_INFO: [u8; 116] = hex!(
+        "3072020103800401020304300B0609608648016503040201A048301506092A864886F70D01090331
+        080606678108010101302F06092A864886F70D0109043122042017524BB508E8640468F142E9B0A7
+        B4D85EB3D619CB3CD4B60EAE28A5FF2E9B06300A06082A8648CE3D0403020404DEADBEEF"
+    );
+
+    #[test]
+    fn test_signed_attributes() {
+        let signer_info = SignerInfo::from_der(&SIGNER_INFO).unwrap();
+        let lds_security_object = Oid::new_unwrap("2.23.136.1.1.1");
+
+        // The signature is over the attributes re-tagged as SET OF, not the eContent.
+        assert_eq!(
+            signer_info_signature_input(&signer_info, ECONTENT).unwrap(),
+            hex!(
+                "3148301506092A864886F70D01090331080606678108010101302F06092A864886F70D010904
+                3122042017524BB508E8640468F142E9B0A7B4D85EB3D619CB3CD4B60EAE28A5FF2E9B06"
+            )
+        );
+
+        check_signer_info_attributes(&signer_info, &lds_security_object, ECONTENT).unwrap();
+        assert!(matches!(
+            check_signer_info_attributes(&signer_info, &lds_security_object, b"tampered"),
+            Err(Error::MessageDigestMismatch)
+        ));
+        assert!(matches!(
+            check_signer_info_attributes(&signer_info, &ID_MESSAGE_DIGEST, ECONTENT),
+            Err(Error::ContentTypeMismatch)
+        ));
+    }
+
+    fn security_object(hashes: &[(u64, usize)]) -> LdsSecurityObject {
+        LdsSecurityObject {
+            version: 0,
+            // SHA-256 without parameters.
+            hash_algorithm: DigestAlgorithmIdentifier::from_der(&hex!(
+                "300B 0609 608648016503040201"
+            ))
+            .unwrap(),
+            data_group_hash_values: hashes
+                .iter()
+                .map(|&(number, length)| DataGroupHash {
+                    data_group_number: number,
+                    hash_value: OctetString::new(vec![0; length]).unwrap(),
+                })
+                .collect(),
+            lds_version_info: None,
+        }
+    }
+
+    /// LDS Security Object with a SHA-384 hash of the TD3 specimen DG1.
+    const LSO_SHA384: [u8; 75] = hex!(
+        "3049020100300B0609608648016503040202303730350201010430F50201DD5CFEBF82D62A17092C041F
+        A9C49F85410D1C4A4EF3BE2E493C514B9F15E428D8785E2F88271661B8664F350D"
+    );
+
+    /// [`SIGNER_INFO`] with the SHA-256 `messageDigest` of [`LSO_SHA384`].
+    const SIGNER_INFO_SHA256: [u8; 116] = hex!(
+        "3072020103800401020304300B0609608648016503040201A048301506092A864886F70D01090331
+        080606678108010101302F06092A864886F70D01090431220420577754169B2772DE38B8F6B7FB16
+        8C0DC231453EE53F002AC63C299153030B0D300A06082A8648CE3D0403020404DEADBEEF"
+    );
+
+    #[test]
+    fn test_distinct_digest_algorithms() {
+        let mut dg1 = hex!("61 5B 5F1F 58").to_vec();
+        dg1.extend_from_slice(b"P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<");
+        dg1.extend_from_slice(b"L898902C36UTO7408122F1204159ZE184226B<<<<<10");
+
+        let lso = LdsSecurityObject::from_der(&LSO_SHA384).unwrap();
+        check_security_object(&lso).unwrap();
+        assert_eq!(lso.hash_algorithm.name(), "SHA2-384");
+        assert_eq!(
+            lso.hash_algorithm.hash_bytes(&dg1),
+            lso.data_group_hash_values[0].hash_value.as_bytes()
+        );
+
+        let signer_info = SignerInfo::from_der(&SIGNER_INFO_SHA256).unwrap();
+        assert_ne!(signer_info.digest_alg.oid, lso.hash_algorithm.oid());
+        check_signer_info_attributes(
+            &signer_info,
+            &Oid::new_unwrap("2.23.136.1.1.1"),
+            &LSO_SHA384,
+        )
+        .unwrap();
+    }
+
+    const SOD_ECDSA: &[u8] = include_bytes!("../tests/documents/specimen-td3-ecdsa/EF_SOD.bin");
+    const SOD_RSA_PSS: &[u8] = include_bytes!("../tests/documents/specimen-td1-rsa-pss/EF_SOD.bin");
+    const CSCA_ECDSA: &[u8] = include_bytes!("../tests/documents/specimen-td3-ecdsa/CSCA.der");
+    const CSCA_RSA: &[u8] = include_bytes!("../tests/documents/specimen-td1-rsa-pss/CSCA.der");
+
+    #[test]
+    fn test_verify_signature() {
+        for bytes in [SOD_ECDSA, SOD_RSA_PSS] {
+            let sod = EfSod::from_der(bytes).unwrap();
+            verify_signature(&sod).unwrap();
+
+            // The signature is the last field of the SignerInfo.
+            let mut tampered = bytes.to_vec();
+            *tampered.last_mut().unwrap() ^= 1;
+            assert!(matches!(
+                verify_signature(&EfSod::from_der(&tampered).unwrap()),
+                Err(Error::InvalidSignature)
+            ));
+
+            let certificate = sod.document_signer_certificate().unwrap();
+            let public_key = SubjectPublicKeyInfo::from_der(
+                &certificate
+                    .tbs_certificate
+                    .subject_public_key_info
+                    .to_der()
+                    .unwrap(),
+            )
+            .unwrap();
+            assert!(matches!(
+                verify_signer_info_signature(sod.signer_info().unwrap(), &public_key, b"tampered"),
+                Err(Error::InvalidSignature)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_check_chain() {
+        let trust_store = |cscas: &[&[u8]]| {
+            let mut trust_store = TrustStore::new();
+            for csca in cscas {
+                trust_store.add_der(csca).unwrap();
+            }
+            trust_store
+        };
+        for (sod, csca, other) in [
+            (SOD_ECDSA, CSCA_ECDSA, CSCA_RSA),
+            (SOD_RSA_PSS, CSCA_RSA, CSCA_ECDSA),
+        ] {
+            let sod = EfSod::from_der(sod).unwrap();
+            let certificate = sod.document_signer_certificate().unwrap();
+            let now = certificate
+                .tbs_certificate
+                .validity
+                .not_before
+                .to_system_time();
+            check_chain(certificate, &trust_store(&[other, csca]), now).unwrap();
+            assert!(matches!(
+                check_chain(certificate, &trust_store(&[other]), now),
+                Err(Error::UntrustedDocumentSigner)
+            ));
+            assert!(matches!(
+                check_chain(certificate, &TrustStore::new(), now),
+                Err(Error::UntrustedDocumentSigner)
+            ));
+
+            // The CSCA must be valid as well.
+            let csca = Certificate::from_der(csca).unwrap();
+            let expired = csca.tbs_certificate.validity.not_after.to_system_time();
+            let mut trusted = TrustStore::new();
+            trusted.add(csca);
+            assert!(matches!(
+                check_chain(certificate, &trusted, expired + Duration::from_secs(1)),
+                Err(Error::CertificateExpired)
+            ));
+
+            let mut tampered = certificate.clone();
+            tampered.tbs_certificate.serial_number = SerialNumber::new(&[0x42]).unwrap();
+            assert!(matches!(
+                check_chain(&tampered, &trusted, now),
+                Err(Error::InvalidSignature)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_check_certificate_validity() {
+        let sod = EfSod::from_der(SOD_ECDSA).unwrap();
+        let certificate = sod.document_signer_certificate().unwrap();
+        let validity = &certificate.tbs_certificate.validity;
+        let not_before = validity.not_before.to_system_time();
+        let not_after = validity.not_after.to_system_time();
+        let second = Duration::from_secs(1);
+
+        check_certificate_validity(certificate, not_before).unwrap();
+        check_certificate_validity(certificate, not_after).unwrap();
+        assert!(matches!(
+            check_certificate_validity(certificate, not_before - second),
+            Err(Error::CertificateNotYetValid)
+        ));
+        assert!(matches!(
+            check_certificate_validity(certificate, not_after + second),
+            Err(Error::CertificateExpired)
+        ));
+        assert!(matches!(
+            check_document_signer(&sod, "P<", not_after + second),
+            Err(Error::CertificateExpired)
+        ));
+    }
+
+    /// The specimen Document Signer with its extensions replaced.
+    fn document_signer(extensions: Vec<Extension>) -> Certificate {
+        let sod = EfSod::from_der(SOD_ECDSA).unwrap();
+        let mut certificate = sod.document_signer_certificate().unwrap().clone();
+        certificate.tbs_certificate.extensions = Some(extensions);
+        certificate
+    }
+
+    fn extension(extn_id: Oid, value: &[u8]) -> Extension {
+        Extension {
+            extn_id,
+            critical: false,
+            extn_value: OctetString::new(value).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_check_document_signer_certificate() {
+        let digital_signature = || extension(ID_CE_KEY_USAGE, &hex!("03020780"));
+        let doc_types = |list: &[u8]| extension(DocumentTypeListSyntax::EXTENSION_ID, list);
+        // Version 0 with document types P and ID.
+        let passport_and_id = hex!("300C 020100 3107 130150 13024944");
+
+        check_document_signer_certificate(&document_signer(vec![digital_signature()]), "P<")
+            .unwrap();
+        let certificate = document_signer(vec![digital_signature(), doc_types(&passport_and_id)]);
+        check_document_signer_certificate(&certificate, "P<").unwrap();
+        check_document_signer_certificate(&certificate, "ID").unwrap();
+
+        // Document types must match exactly, not as a prefix.
+        for document_code in ["PO", "I<", "V<"] {
+            assert!(matches!(
+                check_document_signer_certificate(&certificate, document_code),
+                Err(Error::ConstraintViolation(_))
+            ));
+        }
+
+        for extensions in [
+            vec![],
+            // Key usage keyCertSign only.
+            vec![extension(ID_CE_KEY_USAGE, &hex!("03020204"))],
+            vec![extension(ID_CE_KEY_USAGE, &hex!("030100"))],
+            vec![
+                digital_signature(),
+                extension(ID_CE_EXT_KEY_USAGE, &hex!("300A 06082B06010505070301")),
+            ],
+        ] {
+            assert!(matches!(
+                check_document_signer_certificate(&document_signer(extensions), "P<"),
+                Err(Error::ConstraintViolation(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_check_security_object() {
+        check_security_object(&security_object(&[(1, 32), (2, 32), (14, 32)])).unwrap();
+        for hashes in [
+            &[][..],
+            &[(1, 32), (1, 32)],
+            &[(0, 32)],
+            &[(17, 32)],
+            &[(1, 32), (2, 20)],
+        ] {
+            assert!(matches!(
+                check_security_object(&security_object(hashes)),
+                Err(Error::MalformedSecurityObject(_))
+            ));
+        }
+
+        let mut lso = security_object(&[(1, 32)]);
+        lso.version = 1;
+        assert!(matches!(
+            check_security_object(&lso),
+            Err(Error::MalformedSecurityObject(_))
+        ));
+    }
+}
@@ -6,3 +6,61 @@ macro_rules! ensure_err {
         }
     };
 }
+
+/// Serde helper to encode bytes as base64, use with `#[serde(with = "...")]`.
+#[cfg(feature = "serde")]
+pub mod serde_base64 {
+    use {
+        base64::{engine::general_purpose::STANDARD as BASE64, Engine as _},
+        serde::{Deserialize, Deserializer, Serializer},
+    };
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        BASE64.decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serde helper to encode ASN.1 types as base64 DER, use with `#[serde(with = "...")]`.
+#[cfg(feature = "serde")]
+pub mod serde_der {
+    use {
+        der::{Decode, Encode},
+        serde::{Deserializer, Serializer},
+    };
+
+    pub fn serialize<S: Serializer, T: Encode>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let der = value.to_der().map_err(serde::ser::Error::custom)?;
+        super::serde_base64::serialize(&der, serializer)
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: for<'a> Decode<'a>,
+    {
+        let der = super::serde_base64::deserialize(deserializer)?;
+        T::from_der(&der).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Debug formatting for secrets, printed as `<redacted>` unless the `dangerous` feature is
+/// enabled.
+pub struct Redacted<T>(pub T);
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if cfg!(feature = "dangerous") {
+            self.0.fmt(f)
+        } else {
+            f.write_str("<redacted>")
+        }
+    }
+}
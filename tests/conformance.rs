@@ -0,0 +1,118 @@
+//! Conformance tests against reference eMRTD documents.
+//!
+//! ICAO and BSI publish test documents with known contents, but they are not redistributed
+//! here. Each document is a directory of raw files as dumped by `reader --format raw`, e.g.
+//! `EF_DG1.bin`, `EF_DG2.bin` and `EF_SOD.bin`. An optional `MRZ.txt` holds the expected
+//! MRZ information, as passed to `reader --mrz`, and an optional `CSCA.der` the Document
+//! Signer certificate chains to.
+//!
+//! Documents are looked up in `tests/documents`, or the directory in `ICAO_9303_DOCUMENTS`.
+//! The specimens in `tests/documents` are synthetic, made with OpenSSL: one signed with
+//! ECDSA with explicit brainpoolP256r1 parameters and one with RSASSA-PSS, each under a
+//! CSCA of the same key type. The test fails if no documents are found.
+#![cfg(feature = "passive-auth")]
+
+use {
+    der::Decode,
+    icao_9303_nfc::{
+        asn1::EfSod,
+        emrtd::FileId,
+        lds::{data_group::parse_dg1, parse_data_group, DataGroup, MrzInput, ParseOptions},
+        passive_authentication::{
+            check_chain, check_document_signer_certificate, check_signed_attributes,
+            security_object, verify_signature, TrustStore,
+        },
+    },
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+    },
+};
+
+fn documents() -> Vec<PathBuf> {
+    let root = env::var_os("ICAO_9303_DOCUMENTS").map_or_else(
+        || Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/documents"),
+        PathBuf::from,
+    );
+    let Ok(entries) = fs::read_dir(&root) else {
+        return Vec::new();
+    };
+    let mut documents: Vec<_> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    documents.sort();
+    documents
+}
+
+fn read(document: &Path, file: FileId) -> Option<Vec<u8>> {
+    fs::read(document.join(format!("{}.bin", file.to_string().replace(['.', '/'], "_")))).ok()
+}
+
+fn check_document(document: &Path) {
+    let strict = ParseOptions { strict: true };
+
+    // MRZ fields, with check digits validated.
+    let dg1 = read(document, FileId::Dg1).expect("EF.DG1 missing");
+    let mrz = parse_dg1(&dg1).unwrap();
+    let input = MrzInput::from_mrz(&mrz).unwrap();
+    if let Ok(expected) = fs::read_to_string(document.join("MRZ.txt")) {
+        assert_eq!(input.mrz_information(), expected.trim());
+    }
+
+    // At least one facial image.
+    let dg2 = read(document, FileId::Dg2).expect("EF.DG2 missing");
+    let DataGroup::Face(faces) = parse_data_group(2, &dg2, strict).unwrap() else {
+        panic!("EF.DG2 did not parse as facial images");
+    };
+    assert!(!faces.is_empty(), "EF.DG2 has no facial images");
+
+    // EF.SOD structure, signed attributes, Document Signer profile and signature.
+    let sod = read(document, FileId::Sod).expect("EF.SOD missing");
+    let sod = EfSod::from_der(&sod).unwrap();
+    check_signed_attributes(&sod).unwrap();
+    let lso = security_object(&sod).unwrap();
+    let certificate = sod.document_signer_certificate().unwrap();
+    check_document_signer_certificate(certificate, &mrz[..2]).unwrap();
+    match verify_signature(&sod) {
+        Err(e) if e.is_unsupported() => eprintln!("Signature not verified: {e}"),
+        result => result.unwrap(),
+    }
+    if let Ok(csca) = fs::read(document.join("CSCA.der")) {
+        let mut trust_store = TrustStore::new();
+        trust_store.add_der(&csca).unwrap();
+        let now = certificate
+            .tbs_certificate
+            .validity
+            .not_before
+            .to_system_time();
+        check_chain(certificate, &trust_store, now).unwrap();
+    }
+
+    // Every data group in the dump and listed in EF.SOD matches its hash. Dumps lack data
+    // groups that require Extended Access Control, e.g. EF.DG3.
+    for entry in &lso.data_group_hash_values {
+        let number = entry.data_group_number as u8;
+        let file = FileId::data_group(number).unwrap();
+        let Some(data) = read(document, file) else {
+            eprintln!("Skipping {file}, not in the dump");
+            continue;
+        };
+        assert_eq!(
+            lso.hash_algorithm.hash_bytes(&data),
+            entry.hash_value.as_bytes(),
+            "{file} does not match EF.SOD"
+        );
+        parse_data_group(number, &data, ParseOptions::default()).unwrap();
+    }
+}
+
+#[test]
+fn test_reference_documents() {
+    let documents = documents();
+    assert!(!documents.is_empty(), "no reference documents found");
+    for document in documents {
+        eprintln!("Checking {}", document.display());
+        check_document(&document);
+    }
+}
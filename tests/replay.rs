@@ -0,0 +1,132 @@
+//! End to end tests replaying recorded APDU transcripts.
+
+use {
+    hex_literal::hex,
+    icao_9303_nfc::{
+        asn1::security_info::SymmetricCipher,
+        emrtd::{Emrtd, SessionProtocol, State},
+        iso7816::ResponseApdu,
+        nfc::{transcript::Replay, CardType, NfcReader},
+    },
+    rand::{CryptoRng, RngCore},
+    sha2::{Digest, Sha256},
+    std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Returns fixed bytes, one per `next_u32`, which is how [`rand::Rng::gen`] samples `u8`.
+struct FixedRng(VecDeque<u8>);
+
+impl RngCore for FixedRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.pop_front().expect("FixedRng exhausted") as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.iter_mut()
+            .for_each(|byte| *byte = self.next_u32() as u8);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for FixedRng {}
+
+/// A replay that can be checked for completion after moving it into an [`Emrtd`].
+#[derive(Clone, Default)]
+struct SharedReplay(Arc<Mutex<Replay>>);
+
+impl NfcReader for SharedReplay {
+    fn connect(&mut self) -> anyhow::Result<Option<CardType>> {
+        self.0.lock().unwrap().connect()
+    }
+
+    fn disconnect(&mut self) -> anyhow::Result<()> {
+        self.0.lock().unwrap().disconnect()
+    }
+
+    fn send_apdu(&mut self, apdu: &[u8]) -> anyhow::Result<ResponseApdu> {
+        self.0.lock().unwrap().send_apdu(apdu)
+    }
+}
+
+#[test]
+fn test_replay_icao_appendix_d() {
+    let transcript = include_str!("transcripts/icao_9303_11_appendix_d.txt");
+    let replay = Replay::parse(transcript).unwrap();
+    let mut card = Emrtd::new(Box::new(replay));
+
+    // RND.IFD followed by K.IFD.
+    let mut rng = FixedRng(
+        [
+            &hex!("781723860C06C226")[..],
+            &hex!("0B795240CB7049B01C19B33E32804F0B"),
+        ]
+        .concat()
+        .into(),
+    );
+    card.basic_access_control(&mut rng, "L898902C<369080619406236")
+        .unwrap();
+    assert_eq!(card.state(), State::Session);
+    let session = card.session_info().unwrap();
+    assert_eq!(session.protocol, SessionProtocol::Bac);
+    assert_eq!(session.cipher, SymmetricCipher::Tdes);
+    assert_eq!((session.curve, session.key_bits), (None, None));
+
+    // Read EF.COM in two parts, as in the worked example.
+    card.select_elementary_file(0x011E).unwrap();
+    let mut com = card.send_apdu(&hex!("00 B0 00 00 04")).unwrap().data;
+    let rest = card.send_apdu(&hex!("00 B0 00 04 12")).unwrap().data;
+    com.extend(rest);
+
+    assert_eq!(com, hex!("60145F0104303130365F36063034303030305C026175"));
+    assert_eq!(
+        Sha256::digest(&com)[..],
+        hex!("CBD8BB2ABE3BD7B531337CCF0D121079BF1BC2914A21FAD1230170B719FD7095")
+    );
+}
+
+#[test]
+fn test_replay_read_document() {
+    let transcript = include_str!("transcripts/specimen_read_document.txt");
+    let replay = SharedReplay(Arc::new(Mutex::new(Replay::parse(transcript).unwrap())));
+    let mut card = Emrtd::new(Box::new(replay.clone()));
+
+    // RND.IFD followed by K.IFD.
+    let mut rng = FixedRng(
+        [
+            &hex!("781723860C06C226")[..],
+            &hex!("0B795240CB7049B01C19B33E32804F0B"),
+        ]
+        .concat()
+        .into(),
+    );
+    let document = card
+        .read_document(&mut rng, "L898902C3674081221204159")
+        .unwrap();
+    assert!(replay.0.lock().unwrap().is_done());
+    assert_eq!(card.state(), State::Session);
+
+    assert_eq!(
+        document.mrz,
+        "P<UTOERIKSSON<<ANNA<MARIA<<<<<<<<<<<<<<<<<<<L898902C36UTO7408122F1204159ZE184226B<<<<<10"
+    );
+    assert!(!document.report.pace_supported);
+    assert_eq!(document.report.data_groups, [1, 2]);
+    assert!(document.sod.is_some());
+
+    // The DG1 hash listed in EF.SOD.
+    assert_eq!(
+        Sha256::digest(&document.data_groups[&1])[..],
+        hex!("432BC07D1C637793F4D77E0B756865F7AEC3756F98D6EC6EB767EDA371904651")
+    );
+}